@@ -1,2 +1,2 @@
 #!/bin/bash
-RUST_BACKTRACE=1 cargo run && ./debug_link.sh && ./tmp/prog
+RUST_BACKTRACE=1 cargo run -- run fibonacci.fig
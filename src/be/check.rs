@@ -0,0 +1,116 @@
+//! Native Code Support
+//!
+//! Not everything that type checks can be compiled to native code yet, even
+//! though the evaluator runs all of it. This finds what can't be, so it can be
+//! reported before code generation gets to it.
+
+use crate::fe::{
+    ast::{AstNode, AstNodeKind, BinOp, Pattern, Reference, Statement, UnOp},
+    codes,
+    symbols::{PrimitiveType, SymbolTable, TypeProps},
+    CompileError
+};
+
+/// Reports everything in checked `stmts` that can't be compiled natively.
+pub fn check<'a>(symbols: &SymbolTable<'a>, stmts: &[Statement<'a>]) -> Vec<CompileError<'a>> {
+    let mut errors = vec![];
+    for stmt in stmts {
+        statement(symbols, stmt, &mut errors);
+    }
+    errors
+}
+
+fn statement<'a>(symbols: &SymbolTable<'a>, stmt: &Statement<'a>, errors: &mut Vec<CompileError<'a>>) {
+    match stmt {
+        // tests aren't part of the program
+        Statement::Function { .. } if stmt.is_test() => {},
+        Statement::Declare { value: e, .. }
+            | Statement::Expression(e)
+            | Statement::Return(e)
+            | Statement::Out(e)
+            | Statement::Break { with: Some(e), .. }
+            | Statement::Function { body: e, .. } => node(symbols, e, errors),
+        Statement::Import { .. } | Statement::Continue { .. } | Statement::Break { .. }
+            | Statement::Struct { .. } | Statement::Error => {}
+    }
+}
+
+fn node<'a>(symbols: &SymbolTable<'a>, ast: &AstNode<'a>, errors: &mut Vec<CompileError<'a>>) {
+    if let Some(TypeProps::Float { .. }) = ast.type_data.map(|t| symbols.type_props(t)) {
+        // nothing inside can be compiled without it either
+        return errors.push(unsupported(ast, "floats"));
+    }
+    match &*ast.kind {
+        AstNodeKind::BinOp { a, b, op } => {
+            // native strings can't be compared by their contents yet
+            if matches!(**op, BinOp::Eq | BinOp::NotEq) && is_string(symbols, a) {
+                errors.push(unsupported(ast, "comparing strings"));
+            }
+            node(symbols, a, errors);
+            node(symbols, b, errors);
+        },
+        AstNodeKind::UnOp { op, target } => match **op {
+            UnOp::Try => errors.push(unsupported(ast, "`?`")),
+            UnOp::Negate | UnOp::Not => node(symbols, target, errors)
+        },
+        AstNodeKind::If { condition, body, else_body } => {
+            node(symbols, condition, errors);
+            node(symbols, body, errors);
+            if let Some(eb) = else_body {
+                node(symbols, eb, errors);
+            }
+        },
+        AstNodeKind::Loop { body, .. } => node(symbols, body, errors),
+        AstNodeKind::While { condition, body, .. } => {
+            node(symbols, condition, errors);
+            node(symbols, body, errors);
+        },
+        AstNodeKind::For { from, to, body, .. } => {
+            node(symbols, from, errors);
+            node(symbols, to, errors);
+            node(symbols, body, errors);
+        },
+        AstNodeKind::Block { stmts } => {
+            for s in stmts {
+                statement(symbols, s, errors);
+            }
+        },
+        AstNodeKind::Call { callee, args } => {
+            if let AstNodeKind::Reference(Reference::Builtin(b)) = &*callee.kind {
+                errors.push(unsupported(ast, format!("`{}` outside of tests", b.name())));
+            }
+            for a in args {
+                node(symbols, a, errors);
+            }
+        },
+        AstNodeKind::Interpolation { .. } => errors.push(unsupported(ast, "string interpolation")),
+        AstNodeKind::Match { value, arms } => {
+            node(symbols, value, errors);
+            for arm in arms {
+                if let Pattern::Value(p) = &arm.pattern {
+                    if is_string(symbols, p) {
+                        errors.push(unsupported(p, "matching strings"));
+                    }
+                }
+                node(symbols, &arm.body, errors);
+            }
+        },
+        AstNodeKind::StructLiteral { fields, .. } => {
+            for f in fields {
+                node(symbols, &f.value, errors);
+            }
+        },
+        AstNodeKind::Field { target, .. } => node(symbols, target, errors),
+        AstNodeKind::Reference(..) | AstNodeKind::Value(..) | AstNodeKind::Error => {}
+    }
+}
+
+fn is_string(symbols: &SymbolTable, ast: &AstNode) -> bool {
+    ast.type_data == Some(symbols.get_primitive(PrimitiveType::String))
+}
+
+fn unsupported<'a>(at: &AstNode<'a>, what: impl std::fmt::Display) -> CompileError<'a> {
+    CompileError::error(at, format!("{} can't be compiled to native code yet", what))
+        .with_code(codes::NOT_NATIVE)
+        .with_label(at, "`fig test` and the REPL can run this, but `fig build` can't")
+}
@@ -1,6 +1,6 @@
 use crate::fe::ast::ConstantValue;
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct ConstTable<'a> {
     pub consts: Vec<ConstantValue<'a>>,
     pub const_names: Vec<String>,
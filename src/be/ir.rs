@@ -1,5 +1,7 @@
 //! HLIR types
 
+use std::io::{self, Write};
+
 pub type Register = u8;
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -24,7 +26,7 @@ impl IrOperand {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum IrOpKind {
     /// x = (CONST)
     LoadC(usize),
@@ -38,16 +40,41 @@ pub enum IrOpKind {
     Mul,
     /// x = (op1/op2)
     Div,
+    /// x = (op1/op2), both unsigned
+    UDiv,
     /// x = (op1 == op2)
     Eq,
     /// x = (op1 != op2)
     NotEq,
+    /// x = (op1 < op2)
+    Lt,
+    /// x = (op1 <= op2)
+    LtEq,
+    /// x = (op1 < op2), both unsigned
+    ULt,
+    /// x = (op1 <= op2), both unsigned
+    ULtEq,
+    /// x = (op1 cut down to #(bits) bits, then sign extended if #(signed))
+    Wrap(u8, bool),
     /// x = (-op1)
     Neg,
     /// x = (!op1)
     Not,
     /// () = ret op1
     Ret,
+    /// x = (param #n), at the start of a function
+    Param(usize),
+    /// x = call function #(id) with (op1, op2, ...)
+    Call(usize),
+    /// (starts the code of function #(id), which runs until the next one)
+    Function(usize),
+    /// x = (the address of #(size) bytes of the function's frame, which are
+    /// the same each time this runs)
+    Alloc(usize),
+    /// x = (the word #(offset) bytes past the address op1)
+    Load(usize),
+    /// (the word #(offset) bytes past the address op1) = op2
+    Store(usize),
     
     /// (emits marker #(id))
     DefMarker(usize),
@@ -57,14 +84,14 @@ pub enum IrOpKind {
     If(usize, usize)
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct IrOp {
     pub kind: IrOpKind,
     pub ops: Vec<IrOperand>,
     pub result_into: Option<IrOperand>
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Default)]
 pub struct IrBlock {
     pub ops: Vec<IrOp>
 }
@@ -74,9 +101,9 @@ impl IrBlock {
         Self { ops: vec![] }
     }
     
-    pub fn print(&self) {
+    pub fn print(&self, out: &mut impl Write) -> io::Result<()> {
         for instr in &self.ops {
-            println!("{} = {:?} {}",
+            writeln!(out, "{} = {:?} {}",
                 instr.result_into
                     .clone()
                     .map(|f| format!("{:?}", f))
@@ -87,7 +114,8 @@ impl IrBlock {
                     .map(|f| format!("{:?}", f))
                     .collect::<Vec<String>>()
                     .join(", ")
-            )
+            )?;
         }
+        Ok(())
     }
 }
@@ -1,20 +1,29 @@
 //! The IR Generator
 
-use crate::fe::{ast::{AstNode, AstNodeKind, BinOp, UnOp, Statement}, symbols::SymbolTable, item::Item};
+use std::mem;
 
-use super::{ir::*, consts::ConstTable, CompUnit};
+use crate::fe::{ast::{AstNode, AstNodeKind, BinOp, UnOp, Statement, Reference, ConstantValue, CompInt, Pattern, MaybeTyped}, symbols::{SymbolTable, Symbol, TypeProps}, item::Item, Sp};
+
+use super::{ir::*, consts::ConstTable, layout, CompUnit};
 
 pub struct IrContext {
+    /// The symbol of the loop's label, if it has one
+    label: Option<usize>,
     break_to: Option<usize>,
     break_out: Option<IrOperand>,
     continue_to: Option<usize>
 }
 
 /// generates three address code
+#[derive(Default)]
 pub struct IrGen {
     next_temp: usize,
     next_marker: usize,
-    context: Vec<IrContext>
+    context: Vec<IrContext>,
+    /// Code for the functions we've come across, which goes after the program
+    functions: Vec<IrOp>,
+    /// Where the function we're in puts the struct it gives back, and its size
+    ret_slot: Option<(IrOperand, usize)>
 }
 
 impl IrGen {
@@ -22,7 +31,9 @@ impl IrGen {
         Self {
             next_temp: 0,
             next_marker: 0,
-            context: vec![]
+            context: vec![],
+            functions: vec![],
+            ret_slot: None
         }
     }
 
@@ -33,10 +44,79 @@ impl IrGen {
         target: &mut IrBlock
     ) {
         // TODO: look for main function instead
-        let Item::Function { code, .. } = unit.items.swap_remove(0) else { unreachable!() };
-        let consts = &mut unit.consts;
-        let out = &self.allocate_temp();
-        self.gen_block_code(consts, sym_table, target, code, out);
+        let CompUnit { items, consts, .. } = unit;
+        let Some(Item::Function { code, .. }) = items.first() else { unreachable!() };
+        let out = self.allocate_temp();
+        self.gen_block_code(consts, sym_table, target, code, &out);
+        // running off the end of the program returns from it
+        target.ops.push(IrOp {
+            kind: IrOpKind::Ret,
+            ops: vec![out],
+            result_into: None
+        });
+        target.ops.append(&mut self.functions);
+    }
+
+    /// Generates the code for a function, which is put aside to go after
+    /// the program.
+    fn gen_function<'a>(
+        &mut self,
+        consts: &mut ConstTable<'a>,
+        sym_table: &SymbolTable<'a>,
+        function: &Statement<'a>
+    ) {
+        let Statement::Function { id: Some(id), params, returns, body, .. } = function else { unreachable!() };
+        let mut code = IrBlock::new();
+        code.ops.push(IrOp {
+            kind: IrOpKind::Function(*id),
+            ops: vec![],
+            result_into: None
+        });
+        for (i, p) in params.iter().enumerate() {
+            let var = IrOperand::Reference(p.id.data.clone().unwrap_resolved());
+            let MaybeTyped::TypeResolved(t) = p.with_type else { unreachable!() };
+            if layout::is_struct(sym_table, t) {
+                // the caller's struct is copied, so changing it here doesn't
+                // change it there
+                let arg = self.allocate_temp();
+                code.ops.push(IrOp {
+                    kind: IrOpKind::Param(i),
+                    ops: vec![],
+                    result_into: Some(arg.clone())
+                });
+                self.bind(sym_table, &mut code, var, arg, Some(t));
+            } else {
+                code.ops.push(IrOp {
+                    kind: IrOpKind::Param(i),
+                    ops: vec![],
+                    result_into: Some(var)
+                });
+            }
+        }
+
+        // structs are given back by copying them to where the caller passed
+        // as the last argument
+        let ret_slot = match returns {
+            MaybeTyped::TypeResolved(t) if layout::is_struct(sym_table, *t) => {
+                let slot = self.allocate_temp();
+                code.ops.push(IrOp {
+                    kind: IrOpKind::Param(params.len()),
+                    ops: vec![],
+                    result_into: Some(slot.clone())
+                });
+                Some((slot, layout::size_of(sym_table, *t)))
+            },
+            _ => None
+        };
+
+        // loops around the function don't go into it
+        let outside = mem::take(&mut self.context);
+        let outer_slot = mem::replace(&mut self.ret_slot, ret_slot);
+        let out = self.gen_code(consts, sym_table, &mut code, body);
+        self.ret(&mut code, out);
+        self.context = outside;
+        self.ret_slot = outer_slot;
+        self.functions.append(&mut code.ops);
     }
 
     fn gen_block_code<'a>(
@@ -44,7 +124,7 @@ impl IrGen {
         consts: &mut ConstTable<'a>,
         sym_table: &SymbolTable<'a>,
         target: &mut IrBlock,
-        stmts: Vec<Statement<'a>>,
+        stmts: &[Statement<'a>],
         uni_out: &IrOperand
     ) {
         let exit = self.allocate_new_marker();
@@ -53,11 +133,7 @@ impl IrGen {
                 Statement::Expression(ast) => { self.gen_code(consts, sym_table, target, ast); },
                 Statement::Return(ast) => {
                     let out = self.gen_code(consts, sym_table, target, ast);
-                    target.ops.push(IrOp {
-                        kind: IrOpKind::Ret,
-                        ops: vec![out],
-                        result_into: None
-                    });
+                    self.ret(target, out);
                 },
                 Statement::Declare {
                     id,
@@ -65,11 +141,8 @@ impl IrGen {
                     ..
                 } => {
                     let out = self.gen_code(consts, sym_table, target, value);
-                    target.ops.push(IrOp {
-                        kind: IrOpKind::Cpy,
-                        ops: vec![out],
-                        result_into: Some(IrOperand::Reference(id.unwrap_resolved()))
-                    });
+                    let var = IrOperand::Reference(id.data.clone().unwrap_resolved());
+                    self.bind(sym_table, target, var, out, value.type_data);
                 },
                 Statement::Out(val) => {
                     let out = self.gen_code(consts, sym_table, target, val);
@@ -84,22 +157,16 @@ impl IrGen {
                         result_into: None
                     })
                 },
-                Statement::Continue { label } => {
-                    if label.is_some() {
-                        todo!("continue labels");
-                    }
-                    let ctx = self.context.last().unwrap();
+                Statement::Continue { label, .. } => {
+                    let ctx = self.jump_context(label);
                     target.ops.push(IrOp {
                         kind: IrOpKind::Jmp(ctx.continue_to.unwrap()),
                         ops: vec![],
                         result_into: None
                     })
                 }
-                Statement::Break { label, with } => {
-                    if label.is_some() {
-                        todo!("break labels")
-                    }
-                    let ctx = self.context.last().unwrap();
+                Statement::Break { label, with, .. } => {
+                    let ctx = self.jump_context(label);
                     let break_to = ctx.break_to.unwrap();
                     
                     if let Some(with) = with {
@@ -119,7 +186,11 @@ impl IrGen {
                         result_into: None
                     });
                 }
-                _ => { todo!() }
+                // tests aren't part of the program
+                Statement::Function { .. } if stmt.is_test() => {},
+                Statement::Function { .. } => self.gen_function(consts, sym_table, stmt),
+                Statement::Struct { .. } => {},
+                Statement::Import { .. } | Statement::Error => unreachable!("checked code has neither imports nor errors")
             };
         };
         self.push_marker(target, exit);
@@ -131,12 +202,11 @@ impl IrGen {
         consts: &mut ConstTable<'a>,
         sym_table: &SymbolTable<'a>,
         target: &mut IrBlock,
-        ast: AstNode<'a>
+        ast: &AstNode<'a>
     ) -> IrOperand {
-        let n = *ast.data.kind;
-        match n {
+        match &*ast.kind {
             AstNodeKind::Value(v) => {
-                let constant = consts.add(v);
+                let constant = consts.add(v.clone());
                 let out_id = self.allocate_temp();
                 target.ops.push(IrOp {
                     ops: vec![],
@@ -145,33 +215,137 @@ impl IrGen {
                 });
                 out_id
             },
-            AstNodeKind::BinOp { a, b, op } => {
-                let a = self.gen_code(consts, sym_table, target, a);
+            AstNodeKind::BinOp { a, b, op } if op.is_assignment() => {
+                if let AstNodeKind::Field { target: owner, name } = &*a.kind {
+                    // `a.b = c` and `a.b += c` change the struct in place
+                    let base = self.gen_code(consts, sym_table, target, owner);
+                    let (offset, ty) = layout::field(sym_table, owner.type_data.unwrap(), name.data);
+                    let value = if **op == BinOp::Assign {
+                        self.gen_code(consts, sym_table, target, b)
+                    } else {
+                        let current = self.field_at(consts, sym_table, target, base.clone(), offset, ty);
+                        let b = self.gen_code(consts, sym_table, target, b);
+                        self.arithmetic(consts, sym_table, target, op, [current, b], Some(ty))
+                    };
+                    self.store(sym_table, target, &base, offset, value.clone(), ty);
+                    return value;
+                }
+
+                let var = self.gen_code(consts, sym_table, target, a);
                 let b = self.gen_code(consts, sym_table, target, b);
+                match a.type_data.filter(|t| layout::is_struct(sym_table, *t)) {
+                    // the variable keeps its own place, and takes a copy
+                    Some(t) => self.copy(target, &var, 0, &b, layout::size_of(sym_table, t)),
+                    None if **op == BinOp::Assign => target.ops.push(IrOp {
+                        kind: IrOpKind::Cpy,
+                        ops: vec![b],
+                        result_into: Some(var.clone())
+                    }),
+                    // `a += b` is `a = a + b`
+                    None => {
+                        let out_id = self.arithmetic(consts, sym_table, target, op, [var.clone(), b], a.type_data);
+                        target.ops.push(IrOp {
+                            kind: IrOpKind::Cpy,
+                            ops: vec![out_id],
+                            result_into: Some(var.clone())
+                        });
+                    }
+                }
+                var
+            },
+            AstNodeKind::BinOp { a, b, op } if matches!(**op, BinOp::And | BinOp::Or) => {
+                // the right side only runs when the left doesn't decide it
                 let out_id = self.allocate_temp();
-
-                // special case
-                if *op == BinOp::Assign {
+                let a = self.gen_code(consts, sym_table, target, a);
+                target.ops.push(IrOp {
+                    kind: IrOpKind::Cpy,
+                    ops: vec![a],
+                    result_into: Some(out_id.clone())
+                });
+                let rhs_mark = self.allocate_new_marker();
+                let end_mark = self.allocate_new_marker();
+                target.ops.push(IrOp {
+                    kind: match **op {
+                        BinOp::And => IrOpKind::If(rhs_mark, end_mark),
+                        _ => IrOpKind::If(end_mark, rhs_mark)
+                    },
+                    ops: vec![out_id.clone()],
+                    result_into: None
+                });
+                self.push_marker(target, rhs_mark);
+                let b = self.gen_code(consts, sym_table, target, b);
+                target.ops.push(IrOp {
+                    kind: IrOpKind::Cpy,
+                    ops: vec![b],
+                    result_into: Some(out_id.clone())
+                });
+                self.push_marker(target, end_mark);
+                out_id
+            },
+            AstNodeKind::BinOp { a: a_node, b, op } if matches!(**op, BinOp::Eq | BinOp::NotEq)
+                && a_node.type_data.is_some_and(|t| layout::is_struct(sym_table, t)) => {
+                // structs are equal when all of their words are
+                let size = layout::size_of(sym_table, a_node.type_data.unwrap());
+                let a = self.gen_code(consts, sym_table, target, a_node);
+                let b = self.gen_code(consts, sym_table, target, b);
+                let all = self.allocate_temp();
+                target.ops.push(IrOp {
+                    kind: IrOpKind::LoadC(consts.add(ConstantValue::Bool(true))),
+                    ops: vec![],
+                    result_into: Some(all.clone())
+                });
+                for offset in (0..size).step_by(layout::WORD) {
+                    let wa = self.load(target, &a, offset);
+                    let wb = self.load(target, &b, offset);
+                    let eq = self.allocate_temp();
+                    target.ops.push(IrOp {
+                        kind: IrOpKind::Eq,
+                        ops: vec![wa, wb],
+                        result_into: Some(eq.clone())
+                    });
                     target.ops.push(IrOp {
-                        kind: Cpy,
-                        ops: vec![b.clone()],
-                        result_into: Some(a.clone())
+                        kind: IrOpKind::Mul,
+                        ops: vec![all.clone(), eq],
+                        result_into: Some(all.clone())
                     });
-                    return a;
                 }
+                if **op == BinOp::Eq {
+                    return all;
+                }
+                let out_id = self.allocate_temp();
+                target.ops.push(IrOp {
+                    kind: IrOpKind::Not,
+                    ops: vec![all],
+                    result_into: Some(out_id.clone())
+                });
+                out_id
+            },
+            AstNodeKind::BinOp { a: a_node, b, op } => {
+                let a = self.gen_code(consts, sym_table, target, a_node);
+                let b = self.gen_code(consts, sym_table, target, b);
+                if matches!(**op, BinOp::Add | BinOp::Sub | BinOp::Mul | BinOp::Div | BinOp::Mod | BinOp::Pow) {
+                    return self.arithmetic(consts, sym_table, target, op, [a, b], ast.type_data);
+                }
+                let unsigned = is_unsigned(sym_table, a_node.type_data);
+                let out_id = self.allocate_temp();
 
                 use IrOpKind::*;
+                // `a > b` is `b < a`
+                let ops = match **op {
+                    BinOp::Gt | BinOp::GtEq => vec![b, a],
+                    _ => vec![a, b]
+                };
                 target.ops.push(IrOp {
-                    kind: match *op {
-                        BinOp::Add => Add,
-                        BinOp::Sub => Sub,
-                        BinOp::Mul => Mul,
-                        BinOp::Div => Div,
+                    kind: match **op {
                         BinOp::Eq => Eq,
                         BinOp::NotEq => NotEq,
-                        _ => todo!("many primitive binops are missing at the moment!")
+                        BinOp::Lt | BinOp::Gt if unsigned => ULt,
+                        BinOp::LtEq | BinOp::GtEq if unsigned => ULtEq,
+                        BinOp::Lt | BinOp::Gt => Lt,
+                        BinOp::LtEq | BinOp::GtEq => LtEq,
+                        _ => unreachable!("{:?} is handled above", op)
                     },
-                    ops: vec![a, b],
+                    ops,
                     result_into: Some(out_id.clone())
                 });
 
@@ -183,19 +357,24 @@ impl IrGen {
 
                 use IrOpKind::*;
                 target.ops.push(IrOp {
-                    kind: match *op {
+                    kind: match **op {
                         UnOp::Negate => Neg,
                         UnOp::Not => Not,
-                        _ => todo!("the other primitive unops aren't working yet!")
+                        UnOp::Try => unreachable!("`?` is reported before code generation")
                     },
                     ops: vec![target_done],
                     result_into: Some(out_id.clone())
                 });
 
-                out_id
+                self.wrap(sym_table, target, out_id, ast.type_data)
             },
             AstNodeKind::Reference(r) => {
-                IrOperand::Reference(r.unwrap_resolved())
+                let id = r.clone().unwrap_resolved();
+                if let Some(Symbol::Type(..)) = sym_table.tbl.get(&id).map(|s| &s.data) {
+                    // a unit struct has nothing in it, but still needs an address
+                    return self.alloc(target, 0);
+                }
+                IrOperand::Reference(id)
             }
             AstNodeKind::If { condition, body, else_body } => {
                 let cond = self.gen_code(consts, sym_table, target, condition);
@@ -246,11 +425,12 @@ impl IrGen {
                 self.gen_block_code(consts, sym_table, target, stmts, &o);
                 o
             },
-            AstNodeKind::Loop { body } => {
+            AstNodeKind::Loop { label, body } => {
                 let start_mark = self.push_new_marker(target);
                 let end_mark = self.allocate_new_marker();
                 let o = self.allocate_temp();
                 self.context.push(IrContext {
+                    label: label.as_ref().map(|l| l.data.clone().unwrap_resolved()),
                     continue_to: Some(start_mark),
                     break_out: Some(o.clone()),
                     break_to: Some(end_mark)
@@ -265,10 +445,420 @@ impl IrGen {
                 self.context.pop();
                 o
             },
+            AstNodeKind::While { label, condition, body } => {
+                let start_mark = self.push_new_marker(target);
+                let body_mark = self.allocate_new_marker();
+                let end_mark = self.allocate_new_marker();
+                let o = self.allocate_temp();
+                let cond = self.gen_code(consts, sym_table, target, condition);
+                target.ops.push(IrOp {
+                    kind: IrOpKind::If(body_mark, end_mark),
+                    ops: vec![cond],
+                    result_into: None
+                });
+                self.push_marker(target, body_mark);
+                self.context.push(IrContext {
+                    label: label.as_ref().map(|l| l.data.clone().unwrap_resolved()),
+                    continue_to: Some(start_mark),
+                    break_out: Some(o.clone()),
+                    break_to: Some(end_mark)
+                });
+                self.gen_code(consts, sym_table, target, body);
+                target.ops.push(IrOp {
+                    kind: IrOpKind::Jmp(start_mark),
+                    ops: vec![],
+                    result_into: None
+                });
+                self.push_marker(target, end_mark);
+                self.context.pop();
+                o
+            },
+            AstNodeKind::For { label, var, from, to, inclusive, body } => {
+                let var = IrOperand::Reference(var.data.clone().unwrap_resolved());
+                let from = self.gen_code(consts, sym_table, target, from);
+                target.ops.push(IrOp {
+                    kind: IrOpKind::Cpy,
+                    ops: vec![from],
+                    result_into: Some(var.clone())
+                });
+                // the end is worked out once, even if what it came from changes
+                let to = self.gen_code(consts, sym_table, target, to);
+                let end = self.allocate_temp();
+                target.ops.push(IrOp {
+                    kind: IrOpKind::Cpy,
+                    ops: vec![to],
+                    result_into: Some(end.clone())
+                });
+                let one = self.allocate_temp();
+                target.ops.push(IrOp {
+                    kind: IrOpKind::LoadC(consts.add(ConstantValue::CompInt(CompInt::I64(1)))),
+                    ops: vec![],
+                    result_into: Some(one.clone())
+                });
+
+                let start_mark = self.push_new_marker(target);
+                let body_mark = self.allocate_new_marker();
+                let next_mark = self.allocate_new_marker();
+                let end_mark = self.allocate_new_marker();
+                let o = self.allocate_temp();
+                let cond = self.allocate_temp();
+                target.ops.push(IrOp {
+                    kind: if *inclusive { IrOpKind::LtEq } else { IrOpKind::Lt },
+                    ops: vec![var.clone(), end],
+                    result_into: Some(cond.clone())
+                });
+                target.ops.push(IrOp {
+                    kind: IrOpKind::If(body_mark, end_mark),
+                    ops: vec![cond],
+                    result_into: None
+                });
+                self.push_marker(target, body_mark);
+                self.context.push(IrContext {
+                    label: label.as_ref().map(|l| l.data.clone().unwrap_resolved()),
+                    continue_to: Some(next_mark),
+                    break_out: Some(o.clone()),
+                    break_to: Some(end_mark)
+                });
+                self.gen_code(consts, sym_table, target, body);
+                self.push_marker(target, next_mark);
+                target.ops.push(IrOp {
+                    kind: IrOpKind::Add,
+                    ops: vec![var.clone(), one],
+                    result_into: Some(var)
+                });
+                target.ops.push(IrOp {
+                    kind: IrOpKind::Jmp(start_mark),
+                    ops: vec![],
+                    result_into: None
+                });
+                self.push_marker(target, end_mark);
+                self.context.pop();
+                o
+            },
+            AstNodeKind::Call { callee, args } => {
+                let AstNodeKind::Reference(Reference::Resolved(id)) = &*callee.kind else {
+                    unreachable!("builtins are reported before code generation")
+                };
+                let mut args: Vec<_> = args.iter()
+                    .map(|a| self.gen_code(consts, sym_table, target, a))
+                    .collect();
+                if let Some(t) = ast.type_data.filter(|t| layout::is_struct(sym_table, *t)) {
+                    // somewhere for the function to put the struct it gives back
+                    let slot = self.alloc(target, layout::size_of(sym_table, t));
+                    args.push(slot);
+                }
+                let out_id = self.allocate_temp();
+                target.ops.push(IrOp {
+                    kind: IrOpKind::Call(*id),
+                    ops: args,
+                    result_into: Some(out_id.clone())
+                });
+                out_id
+            },
+            AstNodeKind::Match { value, arms } => {
+                // each arm checks its pattern, and goes on to the next if it doesn't match
+                let v = self.gen_code(consts, sym_table, target, value);
+                let out = self.allocate_temp();
+                let end_mark = self.allocate_new_marker();
+                for arm in arms {
+                    let next_mark = self.allocate_new_marker();
+                    match &arm.pattern {
+                        Pattern::Value(p) => {
+                            let p = self.gen_code(consts, sym_table, target, p);
+                            let arm_mark = self.allocate_new_marker();
+                            let eq = self.allocate_temp();
+                            target.ops.push(IrOp {
+                                kind: IrOpKind::Eq,
+                                ops: vec![v.clone(), p],
+                                result_into: Some(eq.clone())
+                            });
+                            target.ops.push(IrOp {
+                                kind: IrOpKind::If(arm_mark, next_mark),
+                                ops: vec![eq],
+                                result_into: None
+                            });
+                            self.push_marker(target, arm_mark);
+                        },
+                        Pattern::Binding(id) => {
+                            let var = IrOperand::Reference(id.data.clone().unwrap_resolved());
+                            self.bind(sym_table, target, var, v.clone(), value.type_data);
+                        },
+                        Pattern::Wildcard(..) => {}
+                    }
+                    let local_out = self.gen_code(consts, sym_table, target, &arm.body);
+                    target.ops.push(IrOp {
+                        kind: IrOpKind::Cpy,
+                        ops: vec![local_out],
+                        result_into: Some(out.clone())
+                    });
+                    target.ops.push(IrOp {
+                        kind: IrOpKind::Jmp(end_mark),
+                        ops: vec![],
+                        result_into: None
+                    });
+                    self.push_marker(target, next_mark);
+                }
+                self.push_marker(target, end_mark);
+                out
+            },
+            AstNodeKind::StructLiteral { fields, .. } => {
+                let t = ast.type_data.unwrap();
+                let out = self.alloc(target, layout::size_of(sym_table, t));
+                for f in fields {
+                    let value = self.gen_code(consts, sym_table, target, &f.value);
+                    let (offset, ty) = layout::field(sym_table, t, f.name.data);
+                    self.store(sym_table, target, &out, offset, value, ty);
+                }
+                out
+            },
+            AstNodeKind::Field { target: owner, name } => {
+                let base = self.gen_code(consts, sym_table, target, owner);
+                let (offset, ty) = layout::field(sym_table, owner.type_data.unwrap(), name.data);
+                self.field_at(consts, sym_table, target, base, offset, ty)
+            },
+            AstNodeKind::Interpolation { .. } => unreachable!("interpolation is reported before code generation"),
             AstNodeKind::Error => panic!("tried to generate code from a faulty AST"),
         }
     }
 
+    /// Applies an arithmetic operator, or the one its assignment is short for,
+    /// to numbers of type `ty`.
+    fn arithmetic<'a>(
+        &mut self,
+        consts: &mut ConstTable<'a>,
+        sym_table: &SymbolTable<'a>,
+        target: &mut IrBlock,
+        op: &BinOp,
+        [a, b]: [IrOperand; 2],
+        ty: Option<usize>
+    ) -> IrOperand {
+        let kind = match op {
+            BinOp::Add | BinOp::AddEq => IrOpKind::Add,
+            BinOp::Sub | BinOp::SubEq => IrOpKind::Sub,
+            BinOp::Mul | BinOp::MulEq => IrOpKind::Mul,
+            BinOp::Div | BinOp::DivEq if is_unsigned(sym_table, ty) => IrOpKind::UDiv,
+            BinOp::Div | BinOp::DivEq => IrOpKind::Div,
+            // `a % b` is `a - a / b * b`
+            BinOp::Mod | BinOp::ModEq => {
+                let quotient = self.arithmetic(consts, sym_table, target, &BinOp::Div, [a.clone(), b.clone()], ty);
+                let whole = self.arithmetic(consts, sym_table, target, &BinOp::Mul, [quotient, b], ty);
+                return self.arithmetic(consts, sym_table, target, &BinOp::Sub, [a, whole], ty)
+            },
+            BinOp::Pow | BinOp::PowEq => {
+                let out_id = self.power(consts, target, a, b);
+                return self.wrap(sym_table, target, out_id, ty)
+            },
+            _ => unreachable!("{:?} isn't arithmetic", op)
+        };
+        let out_id = self.allocate_temp();
+        target.ops.push(IrOp {
+            kind,
+            ops: vec![a, b],
+            result_into: Some(out_id.clone())
+        });
+        self.wrap(sym_table, target, out_id, ty)
+    }
+
+    /// Cuts `value` down to fit its type, when that's smaller than a
+    /// register, the same as the hardware would for a register that size.
+    fn wrap(&mut self, sym_table: &SymbolTable, target: &mut IrBlock, value: IrOperand, ty: Option<usize>) -> IrOperand {
+        let Some(TypeProps::Integer { signed, bits: bits @ (8 | 16 | 32) }) = ty.map(|t| sym_table.type_props(t)) else {
+            return value
+        };
+        let out_id = self.allocate_temp();
+        target.ops.push(IrOp {
+            kind: IrOpKind::Wrap(bits, signed),
+            ops: vec![value],
+            result_into: Some(out_id.clone())
+        });
+        out_id
+    }
+
+    /// `base ** exponent`, by multiplying `exponent` times. Anything to a
+    /// power of zero or less is one.
+    fn power<'a>(&mut self, consts: &mut ConstTable<'a>, target: &mut IrBlock, base: IrOperand, exponent: IrOperand) -> IrOperand {
+        let mut constant = |this: &mut Self, target: &mut IrBlock, i: i64| {
+            let out_id = this.allocate_temp();
+            target.ops.push(IrOp {
+                kind: IrOpKind::LoadC(consts.add(ConstantValue::CompInt(CompInt::I64(i)))),
+                ops: vec![],
+                result_into: Some(out_id.clone())
+            });
+            out_id
+        };
+        let zero = constant(self, target, 0);
+        let one = constant(self, target, 1);
+        let out_id = constant(self, target, 1);
+        // counted down in a copy, so the exponent's variable isn't changed
+        let left = self.allocate_temp();
+        target.ops.push(IrOp {
+            kind: IrOpKind::Cpy,
+            ops: vec![exponent],
+            result_into: Some(left.clone())
+        });
+
+        let start_mark = self.push_new_marker(target);
+        let body_mark = self.allocate_new_marker();
+        let end_mark = self.allocate_new_marker();
+        let more = self.allocate_temp();
+        target.ops.push(IrOp {
+            kind: IrOpKind::Lt,
+            ops: vec![zero, left.clone()],
+            result_into: Some(more.clone())
+        });
+        target.ops.push(IrOp {
+            kind: IrOpKind::If(body_mark, end_mark),
+            ops: vec![more],
+            result_into: None
+        });
+        self.push_marker(target, body_mark);
+        target.ops.push(IrOp {
+            kind: IrOpKind::Mul,
+            ops: vec![out_id.clone(), base],
+            result_into: Some(out_id.clone())
+        });
+        target.ops.push(IrOp {
+            kind: IrOpKind::Sub,
+            ops: vec![left.clone(), one],
+            result_into: Some(left)
+        });
+        target.ops.push(IrOp {
+            kind: IrOpKind::Jmp(start_mark),
+            ops: vec![],
+            result_into: None
+        });
+        self.push_marker(target, end_mark);
+        out_id
+    }
+
+    /// Gives back `value` from the function we're in, copying it to the
+    /// caller's slot if it's a struct.
+    fn ret(&mut self, target: &mut IrBlock, value: IrOperand) {
+        let value = match self.ret_slot.clone() {
+            Some((slot, size)) => {
+                self.copy(target, &slot, 0, &value, size);
+                slot
+            },
+            None => value
+        };
+        target.ops.push(IrOp {
+            kind: IrOpKind::Ret,
+            ops: vec![value],
+            result_into: None
+        });
+    }
+
+    /// Makes `var` hold `value`, which has the type `t`. A struct gets a
+    /// place of its own, so changing one variable never changes another.
+    fn bind(&mut self, sym_table: &SymbolTable, target: &mut IrBlock, var: IrOperand, value: IrOperand, t: Option<usize>) {
+        match t.filter(|t| layout::is_struct(sym_table, *t)) {
+            Some(t) => {
+                let size = layout::size_of(sym_table, t);
+                target.ops.push(IrOp {
+                    kind: IrOpKind::Alloc(size),
+                    ops: vec![],
+                    result_into: Some(var.clone())
+                });
+                self.copy(target, &var, 0, &value, size);
+            },
+            None => target.ops.push(IrOp {
+                kind: IrOpKind::Cpy,
+                ops: vec![value],
+                result_into: Some(var)
+            })
+        }
+    }
+
+    /// The field `offset` bytes into the struct at `base`. A field that's a
+    /// struct itself is just its address.
+    fn field_at<'a>(
+        &mut self,
+        consts: &mut ConstTable<'a>,
+        sym_table: &SymbolTable<'a>,
+        target: &mut IrBlock,
+        base: IrOperand,
+        offset: usize,
+        ty: usize
+    ) -> IrOperand {
+        if !layout::is_struct(sym_table, ty) {
+            return self.load(target, &base, offset);
+        }
+        if offset == 0 {
+            return base;
+        }
+        let by = self.allocate_temp();
+        target.ops.push(IrOp {
+            kind: IrOpKind::LoadC(consts.add(ConstantValue::CompInt(CompInt::I64(offset as i64)))),
+            ops: vec![],
+            result_into: Some(by.clone())
+        });
+        let out = self.allocate_temp();
+        target.ops.push(IrOp {
+            kind: IrOpKind::Add,
+            ops: vec![base, by],
+            result_into: Some(out.clone())
+        });
+        out
+    }
+
+    /// Puts `value`, of type `ty`, `offset` bytes into the struct at `base`.
+    fn store(&mut self, sym_table: &SymbolTable, target: &mut IrBlock, base: &IrOperand, offset: usize, value: IrOperand, ty: usize) {
+        if layout::is_struct(sym_table, ty) {
+            self.copy(target, base, offset, &value, layout::size_of(sym_table, ty));
+        } else {
+            target.ops.push(IrOp {
+                kind: IrOpKind::Store(offset),
+                ops: vec![base.clone(), value],
+                result_into: None
+            });
+        }
+    }
+
+    /// Copies the `size` bytes at `from` to `offset` bytes into `to`, a word
+    /// at a time.
+    fn copy(&mut self, target: &mut IrBlock, to: &IrOperand, offset: usize, from: &IrOperand, size: usize) {
+        for word in (0..size).step_by(layout::WORD) {
+            let w = self.load(target, from, word);
+            target.ops.push(IrOp {
+                kind: IrOpKind::Store(offset + word),
+                ops: vec![to.clone(), w],
+                result_into: None
+            });
+        }
+    }
+
+    fn load(&mut self, target: &mut IrBlock, from: &IrOperand, offset: usize) -> IrOperand {
+        let out = self.allocate_temp();
+        target.ops.push(IrOp {
+            kind: IrOpKind::Load(offset),
+            ops: vec![from.clone()],
+            result_into: Some(out.clone())
+        });
+        out
+    }
+
+    fn alloc(&mut self, target: &mut IrBlock, size: usize) -> IrOperand {
+        let out = self.allocate_temp();
+        target.ops.push(IrOp {
+            kind: IrOpKind::Alloc(size),
+            ops: vec![],
+            result_into: Some(out.clone())
+        });
+        out
+    }
+
+    /// The loop a `break` or `continue` goes to: the one with its label, or
+    /// the innermost one.
+    fn jump_context(&self, label: &Option<Sp<Reference>>) -> &IrContext {
+        match label {
+            Some(l) => {
+                let id = l.data.clone().unwrap_resolved();
+                self.context.iter().rev().find(|c| c.label == Some(id)).unwrap()
+            },
+            None => self.context.last().unwrap()
+        }
+    }
+
     fn allocate_temp(&mut self) -> IrOperand {
         IrOperand::Temporary(self.allocate_temp_id())
     }
@@ -299,3 +889,7 @@ impl IrGen {
         });
     }
 }
+
+fn is_unsigned(sym_table: &SymbolTable, ty: Option<usize>) -> bool {
+    matches!(ty.map(|t| sym_table.type_props(t)), Some(TypeProps::Integer { signed: false, .. }))
+}
@@ -0,0 +1,40 @@
+//! Struct Layout
+//!
+//! Everything that isn't a struct fits in a register, and takes up one word
+//! when it's stored. Structs are stored as their fields one after another, in
+//! the order they're declared, with any structs in them laid out in place.
+
+use crate::fe::symbols::SymbolTable;
+
+/// The size of a register, and of everything that fits in one
+pub const WORD: usize = 8;
+
+/// Whether values of type `t` are stored in memory, with only their address
+/// in a register
+pub fn is_struct(symbols: &SymbolTable, t: usize) -> bool {
+    symbols.fields(t).is_some()
+}
+
+/// How many bytes a value of type `t` takes up.
+pub fn size_of(symbols: &SymbolTable, t: usize) -> usize {
+    match symbols.fields(t) {
+        Some(fields) => fields.iter()
+            .map(|(_, f)| size_of(symbols, f.expect("checked code knows the type of every field")))
+            .sum(),
+        None => WORD
+    }
+}
+
+/// How many bytes into a struct of type `t` the field called `name` is, and
+/// the field's type.
+pub fn field(symbols: &SymbolTable, t: usize, name: &str) -> (usize, usize) {
+    let mut offset = 0;
+    for (n, f) in symbols.fields(t).unwrap_or_default() {
+        let f = f.expect("checked code knows the type of every field");
+        if *n == name {
+            return (offset, f)
+        }
+        offset += size_of(symbols, f);
+    }
+    unreachable!("checked code only uses fields that exist")
+}
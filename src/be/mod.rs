@@ -4,9 +4,11 @@ use crate::fe::item::Item;
 
 use self::consts::ConstTable;
 
+pub mod check;
 pub mod ir;
 pub mod irgen;
 pub mod consts;
+pub mod layout;
 pub mod platform;
 pub mod ralloc;
 
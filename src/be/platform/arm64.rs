@@ -1,10 +1,16 @@
-use std::io::{Write, BufWriter};
+use std::io::{self, Write, BufWriter};
 
 use crate::{
-    be::{ir::{IrBlock, IrOpKind, IrOperand}, CompUnit, platform::ra_profile::ArmRegAlloc, ralloc::RegAllocProfile},
+    be::{ir::{IrBlock, IrOp, IrOpKind, IrOperand}, CompUnit, platform::ra_profile::ArmRegAlloc, ralloc::{RegAllocProfile, OutOfRegisters}},
     fe::ast::{ConstantValue, CompInt}
 };
 
+/// Mach-O prefixes C symbols with an underscore, ELF doesn't.
+#[cfg(target_os = "macos")]
+const SYMBOL_PREFIX: &str = "_";
+#[cfg(not(target_os = "macos"))]
+const SYMBOL_PREFIX: &str = "";
+
 trait IntoArmReg {
     fn arm_asm(&self) -> String;
 }
@@ -16,13 +22,19 @@ impl IntoArmReg for IrOperand {
     }
 }
 
-pub struct Arm64Generator<'a, T: Write> {
+/// Replaces every operand in `block` with a machine register.
+pub fn allocate_registers(block: &mut IrBlock) -> Result<(), OutOfRegisters> {
+    let ra = ArmRegAlloc::make();
+    ra.allocate_for(block)
+}
+
+pub struct Arm64Generator<'u, 'a, T: Write> {
     output: BufWriter<T>,
-    unit: CompUnit<'a>
+    unit: &'u mut CompUnit<'a>
 }
 
-impl<'a, T: Write> Arm64Generator<'a, T> {
-    pub fn new(wr: T, unit: CompUnit<'a>) -> Self {
+impl<'u, 'a, T: Write> Arm64Generator<'u, 'a, T> {
+    pub fn new(wr: T, unit: &'u mut CompUnit<'a>) -> Self {
         let mut s = Self {
             output: BufWriter::new(wr),
             unit
@@ -33,26 +45,36 @@ impl<'a, T: Write> Arm64Generator<'a, T> {
     }
 
     fn generate_const_block(&mut self) {
-        self.write(".data:\n");
+        // the constants live in the text section so `ldr` can reach them
+        // with a pc-relative literal load
+        self.write(".text\n");
         let nconsts = self.unit.consts.consts.len();
         let mut names = Vec::with_capacity(nconsts);
 
         for i in 0..nconsts {
             names.push(format!("c{}", i));
+            self.instr(".p2align 3");
             self.write(&format!("{}:\n", names[i]));
             match &self.unit.consts.consts[i] {
                 ConstantValue::String(str) => {
-                    // HACK: the debug representation *should* escape stuff
-                    // for me but this could be a bad idea
-                    self.instr(&format!(".ascii {:?}", str));
+                    self.instr(&format!(".ascii \"{}\"", gas_escape(str)));
                 },
                 ConstantValue::CompInt(i) => {
-                    match i {
-                        CompInt::I64(i) => {
-                            self.instr(&format!(".quad {}", (*i) as usize));
-                        },
-                        _ => todo!("other number types are not supported yet!")
-                    } 
+                    // every integer takes up a whole register, so smaller
+                    // ones are extended the way their type says
+                    let value = match *i {
+                        CompInt::I8(i) => i as u64,
+                        CompInt::I16(i) => i as u64,
+                        CompInt::I32(i) => i as u64,
+                        CompInt::I64(i) => i as u64,
+                        CompInt::ISize(i) => i as u64,
+                        CompInt::U8(i) => i as u64,
+                        CompInt::U16(i) => i as u64,
+                        CompInt::U32(i) => i as u64,
+                        CompInt::U64(i) => i,
+                        CompInt::USize(i) => i as u64
+                    };
+                    self.instr(&format!(".quad {}", value));
                 },
                 ConstantValue::Bool(b) => {
                     // OPTIMIZATION: This is really low hanging fruit.
@@ -62,25 +84,26 @@ impl<'a, T: Write> Arm64Generator<'a, T> {
                     // imagine what would happen if someone switched these lol
                     self.instr(&format!(".quad {}", if *b { 1 } else { 0 }))
                 },
-                ConstantValue::CompFloat(f) => todo!(),
-                ConstantValue::Nil => todo!(),
+                ConstantValue::CompFloat(_) => unreachable!("floats are reported before code generation"),
+                ConstantValue::Nil => unreachable!("the type checker rejects `nil`"),
             }
         }
 
         self.unit.consts.const_names = names;
 
         // we're done, move on to the program part
-        self.write(".text:\n");
+        self.instr(".p2align 2");
     }
 
-    pub fn gen(&mut self, entry: &mut IrBlock) {
+    /// Emits assembly for a block that has been through `allocate_registers`.
+    pub fn gen(&mut self, entry: &IrBlock) {
         use IrOpKind::*;
 
-        let ra = ArmRegAlloc::make();
-
-        ra.allocate_for(entry);
-
-        for instr in &entry.ops {
+        // the registers the function we're in uses, which calls have to keep
+        let mut used = used_registers(&entry.ops);
+        // how much of the frame has been handed out to structs
+        let mut frame = self.reserve(&entry.ops);
+        for (i, instr) in entry.ops.iter().enumerate() {
             let into = instr.result_into
                 .clone()
                 .map(|r| r.arm_asm());
@@ -90,7 +113,7 @@ impl<'a, T: Write> Arm64Generator<'a, T> {
                     let t = format!("ldr {}, {}", into, self.unit.consts.const_names[*c]);
                     self.instr(&t);
                 },
-                Add | Sub | Mul | Div => {
+                Add | Sub | Mul | Div | UDiv => {
                     let out = into.unwrap();
                     let [a, b] = &instr.ops[..] else { unreachable!() };
                     let a = a.arm_asm();
@@ -100,24 +123,29 @@ impl<'a, T: Write> Arm64Generator<'a, T> {
                         Sub => "sub",
                         Mul => "mul",
                         Div => "sdiv",
+                        UDiv => "udiv",
                         _ => unreachable!()
                     };
                     self.instr(&format!("{} {}, {}, {}", iname, out, a, b));
                 },
-                Eq | NotEq => {
+                Eq | NotEq | Lt | LtEq | ULt | ULtEq => {
                     let out = into.unwrap();
                     let [a, b] = &instr.ops[..] else { unreachable!() };
                     let a = a.arm_asm();
                     let b = b.arm_asm();
                     self.instr(&format!("cmp {}, {}", a, b));
 
-                    let equality_flag = match instr.kind {
+                    let condition = match instr.kind {
                         Eq => "EQ",
                         NotEq => "NE",
+                        Lt => "LT",
+                        LtEq => "LE",
+                        ULt => "LO",
+                        ULtEq => "LS",
                         _ => unreachable!()
                     };
 
-                    self.instr(&format!("cset {}, {}", out, equality_flag));
+                    self.instr(&format!("cset {}, {}", out, condition));
                 },
                 Neg | Not => {
                     let [i] = &instr.ops[..] else { unreachable!() };
@@ -133,6 +161,17 @@ impl<'a, T: Write> Arm64Generator<'a, T> {
                     };
                     self.instr(&format!("{} {}, {}", iname, out, x));
                 },
+                Wrap(bits, signed) => {
+                    let [i] = &instr.ops[..] else { unreachable!() };
+                    let out = into.unwrap();
+                    let x = i.unwrap_reg();
+                    match (bits, signed) {
+                        (8, true) => self.instr(&format!("sxtb {}, w{}", out, x)),
+                        (16, true) => self.instr(&format!("sxth {}, w{}", out, x)),
+                        (32, true) => self.instr(&format!("sxtw {}, w{}", out, x)),
+                        (bits, _) => self.instr(&format!("and {}, x{}, #{:#x}", out, x, (1u64 << bits) - 1))
+                    }
+                },
                 Cpy => {
                     let [i] = &instr.ops[..] else { unreachable!() };
                     let out = into.unwrap();
@@ -142,7 +181,64 @@ impl<'a, T: Write> Arm64Generator<'a, T> {
                     let [i] = &instr.ops[..] else { unreachable!() };
                     let x0 = i.arm_asm();
                     self.instr(&format!("mov x0, {}", x0));
-                    self.instr("ret");
+                    self.epilogue();
+                },
+                Function(id) => {
+                    used = used_registers(&entry.ops[i + 1..]);
+                    self.write(&format!("fig_fn_{}:\n", id));
+                    self.prologue();
+                    frame = self.reserve(&entry.ops[i + 1..]);
+                },
+                Alloc(size) => {
+                    // structs live just below the frame record
+                    frame += size;
+                    self.instr(&format!("sub {}, x29, #{}", into.unwrap(), frame));
+                },
+                Load(offset) => {
+                    let [from] = &instr.ops[..] else { unreachable!() };
+                    self.instr(&format!("ldr {}, [{}, #{}]", into.unwrap(), from.arm_asm(), offset));
+                },
+                Store(offset) => {
+                    let [to, value] = &instr.ops[..] else { unreachable!() };
+                    self.instr(&format!("str {}, [{}, #{}]", value.arm_asm(), to.arm_asm(), offset));
+                },
+                Param(n) => {
+                    // arguments are on the stack, above the frame record
+                    let out = into.unwrap();
+                    self.instr(&format!("ldr {}, [x29, #{}]", out, 16 + 8 * n));
+                },
+                Call(id) => {
+                    let out = into.unwrap();
+                    // the function is free to use any register, so everything
+                    // we use is kept on the stack, apart from what it'll give us
+                    let kept: Vec<_> = used.iter().filter(|r| **r != out).cloned().collect();
+                    for pair in kept.chunks(2) {
+                        match pair {
+                            [a, b] => self.instr(&format!("stp {}, {}, [sp, #-16]!", a, b)),
+                            [a] => self.instr(&format!("str {}, [sp, #-16]!", a)),
+                            _ => unreachable!()
+                        }
+                    }
+                    // the stack has to stay 16 byte aligned
+                    let args = (8 * instr.ops.len()).next_multiple_of(16);
+                    if args > 0 {
+                        self.instr(&format!("sub sp, sp, #{}", args));
+                    }
+                    for (n, arg) in instr.ops.iter().enumerate() {
+                        self.instr(&format!("str {}, [sp, #{}]", arg.arm_asm(), 8 * n));
+                    }
+                    self.instr(&format!("bl fig_fn_{}", id));
+                    if args > 0 {
+                        self.instr(&format!("add sp, sp, #{}", args));
+                    }
+                    self.instr(&format!("mov {}, x0", out));
+                    for pair in kept.chunks(2).rev() {
+                        match pair {
+                            [a, b] => self.instr(&format!("ldp {}, {}, [sp], #16", a, b)),
+                            [a] => self.instr(&format!("ldr {}, [sp], #16", a)),
+                            _ => unreachable!()
+                        }
+                    }
                 },
                 DefMarker(u) => {
                     self.write(&format!("marker_{}:\n", u));
@@ -164,14 +260,49 @@ impl<'a, T: Write> Arm64Generator<'a, T> {
         }
     }
 
+    /// Flushes everything written so far and hands back the output.
+    pub fn finish(self) -> io::Result<T> {
+        self.output.into_inner().map_err(|e| e.into_error())
+    }
+
     fn instr(&mut self, t: &str) {
         self.write(&format!("    {}\n", t));
     }
 
     fn header(&mut self) {
-        self.write(
-            ".global _fig_entrypoint\n_fig_entrypoint:\n"
-        );
+        let entry = format!("{}fig_entrypoint", SYMBOL_PREFIX);
+        self.write(&format!(".global {}\n{}:\n", entry, entry));
+        self.prologue();
+    }
+
+    /// Sets up a frame, so calls can be made without losing where to return to.
+    fn prologue(&mut self) {
+        self.instr("stp x29, x30, [sp, #-16]!");
+        self.instr("mov x29, sp");
+    }
+
+    /// Makes room on the stack for the structs of the function starting at
+    /// `ops`. Gives back how much of it has been used, which is none yet.
+    fn reserve(&mut self, ops: &[IrOp]) -> usize {
+        let size: usize = ops.iter()
+            .take_while(|i| !matches!(i.kind, IrOpKind::Function(..)))
+            .map(|i| match i.kind {
+                IrOpKind::Alloc(size) => size,
+                _ => 0
+            })
+            .sum();
+        // the stack has to stay 16 byte aligned
+        let size = size.next_multiple_of(16);
+        if size > 0 {
+            self.instr(&format!("sub sp, sp, #{}", size));
+        }
+        0
+    }
+
+    fn epilogue(&mut self) {
+        self.instr("mov sp, x29");
+        self.instr("ldp x29, x30, [sp], #16");
+        self.instr("ret");
     }
 
     fn write(&mut self, txt: &str) {
@@ -179,3 +310,32 @@ impl<'a, T: Write> Arm64Generator<'a, T> {
             .expect("failed to write to assembly target");
     }
 }
+
+/// The registers used by `ops` up until the start of the next function.
+fn used_registers(ops: &[IrOp]) -> Vec<String> {
+    let mut used = vec![];
+    for instr in ops.iter().take_while(|i| !matches!(i.kind, IrOpKind::Function(..))) {
+        for r in instr.ops.iter().chain(&instr.result_into) {
+            if !used.contains(&r.arm_asm()) {
+                used.push(r.arm_asm());
+            }
+        }
+    }
+    used.sort();
+    used
+}
+
+/// Escapes a string for the assembler. Rust's `{:?}` won't do, since `\u{..}`
+/// means nothing to it, so everything but printable ASCII is written in octal.
+fn gas_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for b in s.bytes() {
+        match b {
+            b'"' => out.push_str("\\\""),
+            b'\\' => out.push_str("\\\\"),
+            b' '..=b'~' => out.push(b as char),
+            _ => out.push_str(&format!("\\{:03o}", b))
+        }
+    }
+    out
+}
@@ -4,11 +4,10 @@ pub struct ArmRegAlloc;
 
 impl RegAllocProfile for ArmRegAlloc {
     fn make() -> RegisterAllocator {
-        let r = RegisterAllocator::new(21);
-        r
+        RegisterAllocator::new(21)
     }
 
-    fn init_interference(g: &mut InterferenceGraph) {
+    fn init_interference(_g: &mut InterferenceGraph) {
         // TODO
     }
 }
@@ -1,11 +1,19 @@
 //! Generic Register Allocator
 
-use std::{collections::HashMap, ops::Range, hash::{Hasher, Hash}};
+use std::{collections::HashMap, ops::Range};
 
 use crate::be::ir::{Register, IrOpKind};
 
 use super::ir::{IrBlock, IrOperand};
 
+/// More values are needed at once than there are registers, and spilling them
+/// to the stack isn't done yet
+#[derive(Debug)]
+pub struct OutOfRegisters {
+    /// The function that needs them, or `None` for the main program
+    pub function: Option<usize>
+}
+
 pub struct RegisterAllocator {
     registers: u8,
 }
@@ -15,8 +23,7 @@ impl RegisterAllocator {
         Self { registers: n_reg }
     } 
 
-    pub fn allocate_for(&self, b: &mut IrBlock) {
-        println!("ralloc!");
+    pub fn allocate_for(&self, b: &mut IrBlock) -> Result<(), OutOfRegisters> {
         let mut ranges = HashMap::<IrOperand, Range<usize>>::new();
         let mut graph = InterferenceGraph::new();
         let mut seen_markers = HashMap::<usize, usize>::new();
@@ -83,7 +90,7 @@ impl RegisterAllocator {
             if let Some(into_reg) = &i.result_into {
                 // update its live times
                 let range_of_this_line = ln..ln+1;
-                let times = ranges.get(&into_reg).unwrap_or(&range_of_this_line);
+                let times = ranges.get(into_reg).unwrap_or(&range_of_this_line);
                 // variable updated
                 ranges.insert(into_reg.clone(), times.start..ln+1);
             }
@@ -91,7 +98,7 @@ impl RegisterAllocator {
             // is anything being used here?
             for o in &i.ops {
                 let range_of_this_line = ln - 1..ln;
-                let times = ranges.get(&o).unwrap_or(&range_of_this_line);
+                let times = ranges.get(o).unwrap_or(&range_of_this_line);
                 let s = times.start;
                 // variable used
                 ranges.insert(o.clone(), s..ln);
@@ -136,38 +143,27 @@ impl RegisterAllocator {
         }
 
         let mut colormap = HashMap::new();
-        loop {
-            let mut need_to_spill = false;
-            // color the graph
-            for (operand, node) in &graph.nodes {
-                let mut colors = vec![false; self.registers as usize];
-                for neigh in &node.edges {
-                    if let Some(c) = colormap.get(neigh).unwrap_or(&None) {
-                        colors[*c as usize] = true;
-                    }
-                }
-                let mut color = None;
-                for (i, col) in colors.iter().enumerate() {
-                    if !col {
-                        color = Some(i as Register);
-                        break;
-                    }
+        // color the graph
+        for (operand, node) in &graph.nodes {
+            let mut colors = vec![false; self.registers as usize];
+            for neigh in &node.edges {
+                if let Some(c) = colormap.get(neigh).unwrap_or(&None) {
+                    colors[*c as usize] = true;
                 }
-                if color == None {
-                    need_to_spill = true;
-                }
-
-                colormap.insert(operand.clone(), color);
             }
-
-            if !need_to_spill {
-                break
-            } else {
-                todo!("REGISTER SPILLING REQUIRED!!!!")
+            let color = colors.iter().position(|col| !col).map(|i| i as Register);
+            if color.is_none() {
+                // the function it's used in is the last one to start before it is
+                let first_use = ranges[operand].start;
+                let function = b.ops[..=first_use].iter().rev().find_map(|i| match i.kind {
+                    IrOpKind::Function(id) => Some(id),
+                    _ => None
+                });
+                return Err(OutOfRegisters { function })
             }
-        }
 
-        println!("Color Map: {:?}", colormap);
+            colormap.insert(operand.clone(), color);
+        }
 
         let g = move |op: &IrOperand| {
             IrOperand::Register(colormap.get(op).unwrap().unwrap())
@@ -182,7 +178,7 @@ impl RegisterAllocator {
                 *op = g(op);
             }
         }
-        println!("Finished coloring!");
+        Ok(())
     }
 }
 
@@ -191,6 +187,7 @@ pub trait RegAllocProfile {
     fn init_interference(g: &mut InterferenceGraph);
 }
 
+#[derive(Default)]
 pub struct InterferenceGraph {
     pub nodes: HashMap<IrOperand, RegisterNode>
 }
@@ -203,12 +200,8 @@ impl InterferenceGraph {
     }
 
     fn ensure_init(&mut self, a: &IrOperand) -> &mut RegisterNode {
-        if self.nodes.get(a).is_none() {
+        if !self.nodes.contains_key(a) {
             // initialize a new node
-            let mut hash = std::collections::hash_map::DefaultHasher::new();
-            a.hash(&mut hash);
-            let hash = hash.finish();
-            println!("init {:?} -- {:?}", a, hash);
             self.nodes.insert(a.clone(), RegisterNode { edges: vec![], color: None });
         }
         self.nodes.get_mut(a).unwrap()
@@ -0,0 +1,267 @@
+//! The Documentation Generator
+//!
+//! Collects the `//!` comments of a file and the `///` comments of its items
+//! into a [`Page`], which renders as Markdown or as a standalone HTML file.
+
+use std::fmt::Write;
+
+use crate::fe::{
+    ast::{MaybeTyped, Statement},
+    lexer::Lexer,
+    symbols::SymbolTable,
+    token::{CommentType, Token}
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ItemKind {
+    Struct,
+    Function,
+    Variable
+}
+
+impl ItemKind {
+    const ALL: [ItemKind; 3] = [ItemKind::Struct, ItemKind::Function, ItemKind::Variable];
+
+    fn heading(&self) -> &'static str {
+        match self {
+            ItemKind::Struct => "Structs",
+            ItemKind::Function => "Functions",
+            ItemKind::Variable => "Variables"
+        }
+    }
+
+    fn anchor(&self) -> &'static str {
+        match self {
+            ItemKind::Struct => "struct",
+            ItemKind::Function => "fn",
+            ItemKind::Variable => "let"
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct DocItem {
+    pub kind: ItemKind,
+    pub name: String,
+    /// How the item is declared, without its body
+    pub signature: String,
+    pub docs: String
+}
+
+#[derive(Debug)]
+pub struct Page {
+    pub title: String,
+    pub docs: String,
+    pub items: Vec<DocItem>
+}
+
+/// Documents the top level items of a checked file. Tests are left out.
+pub fn document(title: &str, source: &str, symbols: &SymbolTable, stmts: &[Statement]) -> Page {
+    let module_docs: Vec<_> = Lexer::new(source)
+        .filter_map(|t| match t.data {
+            Token::Comment(CommentType::ModuleDoc, text) => Some(text),
+            _ => None
+        })
+        .collect();
+
+    let type_of = |t: &MaybeTyped| match t {
+        MaybeTyped::TypeResolved(t) => Some(symbols.type_name(*t).into_owned()),
+        MaybeTyped::TypeProvided(t) => Some(t.text().to_string()),
+        MaybeTyped::NotTyped => None
+    };
+    let mut items = vec![];
+    for stmt in stmts {
+        match stmt {
+            Statement::Struct { docs, name, fields, .. } => {
+                let signature = match fields {
+                    Some(fields) if !fields.is_empty() => {
+                        let fields: Vec<_> = fields.iter()
+                            .map(|f| format!("    {}: {}", f.name.data, type_of(&f.with_type).unwrap_or_default()))
+                            .collect();
+                        format!("struct {} {{\n{}\n}}", name.data, fields.join("\n"))
+                    },
+                    Some(_) => format!("struct {} {{}}", name.data),
+                    None => format!("struct {}", name.data)
+                };
+                items.push(DocItem {
+                    kind: ItemKind::Struct,
+                    name: name.to_string(),
+                    signature,
+                    docs: join(docs)
+                });
+            },
+            Statement::Function { docs, name, params, returns, .. } if !stmt.is_test() => {
+                let params: Vec<_> = params.iter()
+                    .map(|p| format!("{}: {}", p.id.text(), type_of(&p.with_type).unwrap_or_default()))
+                    .collect();
+                let mut signature = format!("fn {}({})", name.data, params.join(", "));
+                if let Some(out) = type_of(returns).filter(|_| !matches!(returns, MaybeTyped::TypeResolved(t) if *t == symbols.unit())) {
+                    signature += &format!(" -> {}", out);
+                }
+                items.push(DocItem {
+                    kind: ItemKind::Function,
+                    name: name.to_string(),
+                    signature,
+                    docs: join(docs)
+                });
+            },
+            Statement::Declare { docs, id, with_type, .. } => {
+                let name = id.text();
+                let signature = match with_type {
+                    MaybeTyped::TypeResolved(t) => format!("let {}: {}", name, symbols.type_name(*t)),
+                    MaybeTyped::TypeProvided(t) => format!("let {}: {}", name, t.text()),
+                    MaybeTyped::NotTyped => format!("let {}", name)
+                };
+                items.push(DocItem {
+                    kind: ItemKind::Variable,
+                    name: name.to_string(),
+                    signature,
+                    docs: join(docs)
+                });
+            },
+            _ => {}
+        }
+    }
+
+    Page {
+        title: title.to_string(),
+        docs: join(&module_docs),
+        items
+    }
+}
+
+/// Turns comment lines back into text, dropping the space after `///`.
+fn join(lines: &[&str]) -> String {
+    let lines: Vec<_> = lines.iter()
+        .map(|l| l.strip_prefix(' ').unwrap_or(l).trim_end())
+        .collect();
+    lines.join("\n").trim().to_string()
+}
+
+impl Page {
+    pub fn markdown(&self) -> String {
+        let mut out = format!("# {}\n", self.title);
+        if !self.docs.is_empty() {
+            let _ = write!(out, "\n{}\n", self.docs);
+        }
+        for kind in ItemKind::ALL {
+            let mut items = self.items.iter().filter(|i| i.kind == kind).peekable();
+            if items.peek().is_none() { continue }
+            let _ = write!(out, "\n## {}\n", kind.heading());
+            for item in items {
+                let _ = write!(out, "\n### `{}`\n\n```fig\n{}\n```\n", item.name, item.signature);
+                if !item.docs.is_empty() {
+                    let _ = write!(out, "\n{}\n", item.docs);
+                }
+            }
+        }
+        out
+    }
+
+    pub fn html(&self) -> String {
+        let mut out = String::new();
+        let _ = write!(
+            out,
+            "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>{0}</title>\n{1}</head>\n<body>\n<h1>{0}</h1>\n{2}",
+            escape(&self.title),
+            STYLE,
+            prose(&self.docs)
+        );
+        for kind in ItemKind::ALL {
+            let mut items = self.items.iter().filter(|i| i.kind == kind).peekable();
+            if items.peek().is_none() { continue }
+            let _ = writeln!(out, "<h2>{}</h2>", kind.heading());
+            for item in items {
+                let _ = writeln!(
+                    out,
+                    "<section id=\"{}.{}\">\n<h3><code>{}</code></h3>\n{}</section>",
+                    kind.anchor(),
+                    escape(&item.name),
+                    escape(&item.signature),
+                    prose(&item.docs)
+                );
+            }
+        }
+        out.push_str("</body>\n</html>\n");
+        out
+    }
+}
+
+const STYLE: &str = "<style>
+body { max-width: 48em; margin: 2em auto; padding: 0 1em; font-family: sans-serif; line-height: 1.5; }
+code, pre { font-family: monospace; background: #f4f4f4; border-radius: 3px; }
+pre { padding: 0.5em 1em; overflow-x: auto; }
+section { margin-bottom: 1.5em; }
+</style>
+";
+
+/// Renders doc text as HTML. Only paragraphs, `code` and fenced code blocks
+/// mean anything; the rest is shown as written.
+fn prose(text: &str) -> String {
+    let mut out = String::new();
+    let mut paragraph: Vec<&str> = vec![];
+    let mut code: Option<Vec<&str>> = None;
+
+    let flush = |out: &mut String, paragraph: &mut Vec<&str>| {
+        if !paragraph.is_empty() {
+            let _ = writeln!(out, "<p>{}</p>", inline_code(&escape(&paragraph.join("\n"))));
+            paragraph.clear();
+        }
+    };
+    for line in text.lines() {
+        if line.trim_start().starts_with("```") {
+            match code.take() {
+                Some(lines) => {
+                    let _ = writeln!(out, "<pre><code>{}</code></pre>", escape(&lines.join("\n")));
+                },
+                None => {
+                    flush(&mut out, &mut paragraph);
+                    code = Some(vec![]);
+                }
+            }
+        } else if let Some(lines) = &mut code {
+            lines.push(line);
+        } else if line.trim().is_empty() {
+            flush(&mut out, &mut paragraph);
+        } else {
+            paragraph.push(line);
+        }
+    }
+    if let Some(lines) = code {
+        let _ = writeln!(out, "<pre><code>{}</code></pre>", escape(&lines.join("\n")));
+    }
+    flush(&mut out, &mut paragraph);
+    out
+}
+
+/// Swaps pairs of backticks for `<code>` tags. A backtick without another to
+/// pair up with is left as it is.
+fn inline_code(text: &str) -> String {
+    let mut out = String::new();
+    let parts: Vec<_> = text.split('`').collect();
+    for (i, part) in parts.iter().enumerate() {
+        if i % 2 == 0 {
+            out.push_str(part);
+        } else if i + 1 < parts.len() {
+            let _ = write!(out, "<code>{}</code>", part);
+        } else {
+            out.push('`');
+            out.push_str(part);
+        }
+    }
+    out
+}
+
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            ch => out.push(ch)
+        }
+    }
+    out
+}
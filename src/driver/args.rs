@@ -0,0 +1,167 @@
+//! Command Line Arguments
+
+use std::path::PathBuf;
+
+use super::emit::{self, Emit};
+
+pub const USAGE: &str = "\
+usage: fig <command> <file> [options]
+       fig explain <code>
+
+commands:
+    build   compile <file> into an artifact
+    run     compile <file> and execute it
+    check   parse and type check <file> without generating code
+    test    run the `@test` functions in <file>
+    repl    evaluate code interactively (takes no <file>)
+    doc     write documentation for <file> as HTML and Markdown
+    fmt     format <file> in place
+    lsp     run a language server over stdin and stdout (takes no <file>)
+    explain describe an error code, like `E0100`, with an example
+    help    print this message
+
+options:
+    -o, --output <path>     where to write the artifact (or, for `doc`, the directory)
+    --cc <path>             the C compiler used to link (or `FIG_CC`)
+    --as <path>             the assembler to use instead of the C compiler (or `FIG_AS`)
+    --save-temps            keep intermediate files around
+    --emit <stages>         dump stages to files, e.g. `--emit=tokens,ir=-,asm=out.s`
+                            (stages: tokens, cst, ast, ir, ir-colored, asm; `-` is stdout)
+    --message-format <fmt>  how to print diagnostics: `human` or `json`
+    --check                 with `fmt`, fail instead of rewriting a file that isn't formatted";
+
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Build,
+    Run,
+    Check,
+    Test,
+    Doc,
+    Repl,
+    Fmt,
+    Lsp,
+    /// Takes an error code instead of a file
+    Explain,
+    Help
+}
+
+impl Command {
+    fn takes_input(&self) -> bool {
+        !matches!(self, Command::Repl | Command::Lsp)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MessageFormat {
+    Human,
+    /// One JSON object per line for each diagnostic
+    Json
+}
+
+#[derive(Debug)]
+pub struct Args {
+    pub command: Command,
+    pub input: PathBuf,
+    pub output: Option<PathBuf>,
+    pub cc: Option<PathBuf>,
+    pub assembler: Option<PathBuf>,
+    pub save_temps: bool,
+    pub emit: Vec<Emit>,
+    pub message_format: MessageFormat,
+    /// Only check that the input is formatted
+    pub check: bool
+}
+
+impl Args {
+    fn help() -> Self {
+        Self {
+            command: Command::Help,
+            input: PathBuf::new(),
+            output: None,
+            cc: None,
+            assembler: None,
+            save_temps: false,
+            emit: vec![],
+            message_format: MessageFormat::Human,
+            check: false
+        }
+    }
+}
+
+pub fn parse(mut args: impl Iterator<Item = String>) -> Result<Args, String> {
+    let command = match args.next().as_deref() {
+        Some("build") => Command::Build,
+        Some("run") => Command::Run,
+        Some("check") => Command::Check,
+        Some("test") => Command::Test,
+        Some("doc") => Command::Doc,
+        Some("repl") => Command::Repl,
+        Some("fmt") => Command::Fmt,
+        Some("lsp") => Command::Lsp,
+        Some("explain") => Command::Explain,
+        Some("help" | "-h" | "--help") | None => return Ok(Args::help()),
+        Some(other) => return Err(format!("unknown command `{}`", other))
+    };
+
+    let mut input = None;
+    let mut output = None;
+    let mut cc = None;
+    let mut assembler = None;
+    let mut save_temps = false;
+    let mut emits = vec![];
+    let mut message_format = MessageFormat::Human;
+    let mut check = false;
+    while let Some(arg) = args.next() {
+        // `--flag=value` is the same as `--flag value`
+        let (flag, mut inline) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag, Some(value.to_string())),
+            _ => (arg.as_str(), None)
+        };
+        let mut value = || inline.take()
+            .or_else(|| args.next())
+            .ok_or_else(|| format!("expected a value after `{}`", flag));
+
+        match flag {
+            "-o" | "--output" => output = Some(PathBuf::from(value()?)),
+            "--cc" => cc = Some(PathBuf::from(value()?)),
+            "--as" => assembler = Some(PathBuf::from(value()?)),
+            "--save-temps" => save_temps = true,
+            "--emit" => emits.append(&mut emit::parse(&value()?)?),
+            "--message-format" => message_format = match value()?.as_str() {
+                "human" => MessageFormat::Human,
+                "json" => MessageFormat::Json,
+                other => return Err(format!("unknown message format `{}` (expected human or json)", other))
+            },
+            "--check" => check = true,
+            "-h" | "--help" => return Ok(Args::help()),
+            flag if flag.starts_with('-') && flag.len() > 1 => {
+                return Err(format!("unknown option `{}`", flag))
+            },
+            path => {
+                if input.is_some() || !command.takes_input() {
+                    return Err(format!("unexpected argument `{}`", path))
+                }
+                input = Some(PathBuf::from(path));
+            }
+        }
+    }
+
+    let input = match input {
+        Some(i) => i,
+        None if !command.takes_input() => PathBuf::new(),
+        None if command == Command::Explain => return Err("expected an error code, like `fig explain E0100`".to_string()),
+        None => return Err("expected a source file".to_string())
+    };
+
+    if command == Command::Check {
+        if let Some(e) = emits.iter().find(|e| !e.stage.before_codegen()) {
+            return Err(format!("`fig check` stops before `{}` can be emitted, use `fig build`", e.stage.name()))
+        }
+    }
+
+    if check && command != Command::Fmt {
+        return Err("`--check` only applies to `fig fmt`".to_string())
+    }
+
+    Ok(Args { command, input, output, cc, assembler, save_temps, emit: emits, message_format, check })
+}
@@ -0,0 +1,106 @@
+//! Dumping Intermediate Stages
+
+use std::{fs::File, io::{self, Write}, path::{Path, PathBuf}};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Stage {
+    Tokens,
+    Cst,
+    Ast,
+    Ir,
+    IrColored,
+    Asm
+}
+
+impl Stage {
+    const ALL: [Stage; 6] = [Self::Tokens, Self::Cst, Self::Ast, Self::Ir, Self::IrColored, Self::Asm];
+
+    fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|s| s.name() == name)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Tokens => "tokens",
+            Self::Cst => "cst",
+            Self::Ast => "ast",
+            Self::Ir => "ir",
+            Self::IrColored => "ir-colored",
+            Self::Asm => "asm"
+        }
+    }
+
+    fn extension(&self) -> &'static str {
+        match self {
+            Self::Tokens => "tokens",
+            Self::Cst => "cst",
+            Self::Ast => "ast",
+            Self::Ir => "ir",
+            Self::IrColored => "colored.ir",
+            Self::Asm => "s"
+        }
+    }
+
+    /// Whether `fig check` gets far enough to produce this stage.
+    pub fn before_codegen(&self) -> bool {
+        matches!(self, Self::Tokens | Self::Cst | Self::Ast)
+    }
+}
+
+#[derive(Debug)]
+pub enum Destination {
+    Stdout,
+    File(PathBuf),
+    /// Next to the input file, with the stage's extension
+    Default
+}
+
+#[derive(Debug)]
+pub struct Emit {
+    pub stage: Stage,
+    pub to: Destination
+}
+
+/// Parses `--emit` values such as `tokens,ir=-,asm=out.s`.
+pub fn parse(list: &str) -> Result<Vec<Emit>, String> {
+    let mut emits = vec![];
+    for item in list.split(',').filter(|i| !i.is_empty()) {
+        let (name, to) = match item.split_once('=') {
+            Some((name, "-")) => (name, Destination::Stdout),
+            Some((name, path)) => (name, Destination::File(PathBuf::from(path))),
+            None => (item, Destination::Default)
+        };
+        let Some(stage) = Stage::from_name(name) else {
+            return Err(format!(
+                "unknown stage `{}` for `--emit` (expected tokens, cst, ast, ir, ir-colored or asm)",
+                name
+            ))
+        };
+        emits.push(Emit { stage, to });
+    }
+    Ok(emits)
+}
+
+/// Runs `write` against the destination of every requested `stage`.
+pub fn emit(
+    emits: &[Emit],
+    input: &Path,
+    stage: Stage,
+    mut write: impl FnMut(&mut dyn Write) -> io::Result<()>
+) -> Result<(), String> {
+    for e in emits.iter().filter(|e| e.stage == stage) {
+        let path = match &e.to {
+            Destination::Stdout => {
+                let mut out = io::stdout().lock();
+                write(&mut out).map_err(|err| format!("couldn't write to stdout: {}", err))?;
+                continue
+            },
+            Destination::File(p) => p.clone(),
+            Destination::Default => input.with_extension(stage.extension())
+        };
+        File::create(&path)
+            .and_then(|mut f| write(&mut f))
+            .map_err(|err| format!("couldn't write `{}`: {}", path.display(), err))?;
+    }
+    Ok(())
+}
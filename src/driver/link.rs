@@ -0,0 +1,110 @@
+//! Assembling and Linking
+//!
+//! We don't ship an assembler or a linker, so we borrow the system's C
+//! toolchain. The C compiler doubles as the assembler unless one is given.
+
+use std::{env, ffi::OsStr, fs, path::{Path, PathBuf}, process::Command};
+
+const RUNTIME: &str = include_str!("../be/platform/fig_runtime.c");
+
+/// C compilers to look for on the `PATH`, most preferred first.
+#[cfg(target_arch = "aarch64")]
+const CC_CANDIDATES: &[&str] = &["cc", "clang", "gcc"];
+#[cfg(not(target_arch = "aarch64"))]
+const CC_CANDIDATES: &[&str] = &["aarch64-linux-gnu-gcc", "aarch64-unknown-linux-gnu-gcc", "clang"];
+
+pub struct Toolchain {
+    cc: PathBuf,
+    assembler: Option<PathBuf>
+}
+
+impl Toolchain {
+    /// Picks the tools to use. Explicit paths win over the `FIG_CC`/`FIG_AS`
+    /// environment variables, which win over `CC` and a search of the `PATH`.
+    pub fn locate(cc: Option<&Path>, assembler: Option<&Path>) -> Result<Self, String> {
+        let cc = match cc.map(Path::to_path_buf).or_else(|| env_path("FIG_CC")).or_else(|| env_path("CC")) {
+            Some(c) => c,
+            None => CC_CANDIDATES.iter()
+                .find_map(|c| search_path(c))
+                .ok_or_else(|| format!(
+                    "couldn't find a C compiler for arm64 (tried {}); pass `--cc <path>` or set `FIG_CC`",
+                    CC_CANDIDATES.join(", ")
+                ))?
+        };
+        let assembler = assembler.map(Path::to_path_buf).or_else(|| env_path("FIG_AS"));
+        Ok(Self { cc, assembler })
+    }
+
+    /// Turns the assembly at `asm` into an executable at `out`, using `scratch`
+    /// for the object files.
+    pub fn link(&self, asm: &Path, scratch: &Path, out: &Path) -> Result<(), String> {
+        let runtime = scratch.join("fig_runtime.c");
+        fs::write(&runtime, RUNTIME)
+            .map_err(|e| format!("couldn't write the runtime to `{}`: {}", runtime.display(), e))?;
+
+        let prog_obj = scratch.join("prog.o");
+        let runtime_obj = scratch.join("fig_runtime.o");
+
+        match &self.assembler {
+            Some(a) => invoke(a, "assemble the program", &[asm.as_os_str(), "-o".as_ref(), prog_obj.as_os_str()])?,
+            None => invoke(&self.cc, "assemble the program", &["-c".as_ref(), asm.as_os_str(), "-o".as_ref(), prog_obj.as_os_str()])?
+        }
+        invoke(&self.cc, "compile the runtime", &["-c".as_ref(), runtime.as_os_str(), "-o".as_ref(), runtime_obj.as_os_str()])?;
+        invoke(&self.cc, "link", &[runtime_obj.as_os_str(), prog_obj.as_os_str(), "-o".as_ref(), out.as_os_str()])
+    }
+}
+
+fn invoke(tool: &Path, doing: &str, args: &[&OsStr]) -> Result<(), String> {
+    let output = Command::new(tool)
+        .args(args)
+        .output()
+        .map_err(|e| format!("couldn't invoke `{}` to {}: {}", tool.display(), doing, e))?;
+    if output.status.success() {
+        return Ok(())
+    }
+
+    let mut msg = format!("`{}` failed to {} ({})", tool.display(), doing, output.status);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    for line in stderr.lines() {
+        msg.push_str("\n  | ");
+        msg.push_str(line);
+    }
+    Err(msg)
+}
+
+fn env_path(var: &str) -> Option<PathBuf> {
+    env::var_os(var).filter(|v| !v.is_empty()).map(PathBuf::from)
+}
+
+fn search_path(name: &str) -> Option<PathBuf> {
+    let path = env::var_os("PATH")?;
+    env::split_paths(&path)
+        .map(|dir| dir.join(name))
+        .find(|p| p.is_file())
+}
+
+/// A directory for intermediate files that is removed when dropped,
+/// unless we were asked to keep it around.
+pub struct Scratch {
+    pub path: PathBuf,
+    pub keep: bool
+}
+
+impl Scratch {
+    pub fn new(keep: bool) -> Result<Self, String> {
+        let path = env::temp_dir().join(format!("fig-{}", std::process::id()));
+        fs::create_dir_all(&path)
+            .map_err(|e| format!("couldn't create `{}`: {}", path.display(), e))?;
+        Ok(Self { path, keep })
+    }
+}
+
+impl Drop for Scratch {
+    fn drop(&mut self) {
+        if self.keep {
+            eprintln!("note: intermediate files were kept in `{}`", self.path.display());
+        } else {
+            let _ = fs::remove_dir_all(&self.path);
+        }
+    }
+}
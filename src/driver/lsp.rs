@@ -0,0 +1,493 @@
+//! The Language Server
+//!
+//! Speaks just enough of the Language Server Protocol over stdin and stdout
+//! to publish diagnostics, show types on hover and jump to definitions.
+//! Documents are small, so each one is checked from scratch whenever it's
+//! needed instead of keeping its AST around.
+
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, Write},
+    ops::Range,
+    panic::{self, AssertUnwindSafe},
+    process::ExitCode
+};
+
+use fig::{
+    fe::{
+        ast::{AstNode, AstNodeKind, Pattern, Reference, Statement},
+        lint,
+        parser::Parser,
+        scope::Scope,
+        symbols::{Symbol, SymbolTable},
+        CompileError, Severity, Sp
+    },
+    json::Json
+};
+
+// error codes from the JSON-RPC spec
+const INVALID_REQUEST: f64 = -32600.0;
+const METHOD_NOT_FOUND: f64 = -32601.0;
+
+/// Serves one client until it tells us to exit. Exiting without being asked
+/// to shut down first is a failure, as the protocol says.
+pub fn run() -> io::Result<ExitCode> {
+    // crashes are reported to the client as diagnostics instead
+    panic::set_hook(Box::new(|_| {}));
+
+    let mut input = io::stdin().lock();
+    let mut server = Server {
+        out: io::stdout().lock(),
+        documents: HashMap::new(),
+        shut_down: false
+    };
+
+    loop {
+        let Some(body) = read_message(&mut input)? else {
+            // the client went away without saying goodbye
+            return Ok(ExitCode::FAILURE)
+        };
+        let msg = match Json::parse(&body) {
+            Ok(m) => m,
+            Err(e) => {
+                eprintln!("fig lsp: ignoring a malformed message: {}", e);
+                continue
+            }
+        };
+
+        let method = msg.get("method").as_str().unwrap_or_default();
+        let params = msg.get("params");
+        let id = msg.get("id");
+        if method == "exit" {
+            return Ok(if server.shut_down { ExitCode::SUCCESS } else { ExitCode::FAILURE })
+        }
+        if method.is_empty() {
+            // a response, but we never ask the client anything
+            continue
+        }
+
+        if id.is_null() {
+            server.notification(method, params)?;
+            continue
+        }
+        let reply = match server.request(method, params) {
+            Ok(result) => Json::object([
+                ("jsonrpc", "2.0".into()),
+                ("id", id.clone()),
+                ("result", result)
+            ]),
+            Err((code, message)) => Json::object([
+                ("jsonrpc", "2.0".into()),
+                ("id", id.clone()),
+                ("error", Json::object([
+                    ("code", Json::Number(code)),
+                    ("message", message.into())
+                ]))
+            ])
+        };
+        send(&mut server.out, &reply)?;
+    }
+}
+
+struct Server<W: Write> {
+    out: W,
+    /// The text of every open document, by URI
+    documents: HashMap<String, String>,
+    shut_down: bool
+}
+
+impl<W: Write> Server<W> {
+    fn request(&mut self, method: &str, params: &Json) -> Result<Json, (f64, String)> {
+        if self.shut_down {
+            return Err((INVALID_REQUEST, "the server is shutting down".to_string()))
+        }
+        match method {
+            "initialize" => Ok(Json::object([
+                ("capabilities", Json::object([
+                    // the whole document is sent on every change
+                    ("textDocumentSync", 1.into()),
+                    ("hoverProvider", true.into()),
+                    ("definitionProvider", true.into())
+                ])),
+                ("serverInfo", Json::object([
+                    ("name", "fig".into()),
+                    ("version", env!("CARGO_PKG_VERSION").into())
+                ]))
+            ])),
+            "shutdown" => {
+                self.shut_down = true;
+                Ok(Json::Null)
+            },
+            "textDocument/hover" => Ok(self.at_position(params, hover)),
+            "textDocument/definition" => Ok(self.at_position(params, definition)),
+            _ => Err((METHOD_NOT_FOUND, format!("`{}` isn't supported", method)))
+        }
+    }
+
+    fn notification(&mut self, method: &str, params: &Json) -> io::Result<()> {
+        let uri = params.get("textDocument").get("uri").as_str().unwrap_or_default().to_string();
+        match method {
+            "textDocument/didOpen" => {
+                let text = params.get("textDocument").get("text").as_str().unwrap_or_default();
+                self.documents.insert(uri.clone(), text.to_string());
+            },
+            "textDocument/didChange" => {
+                // we only ask for full syncs, so the last change is the whole document
+                let changes = params.get("contentChanges").as_array().unwrap_or_default();
+                let Some(text) = changes.last().and_then(|c| c.get("text").as_str()) else {
+                    return Ok(())
+                };
+                self.documents.insert(uri.clone(), text.to_string());
+            },
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+            },
+            _ => return Ok(())
+        }
+        self.publish_diagnostics(&uri)
+    }
+
+    fn publish_diagnostics(&mut self, uri: &str) -> io::Result<()> {
+        let diagnostics = match self.documents.get(uri) {
+            Some(text) => {
+                let a = analyze(text);
+                let mut diagnostics: Vec<_> = a.errors.iter()
+                    .map(|e| diagnostic(uri, text, e))
+                    .collect();
+                if let Some(crash) = a.crash {
+                    diagnostics.push(Json::object([
+                        ("range", range(text, 0..0)),
+                        ("severity", 1.into()),
+                        ("source", "fig".into()),
+                        ("message", format!("internal compiler error: {}", crash).into())
+                    ]));
+                }
+                diagnostics
+            },
+            // clear out whatever we said about a closed document
+            None => vec![]
+        };
+        send(&mut self.out, &Json::object([
+            ("jsonrpc", "2.0".into()),
+            ("method", "textDocument/publishDiagnostics".into()),
+            ("params", Json::object([
+                ("uri", uri.into()),
+                ("diagnostics", Json::Array(diagnostics))
+            ]))
+        ]))
+    }
+
+    /// Analyzes the document named by a `TextDocumentPositionParams` and
+    /// asks `answer` about what's at that position.
+    fn at_position(&self, params: &Json, answer: fn(&Query) -> Option<Json>) -> Json {
+        let uri = params.get("textDocument").get("uri").as_str().unwrap_or_default();
+        let Some(text) = self.documents.get(uri) else { return Json::Null };
+        let Some(at) = offset(text, params.get("position")) else { return Json::Null };
+
+        let analysis = analyze(text);
+        let Some(found) = find_block(&analysis.stmts, at) else { return Json::Null };
+        answer(&Query { uri, text, syms: &analysis.syms, found }).unwrap_or(Json::Null)
+    }
+}
+
+/// The results of checking a document, as far as we got
+struct Analysis<'a> {
+    stmts: Vec<Statement<'a>>,
+    syms: SymbolTable<'a>,
+    errors: Vec<CompileError<'a>>,
+    /// What the compiler said if it crashed on the way
+    crash: Option<String>
+}
+
+fn analyze(text: &str) -> Analysis<'_> {
+    let mut a = Analysis {
+        stmts: vec![],
+        syms: SymbolTable::new(),
+        errors: vec![],
+        crash: None
+    };
+    let checked = panic::catch_unwind(AssertUnwindSafe(|| {
+        let (stmts, errors) = Parser::new(text).parse();
+        a.stmts = stmts;
+        if !errors.is_empty() {
+            a.errors = errors;
+            return
+        }
+        if let Err(d) = fig::analyze(&mut a.syms, &mut Scope::new(), &mut a.stmts) {
+            a.errors = d.errors;
+        }
+        a.errors.append(&mut lint::lint(&a.stmts));
+    }));
+    if let Err(payload) = checked {
+        let msg = payload.downcast_ref::<&str>().map(|s| s.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_default();
+        a.crash = Some(msg);
+    }
+    a
+}
+
+/// The smallest thing in a document that covers some position
+enum Found<'n, 'a> {
+    Node(&'n AstNode<'a>),
+    /// The name in a `let`, of a parameter, a loop label, or a struct being
+    /// built
+    Declaration(&'n Sp<'a, Reference<'a>>)
+}
+
+impl Found<'_, '_> {
+    fn span(&self) -> Range<usize> {
+        match self {
+            Found::Node(n) => n.span.clone(),
+            Found::Declaration(d) => d.span.clone()
+        }
+    }
+}
+
+struct Query<'q, 'a> {
+    uri: &'q str,
+    text: &'q str,
+    syms: &'q SymbolTable<'a>,
+    found: Found<'q, 'a>
+}
+
+fn hover(q: &Query) -> Option<Json> {
+    let (name, ty) = match q.found {
+        Found::Node(n) => match &*n.kind {
+            AstNodeKind::Reference(_) => (Some(n.text()), n.type_data?),
+            _ => (None, n.type_data?)
+        },
+        Found::Declaration(d) => {
+            let Reference::Resolved(id) = d.data else { return None };
+            match q.syms.tbl.get(&id).map(|s| &s.data) {
+                Some(Symbol::Variable { ty: Some(ty) }) => (Some(d.text()), *ty),
+                _ => return None
+            }
+        }
+    };
+    let ty = q.syms.type_name(ty);
+    let shown = match name {
+        Some(name) => format!("{}: {}", name, ty),
+        None => ty.to_string()
+    };
+    Some(Json::object([
+        ("contents", Json::object([
+            ("kind", "markdown".into()),
+            ("value", format!("```fig\n{}\n```", shown).into())
+        ])),
+        ("range", range(q.text, q.found.span()))
+    ]))
+}
+
+fn definition(q: &Query) -> Option<Json> {
+    let id = match q.found {
+        Found::Node(n) => match &*n.kind {
+            AstNodeKind::Reference(Reference::Resolved(id)) => *id,
+            _ => return None
+        },
+        Found::Declaration(d) => match d.data {
+            Reference::Resolved(id) => id,
+            _ => return None
+        }
+    };
+    let sym = q.syms.tbl.get(&id)?;
+    // builtins don't live anywhere the client could open
+    if !std::ptr::eq(sym.of, q.text) {
+        return None
+    }
+    Some(Json::object([
+        ("uri", q.uri.into()),
+        ("range", range(q.text, sym.span.clone()))
+    ]))
+}
+
+fn find_block<'n, 'a>(stmts: &'n [Statement<'a>], at: usize) -> Option<Found<'n, 'a>> {
+    let mut best = None;
+    for stmt in stmts {
+        let found = match stmt {
+            Statement::Declare { id, value, .. } => {
+                if covers(&id.span, at) {
+                    Some(Found::Declaration(id))
+                } else {
+                    find(value, at)
+                }
+            },
+            Statement::Function { params, body, .. } => params.iter()
+                .find(|p| covers(&p.id.span, at))
+                .map(|p| Found::Declaration(&p.id))
+                .or_else(|| find(body, at)),
+            // labels take you to the loop they name, like a declaration would
+            Statement::Break { label: Some(l), .. } | Statement::Continue { label: Some(l), .. } if covers(&l.span, at) => {
+                Some(Found::Declaration(l))
+            },
+            Statement::Expression(e)
+                | Statement::Return(e)
+                | Statement::Out(e)
+                | Statement::Break { with: Some(e), .. } => find(e, at),
+            _ => None
+        };
+        best = smallest(best, found);
+    }
+    best
+}
+
+fn find<'n, 'a>(node: &'n AstNode<'a>, at: usize) -> Option<Found<'n, 'a>> {
+    if !covers(&node.span, at) {
+        return None
+    }
+    let mut best = Some(Found::Node(node));
+    let children = match &*node.kind {
+        AstNodeKind::BinOp { a, b, .. } => vec![find(a, at), find(b, at)],
+        AstNodeKind::UnOp { target, .. } => vec![find(target, at)],
+        AstNodeKind::If { condition, body, else_body } => vec![
+            find(condition, at),
+            find(body, at),
+            else_body.as_ref().and_then(|e| find(e, at))
+        ],
+        AstNodeKind::Loop { label, body } => vec![
+            label.as_ref().filter(|l| covers(&l.span, at)).map(Found::Declaration),
+            find(body, at)
+        ],
+        AstNodeKind::While { label, condition, body } => vec![
+            label.as_ref().filter(|l| covers(&l.span, at)).map(Found::Declaration),
+            find(condition, at),
+            find(body, at)
+        ],
+        AstNodeKind::For { label, var, from, to, body, .. } => vec![
+            label.as_ref().filter(|l| covers(&l.span, at)).map(Found::Declaration),
+            covers(&var.span, at).then_some(Found::Declaration(var)),
+            find(from, at),
+            find(to, at),
+            find(body, at)
+        ],
+        AstNodeKind::Block { stmts } => vec![find_block(stmts, at)],
+        AstNodeKind::Call { callee, args } => {
+            std::iter::once(callee).chain(args).map(|n| find(n, at)).collect()
+        },
+        AstNodeKind::Interpolation { parts } => parts.iter().map(|n| find(n, at)).collect(),
+        // the struct's name takes you to it, like a variable would
+        AstNodeKind::StructLiteral { name, fields } => std::iter::once(covers(&name.span, at).then_some(Found::Declaration(name)))
+            .chain(fields.iter().map(|f| find(&f.value, at)))
+            .collect(),
+        AstNodeKind::Field { target, .. } => vec![find(target, at)],
+        AstNodeKind::Match { value, arms } => std::iter::once(find(value, at))
+            .chain(arms.iter().flat_map(|arm| [
+                match &arm.pattern {
+                    Pattern::Value(v) => find(v, at),
+                    Pattern::Binding(id) => covers(&id.span, at).then_some(Found::Declaration(id)),
+                    Pattern::Wildcard(..) => None
+                },
+                find(&arm.body, at)
+            ]))
+            .collect(),
+        _ => vec![]
+    };
+    for c in children {
+        best = smallest(best, c);
+    }
+    best
+}
+
+fn smallest<'n, 'a>(a: Option<Found<'n, 'a>>, b: Option<Found<'n, 'a>>) -> Option<Found<'n, 'a>> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(if b.span().len() < a.span().len() { b } else { a }),
+        (a, b) => a.or(b)
+    }
+}
+
+/// Whether a cursor at `at` is touching `span`, including just after its end.
+fn covers(span: &Range<usize>, at: usize) -> bool {
+    span.start <= at && at <= span.end
+}
+
+fn diagnostic(uri: &str, text: &str, e: &CompileError) -> Json {
+    let related = e.labels.iter().map(|l| Json::object([
+        ("location", Json::object([
+            ("uri", uri.into()),
+            ("range", range(text, l.at.span.clone()))
+        ])),
+        ("message", l.message.as_str().into())
+    ]));
+    Json::object([
+        ("range", range(text, e.at.span.clone())),
+        ("severity", match e.severity {
+            Severity::Error => 1,
+            Severity::Warning => 2
+        }.into()),
+        ("code", e.code.into()),
+        ("source", "fig".into()),
+        ("message", e.message.as_str().into()),
+        ("relatedInformation", Json::Array(related.collect()))
+    ])
+}
+
+fn range(text: &str, span: Range<usize>) -> Json {
+    Json::object([
+        ("start", position(text, span.start)),
+        ("end", position(text, span.end))
+    ])
+}
+
+/// Positions are zero-based, and count UTF-16 code units along the line.
+fn position(text: &str, byte: usize) -> Json {
+    let before = text.get(..byte).unwrap_or(text);
+    let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+    let character: usize = before[line_start..].chars().map(char::len_utf16).sum();
+    Json::object([
+        ("line", before.matches('\n').count().into()),
+        ("character", character.into())
+    ])
+}
+
+/// The byte offset of a position, clamped to the end of its line.
+fn offset(text: &str, pos: &Json) -> Option<usize> {
+    let line = pos.get("line").as_usize()?;
+    let character = pos.get("character").as_usize()?;
+
+    let mut line_start = 0;
+    for _ in 0..line {
+        line_start += text[line_start..].find('\n')? + 1;
+    }
+    let mut units = 0;
+    for (i, ch) in text[line_start..].char_indices() {
+        if units >= character || ch == '\n' {
+            return Some(line_start + i)
+        }
+        units += ch.len_utf16();
+    }
+    Some(text.len())
+}
+
+/// Reads the body of the next message, or `None` at the end of input.
+fn read_message(input: &mut impl BufRead) -> io::Result<Option<String>> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None)
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+    let Some(length) = length else {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "message without a `Content-Length`"))
+    };
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+    String::from_utf8(body)
+        .map(Some)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "message isn't valid UTF-8"))
+}
+
+fn send(out: &mut impl Write, msg: &Json) -> io::Result<()> {
+    let body = msg.to_string();
+    write!(out, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    out.flush()
+}
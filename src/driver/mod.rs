@@ -0,0 +1,308 @@
+//! The Driver
+
+use std::{fs, io::{IsTerminal, Write}, mem, path::{Path, PathBuf}, process::{self, ExitCode}, thread};
+
+use fig::{fe::{ast::print_statements, codes, cst, lexer::Lexer, Severity}, json::Json, Diagnostics, Options};
+
+use self::{args::{Args, Command, MessageFormat}, link::{Scratch, Toolchain}, emit::Stage};
+
+pub mod args;
+pub mod link;
+pub mod emit;
+pub mod repl;
+pub mod lsp;
+pub mod test;
+
+pub fn main() -> ExitCode {
+    let args = match args::parse(std::env::args().skip(1)) {
+        Ok(a) => a,
+        Err(e) => {
+            eprintln!("error: {}\n\n{}", e, args::USAGE);
+            return ExitCode::from(2)
+        }
+    };
+
+    match args.command {
+        Command::Help => {
+            println!("{}", args::USAGE);
+            ExitCode::SUCCESS
+        },
+        Command::Check => match compile(&args, None) {
+            Ok(_) => ExitCode::SUCCESS,
+            Err(_) => ExitCode::FAILURE
+        },
+        Command::Build | Command::Run => build(&args),
+        Command::Fmt => format(&args),
+        Command::Test => test::run(&args),
+        Command::Doc => document(&args),
+        Command::Repl => match repl::run() {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                eprintln!("error: {}", e);
+                ExitCode::FAILURE
+            }
+        },
+        Command::Lsp => lsp::run().unwrap_or_else(|e| {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }),
+        Command::Explain => explain(&args.input.to_string_lossy())
+    }
+}
+
+/// Prints the long explanation of an error code. `e302`, `302` and `E0302`
+/// are all the same code.
+fn explain(code: &str) -> ExitCode {
+    let digits = code.trim_start_matches(['E', 'e']);
+    let code = format!("E{:0>4}", digits);
+    match codes::explain(&code) {
+        Some(text) => {
+            print!("{}", text);
+            ExitCode::SUCCESS
+        },
+        None => {
+            eprintln!("error: `{}` isn't a Fig error code (they go from {} to {})", code, codes::ALL[0], codes::ALL[codes::ALL.len() - 1]);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// How much stack the evaluator gets. Every call in the code it runs takes
+/// a few of its own calls, which take a lot of room in debug builds.
+const EVAL_STACK: usize = 64 << 20;
+
+/// Runs `f`, which evaluates code, on a thread with room for it to recurse.
+/// A panic comes back as an error.
+pub fn with_eval_stack<T: Send>(f: impl FnOnce() -> T + Send) -> thread::Result<T> {
+    thread::scope(|s| {
+        thread::Builder::new()
+            .stack_size(EVAL_STACK)
+            .spawn_scoped(s, f)
+            .expect("couldn't start a thread to evaluate on")
+            .join()
+    })
+}
+
+/// Reads the input file. Bytes that aren't UTF-8 become `\u{FFFD}`, which the
+/// lexer then reports where they are.
+pub fn read_input(args: &Args) -> Result<String, ()> {
+    let bytes = fs::read(&args.input).map_err(|e| {
+        eprintln!("error: couldn't read `{}`: {}", args.input.display(), e);
+    })?;
+    match String::from_utf8(bytes) {
+        Ok(prog) => Ok(prog),
+        Err(e) => {
+            eprintln!("warning: `{}` isn't valid UTF-8", args.input.display());
+            Ok(String::from_utf8_lossy(e.as_bytes()).into_owned())
+        }
+    }
+}
+
+/// Compiles the input file, writing assembly to `asm_out`.
+/// If there's nowhere to write to, we stop after type checking.
+fn compile(args: &Args, asm_out: Option<&Path>) -> Result<(), ()> {
+    let prog = read_input(args)?;
+    let emit = |stage, write: &mut dyn FnMut(&mut dyn Write) -> std::io::Result<()>| {
+        emit::emit(&args.emit, &args.input, stage, write)
+            .map_err(|e| eprintln!("error: {}", e))
+    };
+
+    emit(Stage::Tokens, &mut |out| {
+        let lexer = Lexer::new(&prog);
+        for t in lexer {
+            writeln!(out, "{}:{}\t{:?}\t{:?}", t.line, t.col, t.span, t.data)?;
+        }
+        Ok(())
+    })?;
+    emit(Stage::Cst, &mut |mut out| cst::parse(&prog).print(&mut out, 0))?;
+
+    let options = Options { check_only: asm_out.is_none() };
+    let mut artifact = match fig::compile(&prog, &options) {
+        Ok(a) => a,
+        Err(d) => {
+            report(args, &d);
+            return Err(())
+        }
+    };
+    if !artifact.warnings.is_empty() {
+        report(args, &Diagnostics { errors: mem::take(&mut artifact.warnings) });
+    }
+
+    emit(Stage::Ast, &mut |mut out| print_statements(&mut out, &artifact.symbols, 0, artifact.statements()))?;
+
+    let Some(asm_out) = asm_out else { return Ok(()) };
+
+    if let Some(ir) = &artifact.ir {
+        emit(Stage::Ir, &mut |mut out| ir.print(&mut out))?;
+    }
+    if let Some(ir) = &artifact.colored_ir {
+        emit(Stage::IrColored, &mut |mut out| ir.print(&mut out))?;
+    }
+
+    let asm = artifact.asm.as_deref().unwrap_or_default();
+    if let Err(e) = fs::write(asm_out, asm) {
+        eprintln!("error: couldn't write `{}`: {}", asm_out.display(), e);
+        return Err(())
+    }
+    emit(Stage::Asm, &mut |out| out.write_all(asm.as_bytes()))
+}
+
+pub fn report(args: &Args, diagnostics: &Diagnostics) {
+    let file = args.input.display().to_string();
+    for e in &diagnostics.errors {
+        match args.message_format {
+            MessageFormat::Human => eprintln!("{}", e.render(&file, use_color())),
+            MessageFormat::Json => {
+                let (line, col) = e.at.line_col(e.at.span.start);
+                let (end_line, end_col) = e.at.line_col(e.at.span.end);
+                let labels = e.labels.iter().map(|l| {
+                    let (line, col) = l.at.line_col(l.at.span.start);
+                    Json::object([
+                        ("message", l.message.as_str().into()),
+                        ("byte_start", l.at.span.start.into()),
+                        ("byte_end", l.at.span.end.into()),
+                        ("line", line.into()),
+                        ("column", col.into())
+                    ])
+                });
+                println!("{}", Json::object([
+                    ("file", file.as_str().into()),
+                    ("severity", e.severity.name().into()),
+                    ("code", e.code.into()),
+                    ("message", e.message.as_str().into()),
+                    ("byte_start", e.at.span.start.into()),
+                    ("byte_end", e.at.span.end.into()),
+                    ("line", line.into()),
+                    ("column", col.into()),
+                    ("end_line", end_line.into()),
+                    ("end_column", end_col.into()),
+                    ("labels", Json::Array(labels.collect()))
+                ]));
+            }
+        }
+    }
+    if args.message_format == MessageFormat::Human {
+        let warnings = diagnostics.errors.iter().filter(|e| e.severity == Severity::Warning).count();
+        let errors = diagnostics.errors.len() - warnings;
+        if errors == 0 {
+            eprintln!("warning: `{}` generated {} warning(s)", file, warnings);
+        } else if warnings == 0 {
+            eprintln!("error: could not compile `{}` due to {} previous error(s)", file, errors);
+        } else {
+            eprintln!("error: could not compile `{}` due to {} previous error(s); {} warning(s) emitted", file, errors, warnings);
+        }
+        // lints go by name, and aren't explained
+        let mut codes: Vec<_> = diagnostics.errors.iter()
+            .filter_map(|e| e.code)
+            .filter(|c| codes::explain(c).is_some())
+            .collect();
+        codes.sort();
+        codes.dedup();
+        match codes[..] {
+            [] => {},
+            [code] => eprintln!("for more information about this error, try `fig explain {}`", code),
+            [first, ..] => eprintln!(
+                "some errors have detailed explanations: {}\nfor more information about an error, try `fig explain {}`",
+                codes.join(", "), first
+            )
+        }
+    }
+}
+
+/// Whether diagnostics on stderr should be coloured.
+pub fn use_color() -> bool {
+    std::io::stderr().is_terminal() && std::env::var_os("NO_COLOR").is_none()
+}
+
+/// Writes documentation for the input file to the output directory.
+fn document(args: &Args) -> ExitCode {
+    let Ok(prog) = read_input(args) else { return ExitCode::FAILURE };
+    let artifact = match fig::compile(&prog, &Options { check_only: true }) {
+        Ok(a) => a,
+        Err(d) => {
+            report(args, &d);
+            return ExitCode::FAILURE
+        }
+    };
+
+    let title = args.input.file_stem().unwrap_or_default().to_string_lossy();
+    let page = fig::doc::document(&title, &prog, &artifact.symbols, artifact.statements());
+    let dir = args.output.clone().unwrap_or_else(|| PathBuf::from("doc"));
+    let written = fs::create_dir_all(&dir)
+        .and_then(|_| fs::write(dir.join(format!("{}.md", title)), page.markdown()))
+        .and_then(|_| fs::write(dir.join(format!("{}.html", title)), page.html()));
+    if let Err(e) = written {
+        eprintln!("error: couldn't write documentation to `{}`: {}", dir.display(), e);
+        return ExitCode::FAILURE
+    }
+    println!("documented `{}` in `{}`", args.input.display(), dir.display());
+    ExitCode::SUCCESS
+}
+
+/// Formats the input file in place, or checks that it already is.
+fn format(args: &Args) -> ExitCode {
+    let Ok(prog) = read_input(args) else { return ExitCode::FAILURE };
+    let formatted = match fig::fmt::format(&prog) {
+        Ok(f) => f,
+        Err(d) => {
+            report(args, &d);
+            return ExitCode::FAILURE
+        }
+    };
+
+    if formatted == prog {
+        return ExitCode::SUCCESS
+    }
+    if args.check {
+        eprintln!("error: `{}` isn't formatted, run `fig fmt` on it", args.input.display());
+        return ExitCode::FAILURE
+    }
+    if let Err(e) = fs::write(&args.input, formatted) {
+        eprintln!("error: couldn't write `{}`: {}", args.input.display(), e);
+        return ExitCode::FAILURE
+    }
+    ExitCode::SUCCESS
+}
+
+/// Compiles and links the input file into an executable, then runs it
+/// if we were asked to.
+fn build(args: &Args) -> ExitCode {
+    let scratch = match Scratch::new(args.save_temps) {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            return ExitCode::FAILURE
+        }
+    };
+
+    let asm = scratch.path.join("prog.s");
+    if compile(args, Some(&asm)).is_err() {
+        return ExitCode::FAILURE
+    }
+
+    let exe = match (&args.output, &args.command) {
+        (Some(o), _) => o.clone(),
+        (None, Command::Run) => scratch.path.join("prog"),
+        (None, _) => args.input.with_extension("")
+    };
+    let linked = Toolchain::locate(args.cc.as_deref(), args.assembler.as_deref())
+        .and_then(|t| t.link(&asm, &scratch.path, &exe));
+    if let Err(e) = linked {
+        eprintln!("error: {}", e);
+        return ExitCode::FAILURE
+    }
+
+    if args.command != Command::Run {
+        return ExitCode::SUCCESS
+    }
+
+    // a bare file name would send `Command` searching the PATH
+    let exe = if exe.is_relative() { Path::new(".").join(exe) } else { exe };
+    match process::Command::new(&exe).status() {
+        Ok(s) => ExitCode::from(s.code().unwrap_or(1) as u8),
+        Err(e) => {
+            eprintln!("error: couldn't run `{}`: {}", exe.display(), e);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,147 @@
+//! The Interactive Prompt
+
+use std::{io::{self, BufRead, Write}, panic};
+
+use fig::{
+    fe::{ast::Statement, lexer::Lexer, scope::Scope, symbols::SymbolTable, token::Token, CompileError},
+    eval::Evaluator
+};
+
+const HELP: &str = "\
+Enter statements or expressions to evaluate them. Input continues onto the next
+line until every bracket is closed.
+
+    :help   show this message
+    :quit   leave (so does Ctrl-D)";
+
+/// Everything the prompt remembers between inputs. Inputs, and the code made
+/// from them, are leaked so the symbol table, scope and evaluator can keep
+/// borrowing from them for the whole session.
+struct Session {
+    syms: SymbolTable<'static>,
+    scope: Scope<'static>,
+    evaluator: Evaluator<'static, 'static>
+}
+
+pub fn run() -> io::Result<()> {
+    println!("fig {} (type :help for help)", env!("CARGO_PKG_VERSION"));
+
+    // a bug in the compiler shouldn't take the whole session down with it
+    panic::set_hook(Box::new(|info| {
+        let msg = info.payload().downcast_ref::<&str>().map(|s| s.to_string())
+            .or_else(|| info.payload().downcast_ref::<String>().cloned())
+            .unwrap_or_default();
+        eprintln!("internal compiler error: {}", msg);
+    }));
+
+    let mut session = Session {
+        syms: SymbolTable::new(),
+        scope: Scope::new(),
+        evaluator: Evaluator::new()
+    };
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    let mut buf = String::new();
+
+    loop {
+        print!("{}", if buf.is_empty() { "fig> " } else { "...  " });
+        io::stdout().flush()?;
+
+        let Some(line) = lines.next().transpose()? else { break };
+        if buf.is_empty() {
+            match line.trim() {
+                "" => continue,
+                ":q" | ":quit" => break,
+                ":h" | ":help" => { println!("{}", HELP); continue },
+                cmd if cmd.starts_with(':') => {
+                    eprintln!("unknown command `{}`, try :help", cmd);
+                    continue
+                },
+                _ => {}
+            }
+        }
+
+        buf.push_str(&line);
+        buf.push('\n');
+        if open_brackets(&buf) > 0 {
+            continue
+        }
+
+        // kept as it was typed, so that's what errors show
+        let input = std::mem::take(&mut buf).trim_end().to_owned();
+        let input: &'static str = Box::leak(input.into_boxed_str());
+
+        let (items, symbols) = (session.scope.items.len(), session.syms.next_id());
+        if !super::with_eval_stack(|| session.eval(input)).unwrap_or(false) {
+            // forget anything this input declared, it might be half-checked,
+            // or never given a value
+            session.scope.items.truncate(items);
+            session.syms.forget_from(symbols);
+        }
+    }
+
+    println!();
+    Ok(())
+}
+
+impl Session {
+    /// Checks and runs `input`, giving back whether it all went well.
+    fn eval(&mut self, input: &'static str) -> bool {
+        let checked = fig::parse(input).and_then(|mut stmts| {
+            fig::analyze(&mut self.syms, &mut self.scope, &mut stmts).map(|_| stmts)
+        });
+        let stmts: &'static [Statement] = match checked {
+            Ok(s) => s.leak(),
+            Err(d) => {
+                for e in &d.errors {
+                    eprintln!("{}", e.render("<repl>", super::use_color()));
+                }
+                return false
+            }
+        };
+
+        self.evaluator.define(stmts);
+        for (i, stmt) in stmts.iter().enumerate() {
+            let v = match self.evaluator.run_statement(stmt) {
+                Ok(v) => v,
+                Err(e) => {
+                    eprintln!("{}", CompileError::from(e).render("<repl>", super::use_color()));
+                    return false
+                }
+            };
+
+            let shown = match stmt {
+                Statement::Expression(e) | Statement::Return(e) | Statement::Out(e) => e.type_data,
+                _ => None
+            };
+            match (v, shown) {
+                (Some(v), Some(t)) if !v.is_unit() => {
+                    println!("{} : {}", v, self.syms.type_name(t));
+                },
+                _ => {}
+            }
+            if let Statement::Return(..) | Statement::Out(..) = stmt {
+                // nothing after these would run in a real program either
+                if i + 1 < stmts.len() {
+                    eprintln!("note: ignored the statements after this one");
+                }
+                return true
+            }
+        }
+        true
+    }
+}
+
+/// How many more brackets were opened than closed.
+fn open_brackets(src: &str) -> isize {
+    let lexer = Lexer::new(src);
+    let mut depth = 0;
+    for t in lexer {
+        match *t {
+            Token::LBrace | Token::LParen | Token::LBracket => depth += 1,
+            Token::RBrace | Token::RParen | Token::RBracket => depth -= 1,
+            _ => {}
+        }
+    }
+    depth
+}
@@ -0,0 +1,73 @@
+//! The Test Runner
+
+use std::{panic, process::ExitCode};
+
+use fig::{
+    fe::{ast::Statement, CompileError},
+    eval::Evaluator,
+    Diagnostics,
+    Options
+};
+
+use super::args::Args;
+
+/// Runs every `@test` function in the input, each with nothing but itself and
+/// the functions and structs around it. Like any function, a test can't see
+/// the variables declared outside of it, so there's nothing else to set up.
+pub fn run(args: &Args) -> ExitCode {
+    let Ok(prog) = super::read_input(args) else { return ExitCode::FAILURE };
+    let mut artifact = match fig::compile(&prog, &Options { check_only: true }) {
+        Ok(a) => a,
+        Err(d) => {
+            super::report(args, &d);
+            return ExitCode::FAILURE
+        }
+    };
+    if !artifact.warnings.is_empty() {
+        super::report(args, &Diagnostics { errors: std::mem::take(&mut artifact.warnings) });
+    }
+
+    let tests: Vec<_> = artifact.statements().iter()
+        .filter(|s| s.is_test())
+        .filter_map(|s| match s {
+            Statement::Function { name, body, .. } => Some((name, body)),
+            _ => None
+        })
+        .collect();
+    println!("running {} test{}", tests.len(), if tests.len() == 1 { "" } else { "s" });
+
+    let mut failures = vec![];
+    let mut failed = 0;
+    for (name, body) in &tests {
+        let result = super::with_eval_stack(|| {
+            let mut evaluator = Evaluator::new();
+            evaluator.define(artifact.statements());
+            evaluator.run_body(body).map(|_| ())
+        });
+        match result.unwrap_or_else(|e| panic::resume_unwind(e)) {
+            Ok(_) => println!("test {} ... ok", name.data),
+            Err(e) => {
+                println!("test {} ... FAILED", name.data);
+                failures.push((name, e));
+                failed += 1;
+            }
+        }
+    }
+
+    if !failures.is_empty() {
+        println!("\nfailures:");
+        let file = args.input.display().to_string();
+        for (name, e) in failures.drain(..) {
+            println!("\n---- {} ----", name.data);
+            print!("{}", CompileError::from(e).render(&file, false));
+        }
+    }
+    println!(
+        "\ntest result: {}. {} passed; {} failed",
+        if failed == 0 { "ok" } else { "FAILED" },
+        tests.len() - failed,
+        failed
+    );
+
+    if failed == 0 { ExitCode::SUCCESS } else { ExitCode::FAILURE }
+}
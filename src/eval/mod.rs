@@ -0,0 +1,489 @@
+//! The Evaluator
+//!
+//! Walks a resolved and type checked AST directly instead of going through
+//! the backend, for places where we want answers without an arm64 machine.
+
+use std::{collections::HashMap, fmt::Display, mem};
+
+use crate::fe::{ast::{AstNode, AstNodeKind, BinOp, UnOp, Statement, Reference, Param, Pattern, FieldDecl}, symbols::Builtin, CompileError, Sp};
+
+use self::value::Value;
+
+pub mod value;
+
+#[derive(Debug)]
+pub struct EvalError<'a> {
+    pub at: Sp<'a, ()>,
+    pub message: String
+}
+
+impl Display for EvalError<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "error: {} (at `{}`)", self.message, &self.at.of[self.at.span.clone()])
+    }
+}
+
+impl<'a> From<EvalError<'a>> for CompileError<'a> {
+    fn from(e: EvalError<'a>) -> Self {
+        CompileError::error(&e.at, e.message)
+    }
+}
+
+/// Anything that stops evaluation of the current expression early
+enum Unwind<'a> {
+    /// These carry the label of the loop they're going to, if they have one
+    Break(Option<usize>, Value),
+    Continue(Option<usize>),
+    Out(Value),
+    Return(Value),
+    Error(EvalError<'a>)
+}
+
+type Eval<'a, T = Value> = Result<T, Unwind<'a>>;
+
+/// How many calls deep a program can go before we give up on it, rather than
+/// running out of stack ourselves
+const MAX_DEPTH: usize = 1000;
+
+#[derive(Clone, Copy)]
+struct Function<'f, 'a> {
+    params: &'f [Param<'a>],
+    body: &'f AstNode<'a>
+}
+
+#[derive(Clone, Copy)]
+struct Struct<'f, 'a> {
+    name: &'a str,
+    fields: &'f [FieldDecl<'a>]
+}
+
+#[derive(Default)]
+pub struct Evaluator<'f, 'a> {
+    /// Values of variables, by symbol id. Each call gets its own.
+    vars: HashMap<usize, Value>,
+    /// The functions that can be called, by symbol id
+    functions: HashMap<usize, Function<'f, 'a>>,
+    /// The structs that can be made, by symbol id
+    structs: HashMap<usize, Struct<'f, 'a>>,
+    /// How many calls deep we are
+    depth: usize
+}
+
+impl<'f, 'a> Evaluator<'f, 'a> {
+    pub fn new() -> Self {
+        Self { vars: HashMap::new(), functions: HashMap::new(), structs: HashMap::new(), depth: 0 }
+    }
+
+    /// Makes the functions declared in `stmts` callable, and their structs
+    /// usable. Blocks do this for themselves, since what's declared in them
+    /// can be used before it's declared.
+    pub fn define(&mut self, stmts: &'f [Statement<'a>]) {
+        for stmt in stmts {
+            match stmt {
+                Statement::Function { id: Some(id), params, body, .. } => {
+                    self.functions.insert(*id, Function { params, body });
+                },
+                Statement::Struct { id: Some(id), name, fields, .. } => {
+                    let fields = fields.as_deref().unwrap_or_default();
+                    self.structs.insert(*id, Struct { name: name.data, fields });
+                },
+                _ => {}
+            }
+        }
+    }
+
+    /// Runs a top level statement. Expression, `<-` and `return` statements
+    /// produce the value they evaluated to.
+    pub fn run_statement(&mut self, stmt: &'f Statement<'a>) -> Result<Option<Value>, EvalError<'a>> {
+        let result = match stmt {
+            Statement::Expression(e) => self.eval(e).map(Some),
+            _ => self.exec(stmt).map(|_| None)
+        };
+        settle(result)
+    }
+
+    /// Runs a whole program, producing the value it returned.
+    pub fn run(&mut self, stmts: &'f [Statement<'a>]) -> Result<Value, EvalError<'a>> {
+        self.define(stmts);
+        for stmt in stmts {
+            if let Err(u) = self.exec(stmt) {
+                return settle(Err(u)).map(|v| v.unwrap_or(Value::Unit))
+            }
+        }
+        Ok(Value::Unit)
+    }
+
+    /// Runs the body of a function, producing the value it returned.
+    pub fn run_body(&mut self, body: &'f AstNode<'a>) -> Result<Value, EvalError<'a>> {
+        settle(self.eval(body).map(Some)).map(|v| v.unwrap_or(Value::Unit))
+    }
+
+    fn exec(&mut self, stmt: &'f Statement<'a>) -> Eval<'a, ()> {
+        match stmt {
+            Statement::Declare { id, value, .. } => {
+                let v = self.eval(value)?;
+                self.vars.insert(id.data.clone().unwrap_resolved(), v);
+            },
+            Statement::Expression(e) => { self.eval(e)?; },
+            Statement::Return(e) => return Err(Unwind::Return(self.eval(e)?)),
+            Statement::Out(e) => return Err(Unwind::Out(self.eval(e)?)),
+            Statement::Break { label, with, .. } => {
+                let v = match with {
+                    Some(w) => self.eval(w)?,
+                    None => Value::Unit
+                };
+                return Err(Unwind::Break(label_id(label), v))
+            },
+            Statement::Continue { label, .. } => return Err(Unwind::Continue(label_id(label))),
+            Statement::Function { .. } | Statement::Struct { .. } => self.define(std::slice::from_ref(stmt)),
+            Statement::Import { .. } | Statement::Error => {}
+        }
+        Ok(())
+    }
+
+    fn eval(&mut self, node: &'f AstNode<'a>) -> Eval<'a> {
+        Ok(match &*node.kind {
+            AstNodeKind::Value(c) => Value::from_constant(c),
+            AstNodeKind::Reference(Reference::Builtin(b)) => {
+                return fail(node, format!("`{}` can only be called", b.name()))
+            },
+            AstNodeKind::Reference(r) => {
+                let id = r.clone().unwrap_resolved();
+                match (self.vars.get(&id), self.structs.get(&id)) {
+                    (Some(v), _) => v.clone(),
+                    (None, Some(s)) => Value::Struct { name: s.name.into(), fields: vec![] },
+                    (None, None) => return fail(node, "use of a variable before it was given a value")
+                }
+            },
+            AstNodeKind::BinOp { a, b, op } => self.binary(a, b, op)?,
+            AstNodeKind::UnOp { op, target } => {
+                let t = self.eval(target)?;
+                match (&**op, t) {
+                    (UnOp::Negate, Value::Int { value, bits }) => Value::int(value.wrapping_neg(), bits),
+                    (UnOp::Negate, Value::Float(f)) => Value::Float(-f),
+                    (UnOp::Not, Value::Bool(b)) => Value::Bool(!b),
+                    (op, t) => return fail(node, format!("can't apply {:?} to {}", op, t))
+                }
+            },
+            AstNodeKind::If { condition, body, else_body } => {
+                match self.eval(condition)? {
+                    Value::Bool(true) => self.eval(body)?,
+                    Value::Bool(false) => match else_body {
+                        Some(eb) => self.eval(eb)?,
+                        None => Value::Unit
+                    },
+                    other => return fail(condition, format!("expected a boolean condition, got {}", other))
+                }
+            },
+            AstNodeKind::Match { value, arms } => {
+                let v = self.eval(value)?;
+                let mut result = None;
+                for arm in arms {
+                    let matched = match &arm.pattern {
+                        Pattern::Value(p) => self.eval(p)? == v,
+                        Pattern::Binding(id) => {
+                            self.vars.insert(id.data.clone().unwrap_resolved(), v.clone());
+                            true
+                        },
+                        Pattern::Wildcard(..) => true
+                    };
+                    if matched {
+                        result = Some(self.eval(&arm.body)?);
+                        break
+                    }
+                }
+                match result {
+                    Some(r) => r,
+                    None => return fail(value, format!("no arm of the `match` matched {}", v))
+                }
+            },
+            AstNodeKind::Loop { label, body } => loop {
+                if let Some(v) = self.iteration(label, body)? {
+                    break v
+                }
+            },
+            AstNodeKind::While { label, condition, body } => loop {
+                match self.eval(condition)? {
+                    Value::Bool(true) => {},
+                    Value::Bool(false) => break Value::Unit,
+                    other => return fail(condition, format!("expected a boolean condition, got {}", other))
+                }
+                if let Some(v) = self.iteration(label, body)? {
+                    break v
+                }
+            },
+            AstNodeKind::For { label, var, from, to, inclusive, body } => {
+                // counting in something wider than any integer, so going past the end can't overflow
+                let (mut i, end, signed, bits) = match (self.eval(from)?, self.eval(to)?) {
+                    (Value::Int { value: a, bits }, Value::Int { value: b, .. }) => (a as i128, b as i128, true, bits),
+                    (Value::UInt { value: a, bits }, Value::UInt { value: b, .. }) => (a as i128, b as i128, false, bits),
+                    (a, b) => return fail(node, format!("can't count from {} to {}", a, b))
+                };
+                let id = var.data.clone().unwrap_resolved();
+                loop {
+                    if i > end || (i == end && !inclusive) {
+                        break Value::Unit
+                    }
+                    let value = if signed { Value::int(i as i64, bits) } else { Value::uint(i as u64, bits) };
+                    self.vars.insert(id, value);
+                    if let Some(v) = self.iteration(label, body)? {
+                        break v
+                    }
+                    i += 1;
+                }
+            },
+            AstNodeKind::Block { stmts } => {
+                self.define(stmts);
+                for s in stmts {
+                    match self.exec(s) {
+                        Ok(()) => {},
+                        Err(Unwind::Out(v)) => return Ok(v),
+                        Err(e) => return Err(e)
+                    }
+                }
+                Value::Unit
+            },
+            AstNodeKind::Call { callee, args } => match (&*callee.kind, &args[..]) {
+                (AstNodeKind::Reference(Reference::Builtin(Builtin::Assert)), [cond]) => {
+                    match self.eval(cond)? {
+                        Value::Bool(true) => Value::Unit,
+                        Value::Bool(false) => return fail(node, format!("assertion failed: `{}`", cond.text())),
+                        other => return fail(cond, format!("expected a boolean, got {}", other))
+                    }
+                },
+                (AstNodeKind::Reference(Reference::Resolved(id)), args) => self.call(node, *id, args)?,
+                _ => return fail(callee, "only functions can be called")
+            },
+            AstNodeKind::Interpolation { parts } => {
+                let mut text = String::new();
+                for p in parts {
+                    match self.eval(p)? {
+                        Value::String(s) => text.push_str(&s),
+                        other => text.push_str(&other.to_string())
+                    }
+                }
+                Value::String(text.into())
+            },
+            AstNodeKind::StructLiteral { name, fields } => {
+                let Some(s) = self.structs.get(&name.data.clone().unwrap_resolved()).copied() else {
+                    return fail(name, "only structs can be made with `{ .. }`")
+                };
+                let mut given = vec![];
+                for f in fields {
+                    given.push((f.name.data, self.eval(&f.value)?));
+                }
+                let mut values = vec![];
+                for decl in s.fields {
+                    match given.iter().position(|(n, _)| *n == decl.name.data) {
+                        Some(i) => values.push((decl.name.data.into(), given.swap_remove(i).1)),
+                        None => return fail(node, format!("`{}` wasn't given a value", decl.name.data))
+                    }
+                }
+                Value::Struct { name: s.name.into(), fields: values }
+            },
+            AstNodeKind::Field { target, name } => match self.eval(target)? {
+                Value::Struct { fields, .. } => match fields.into_iter().find(|(n, _)| **n == *name.data) {
+                    Some((_, v)) => v,
+                    None => return fail(name, format!("there's no field called `{}`", name.data))
+                },
+                other => return fail(target, format!("{} doesn't have any fields", other))
+            },
+            AstNodeKind::Error => return fail(node, "tried to evaluate a faulty AST")
+        })
+    }
+
+    /// Calls the function with the symbol `id`. It gets variables of its own,
+    /// and can't see the caller's.
+    fn call(&mut self, at: &AstNode<'a>, id: usize, args: &'f [AstNode<'a>]) -> Eval<'a> {
+        let Some(Function { params, body }) = self.functions.get(&id).copied() else {
+            return fail(at, "only functions can be called")
+        };
+        if self.depth == MAX_DEPTH {
+            return fail(at, format!("calls went more than {} deep", MAX_DEPTH))
+        }
+        let mut frame = HashMap::new();
+        for (p, a) in params.iter().zip(args) {
+            frame.insert(p.id.data.clone().unwrap_resolved(), self.eval(a)?);
+        }
+
+        let caller = mem::replace(&mut self.vars, frame);
+        self.depth += 1;
+        let result = self.eval(body);
+        self.depth -= 1;
+        self.vars = caller;
+        match result {
+            Ok(v) | Err(Unwind::Return(v)) => Ok(v),
+            Err(Unwind::Break(..) | Unwind::Continue(..) | Unwind::Out(..)) => {
+                fail(at, "`break` or `continue` outside of a loop")
+            },
+            Err(e) => Err(e)
+        }
+    }
+
+    /// Runs the body of a loop once, giving back what it was broken out of
+    /// with, if it was.
+    fn iteration(&mut self, label: &Option<Sp<'a, Reference<'a>>>, body: &'f AstNode<'a>) -> Eval<'a, Option<Value>> {
+        let ours = |to: Option<usize>| to.is_none() || to == label_id(label);
+        match self.eval(body) {
+            Ok(_) => Ok(None),
+            Err(Unwind::Continue(to)) if ours(to) => Ok(None),
+            Err(Unwind::Break(to, v)) if ours(to) => Ok(Some(v)),
+            Err(e) => Err(e)
+        }
+    }
+
+    fn binary(&mut self, a: &'f AstNode<'a>, b: &'f AstNode<'a>, op: &Sp<'a, BinOp>) -> Eval<'a> {
+        // these don't just evaluate both sides up front
+        match **op {
+            BinOp::And | BinOp::Or => {
+                let lhs = self.eval(a)?;
+                return match (&**op, lhs) {
+                    (BinOp::And, Value::Bool(false)) => Ok(Value::Bool(false)),
+                    (BinOp::Or, Value::Bool(true)) => Ok(Value::Bool(true)),
+                    (_, Value::Bool(_)) => self.eval(b),
+                    (_, other) => fail(a, format!("expected a boolean, got {}", other))
+                }
+            },
+            BinOp::Assign => {
+                let v = self.eval(b)?;
+                self.assign(a, v)?;
+                return Ok(Value::Unit)
+            },
+            BinOp::AddEq | BinOp::SubEq | BinOp::MulEq | BinOp::DivEq | BinOp::PowEq | BinOp::ModEq => {
+                let lhs = self.eval(a)?;
+                let rhs = self.eval(b)?;
+                let base = match **op {
+                    BinOp::AddEq => BinOp::Add,
+                    BinOp::SubEq => BinOp::Sub,
+                    BinOp::MulEq => BinOp::Mul,
+                    BinOp::DivEq => BinOp::Div,
+                    BinOp::PowEq => BinOp::Pow,
+                    _ => BinOp::Mod
+                };
+                let v = arith(op, &base, lhs, rhs)?;
+                self.assign(a, v)?;
+                return Ok(Value::Unit)
+            },
+            _ => {}
+        }
+
+        let lhs = self.eval(a)?;
+        let rhs = self.eval(b)?;
+        arith(op, op, lhs, rhs)
+    }
+
+    fn assign(&mut self, to: &AstNode<'a>, v: Value) -> Eval<'a, ()> {
+        match &*to.kind {
+            AstNodeKind::Reference(r) => {
+                self.vars.insert(r.clone().unwrap_resolved(), v);
+            },
+            AstNodeKind::Field { .. } => *self.place(to)? = v,
+            _ => return fail(to, "can only assign to variables and fields")
+        }
+        Ok(())
+    }
+
+    /// The variable or field that `node` refers to, so it can be changed.
+    fn place(&mut self, node: &AstNode<'a>) -> Eval<'a, &mut Value> {
+        match &*node.kind {
+            AstNodeKind::Reference(r) => match self.vars.get_mut(&r.clone().unwrap_resolved()) {
+                Some(v) => Ok(v),
+                None => fail(node, "use of a variable before it was given a value")
+            },
+            AstNodeKind::Field { target, name } => match self.place(target)? {
+                Value::Struct { fields, .. } => match fields.iter_mut().find(|(n, _)| **n == *name.data) {
+                    Some((_, v)) => Ok(v),
+                    None => fail(name, format!("there's no field called `{}`", name.data))
+                },
+                other => fail(target, format!("{} doesn't have any fields", other))
+            },
+            _ => fail(node, "can only assign to variables and fields")
+        }
+    }
+}
+
+fn label_id(label: &Option<Sp<Reference>>) -> Option<usize> {
+    label.as_ref().map(|l| l.data.clone().unwrap_resolved())
+}
+
+/// Turns whatever escaped the top level into a result.
+fn settle<'a>(result: Eval<'a, Option<Value>>) -> Result<Option<Value>, EvalError<'a>> {
+    match result {
+        Ok(v) => Ok(v),
+        Err(Unwind::Out(v) | Unwind::Return(v)) => Ok(Some(v)),
+        Err(Unwind::Error(e)) => Err(e),
+        Err(Unwind::Break(..) | Unwind::Continue(..)) => Err(EvalError {
+            at: Sp::builtin(()),
+            message: "`break` or `continue` outside of a loop".to_string()
+        })
+    }
+}
+
+/// Applies a non-assigning binary operator.
+fn arith<'a>(at: &Sp<'a, BinOp>, op: &BinOp, lhs: Value, rhs: Value) -> Eval<'a> {
+    use Value::*;
+    Ok(match (op, lhs, rhs) {
+        (BinOp::Eq, a, b) => Bool(a == b),
+        (BinOp::NotEq, a, b) => Bool(a != b),
+
+        (BinOp::Div | BinOp::Mod, Int { .. }, Int { value: 0, .. }) | (BinOp::Div | BinOp::Mod, UInt { .. }, UInt { value: 0, .. }) => {
+            return fail(at, "attempt to divide by zero")
+        },
+
+        // worked out in 64 bits, and then cut down to size
+        (op, Int { value: a, bits }, Int { value: b, .. }) => Value::int(match op {
+            BinOp::Add => a.wrapping_add(b),
+            BinOp::Sub => a.wrapping_sub(b),
+            BinOp::Mul => a.wrapping_mul(b),
+            BinOp::Div => a.wrapping_div(b),
+            BinOp::Mod => a.wrapping_rem(b),
+            BinOp::Pow => match u32::try_from(b) {
+                Ok(b) => a.wrapping_pow(b),
+                Err(_) => return fail(at, "exponent out of range")
+            },
+            _ => return Ok(compare(op, a.cmp(&b)))
+        }, bits),
+        (op, UInt { value: a, bits }, UInt { value: b, .. }) => Value::uint(match op {
+            BinOp::Add => a.wrapping_add(b),
+            BinOp::Sub => a.wrapping_sub(b),
+            BinOp::Mul => a.wrapping_mul(b),
+            BinOp::Div => a / b,
+            BinOp::Mod => a % b,
+            BinOp::Pow => match u32::try_from(b) {
+                Ok(b) => a.wrapping_pow(b),
+                Err(_) => return fail(at, "exponent out of range")
+            },
+            _ => return Ok(compare(op, a.cmp(&b)))
+        }, bits),
+        (op, Float(a), Float(b)) => match op {
+            BinOp::Add => Float(a + b),
+            BinOp::Sub => Float(a - b),
+            BinOp::Mul => Float(a * b),
+            BinOp::Div => Float(a / b),
+            BinOp::Mod => Float(a % b),
+            BinOp::Pow => Float(a.powf(b)),
+            _ => match a.partial_cmp(&b) {
+                Some(o) => compare(op, o),
+                None => Bool(false)
+            }
+        },
+        (op, a, b) => return fail(at, format!("can't apply {:?} to {} and {}", op, a, b))
+    })
+}
+
+fn compare(op: &BinOp, o: std::cmp::Ordering) -> Value {
+    Value::Bool(match op {
+        BinOp::Gt => o.is_gt(),
+        BinOp::GtEq => o.is_ge(),
+        BinOp::Lt => o.is_lt(),
+        BinOp::LtEq => o.is_le(),
+        _ => unreachable!("{:?} is not a comparison", op)
+    })
+}
+
+fn fail<'a, T: std::fmt::Debug, R>(at: &Sp<'a, T>, message: impl ToString) -> Eval<'a, R> {
+    Err(Unwind::Error(EvalError {
+        at: at.ditch(),
+        message: message.to_string()
+    }))
+}
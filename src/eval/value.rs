@@ -0,0 +1,91 @@
+use std::{fmt::Display, sync::Arc};
+
+use crate::fe::ast::{ConstantValue, CompInt, CompFloat};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    /// Integers remember how many bits their type has, like
+    /// [`TypeProps::Integer`](crate::fe::symbols::TypeProps::Integer), and
+    /// wrap around to fit in them.
+    Int { value: i64, bits: u8 },
+    UInt { value: u64, bits: u8 },
+    Float(f64),
+    Bool(bool),
+    String(Arc<str>),
+    /// Fields are in the order the struct declares them
+    Struct {
+        name: Arc<str>,
+        fields: Vec<(Arc<str>, Value)>
+    },
+    Unit
+}
+
+impl Value {
+    pub fn from_constant(c: &ConstantValue) -> Value {
+        match c {
+            ConstantValue::String(s) => Value::String(Arc::from(&**s)),
+            ConstantValue::CompInt(i) => match *i {
+                CompInt::I8(i) => Value::int(i as i64, 8),
+                CompInt::I16(i) => Value::int(i as i64, 16),
+                CompInt::I32(i) => Value::int(i as i64, 32),
+                CompInt::I64(i) => Value::int(i, 64),
+                CompInt::ISize(i) => Value::int(i as i64, 0),
+                CompInt::U8(i) => Value::uint(i as u64, 8),
+                CompInt::U16(i) => Value::uint(i as u64, 16),
+                CompInt::U32(i) => Value::uint(i as u64, 32),
+                CompInt::U64(i) => Value::uint(i, 64),
+                CompInt::USize(i) => Value::uint(i as u64, 0),
+            },
+            ConstantValue::CompFloat(f) => match *f {
+                CompFloat::F32(f) => Value::Float(f as f64),
+                CompFloat::F64(f) => Value::Float(f)
+            },
+            ConstantValue::Bool(b) => Value::Bool(*b),
+            ConstantValue::Nil => Value::Unit
+        }
+    }
+
+    /// A signed integer of `bits` bits, wrapping `value` around to fit.
+    pub fn int(value: i64, bits: u8) -> Value {
+        let value = match bits {
+            8 => value as i8 as i64,
+            16 => value as i16 as i64,
+            32 => value as i32 as i64,
+            _ => value
+        };
+        Value::Int { value, bits }
+    }
+
+    /// An unsigned integer of `bits` bits, wrapping `value` around to fit.
+    pub fn uint(value: u64, bits: u8) -> Value {
+        let value = match bits {
+            8 => value as u8 as u64,
+            16 => value as u16 as u64,
+            32 => value as u32 as u64,
+            _ => value
+        };
+        Value::UInt { value, bits }
+    }
+
+    pub fn is_unit(&self) -> bool {
+        matches!(self, Value::Unit)
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Int { value, .. } => write!(f, "{}", value),
+            Value::UInt { value, .. } => write!(f, "{}", value),
+            Value::Float(x) => write!(f, "{:?}", x),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::String(s) => write!(f, "{:?}", s),
+            Value::Struct { name, fields } if fields.is_empty() => write!(f, "{}", name),
+            Value::Struct { name, fields } => {
+                let fields: Vec<_> = fields.iter().map(|(n, v)| format!("{}: {}", n, v)).collect();
+                write!(f, "{} {{ {} }}", name, fields.join(", "))
+            },
+            Value::Unit => write!(f, "()")
+        }
+    }
+}
@@ -1,6 +1,8 @@
-use super::{Sp, symbols::SymbolTable};
+use std::{borrow::Cow, io::{self, Write}};
 
-#[derive(Debug)]
+use super::{Sp, symbols::{SymbolTable, Builtin}};
+
+#[derive(Debug, Clone)]
 pub enum CompInt {
     U8(u8),
     U16(u16),
@@ -14,15 +16,15 @@ pub enum CompInt {
     ISize(isize)
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum CompFloat {
     F32(f32),
     F64(f64)
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum ConstantValue<'a> {
-    String(&'a str),
+    String(Cow<'a, str>),
     CompInt(CompInt),
     CompFloat(CompFloat),
     Bool(bool),
@@ -48,6 +50,13 @@ pub enum BinOp {
     And, Or
 }
 
+impl BinOp {
+    /// Whether it changes its left side: `=`, `+=` and the like.
+    pub fn is_assignment(&self) -> bool {
+        matches!(self, Self::Assign | Self::AddEq | Self::SubEq | Self::MulEq | Self::DivEq | Self::PowEq | Self::ModEq)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum UnOp {
     // prefix
@@ -61,7 +70,8 @@ pub enum UnOp {
 #[derive(Debug, Clone)]
 pub enum Reference<'a> {
     Unresolved(&'a str),
-    Resolved(usize)
+    Resolved(usize),
+    Builtin(Builtin)
 }
 
 impl<'a> Reference<'a> {
@@ -101,10 +111,50 @@ pub enum AstNodeKind<'a> {
         else_body: Option<AstNode<'a>>
     },
     Loop {
+        /// `'name: loop`, which is declared like a variable
+        label: Option<Sp<'a, Reference<'a>>>,
+        body: AstNode<'a>
+    },
+    While {
+        label: Option<Sp<'a, Reference<'a>>>,
+        condition: AstNode<'a>,
+        body: AstNode<'a>
+    },
+    /// `for var in from..to`, or `from..=to` when `to` is included
+    For {
+        label: Option<Sp<'a, Reference<'a>>>,
+        var: Sp<'a, Reference<'a>>,
+        from: AstNode<'a>,
+        to: AstNode<'a>,
+        inclusive: bool,
         body: AstNode<'a>
     },
     Block {
         stmts: Vec<Statement<'a>>
+    },
+    Call {
+        callee: AstNode<'a>,
+        args: Vec<AstNode<'a>>
+    },
+    /// A string with `{..}` in it. The text between them are string values.
+    Interpolation {
+        parts: Vec<AstNode<'a>>
+    },
+    /// `match value { pattern -> result, .. }`, which takes the first arm
+    /// whose pattern matches
+    Match {
+        value: AstNode<'a>,
+        arms: Vec<MatchArm<'a>>
+    },
+    /// `Name { field: value, .. }`
+    StructLiteral {
+        name: Sp<'a, Reference<'a>>,
+        fields: Vec<FieldInit<'a>>
+    },
+    /// `target.name`
+    Field {
+        target: AstNode<'a>,
+        name: Sp<'a, &'a str>
     }
 }
 
@@ -113,6 +163,7 @@ impl<'a> AstNodeKind<'a> {
         Some(match self {
             Self::BinOp { a, b, .. } => (a.ditch(), b.ditch()),
             Self::UnOp { op, target } => (op.ditch(), target.ditch()),
+            Self::Field { target, name } => (target.ditch(), name.ditch()),
             _ => return None
         })
     }
@@ -126,7 +177,7 @@ pub struct RawAstNode<'a> {
     pub type_data: Option<usize>,
 }
 
-impl<'a, 'tr> RawAstNode<'a> {
+impl<'a> RawAstNode<'a> {
     pub fn new(kind: AstNodeKind<'a>) -> Self {
         Self {
             kind: Box::new(kind),
@@ -135,39 +186,119 @@ impl<'a, 'tr> RawAstNode<'a> {
     }
 }
 
+/// `@name` or `@name(args, ...)` in front of an item
+#[derive(Debug)]
+pub struct Attribute<'a> {
+    pub name: Sp<'a, &'a str>,
+    pub args: Vec<Sp<'a, &'a str>>
+}
+
 #[derive(Debug)]
 pub enum ImportElement<'a> {
-    Item(&'a str),
-    Access(&'a str, Vec<ImportElement<'a>>),
+    Item(Sp<'a, &'a str>),
+    Access(Sp<'a, &'a str>, Vec<ImportElement<'a>>),
+}
+
+impl ImportElement<'_> {
+    /// Writes these out the way they'd look in an `import`
+    pub fn describe(elems: &[ImportElement]) -> String {
+        let parts: Vec<_> = elems.iter().map(|e| match e {
+            ImportElement::Item(name) => name.data.to_string(),
+            ImportElement::Access(name, rest) => format!("{}.{}", name.data, ImportElement::describe(rest))
+        }).collect();
+        match &parts[..] {
+            [one] => one.clone(),
+            _ => format!("({})", parts.join(", "))
+        }
+    }
 }
 
 #[derive(Debug)]
 pub enum MaybeTyped<'a> {
     NotTyped,
-    TypeProvided(&'a str),
+    /// The name of a type, which is resolved like any other name when it's
+    /// a struct
+    TypeProvided(Sp<'a, Reference<'a>>),
     TypeResolved(usize)
 }
 
 impl<'a> MaybeTyped<'a> {
     pub fn unwrap_type(&self) -> usize {
         match self {
-            Self::TypeResolved(r) => return *r,
+            Self::TypeResolved(r) => *r,
             _ => panic!("tried to unwrap type but got {:?}", self)
         }
     }
 
     pub fn unwrap_type_str(self) -> &'a str {
         match self {
-            Self::TypeProvided(r) => return r,
+            Self::TypeProvided(r) => r.text(),
             _ => panic!("tried to unwrap type str but got {:?}", self)
         }
     }
 }
 
+#[derive(Debug)]
+pub enum Pattern<'a> {
+    /// A literal, which the value has to be equal to
+    Value(AstNode<'a>),
+    /// A name, which matches anything and holds onto it for the arm
+    Binding(Sp<'a, Reference<'a>>),
+    /// `_`, which matches anything and throws it away
+    Wildcard(Sp<'a, ()>)
+}
+
+impl<'a> Pattern<'a> {
+    pub fn at(&self) -> Sp<'a, ()> {
+        match self {
+            Pattern::Value(v) => v.ditch(),
+            Pattern::Binding(b) => b.ditch(),
+            Pattern::Wildcard(w) => w.ditch()
+        }
+    }
+
+    /// Whether this matches whatever it's given
+    pub fn catches_all(&self) -> bool {
+        matches!(self, Pattern::Binding(..) | Pattern::Wildcard(..))
+    }
+}
+
+/// `pattern -> result` in a `match`
+#[derive(Debug)]
+pub struct MatchArm<'a> {
+    pub pattern: Pattern<'a>,
+    pub body: AstNode<'a>
+}
+
+/// `name: type` in the parameters of a function
+#[derive(Debug)]
+pub struct Param<'a> {
+    pub id: Sp<'a, Reference<'a>>,
+    pub with_type: MaybeTyped<'a>
+}
+
+/// `name: type` in the braces of a `struct`
+#[derive(Debug)]
+pub struct FieldDecl<'a> {
+    pub name: Sp<'a, &'a str>,
+    pub with_type: MaybeTyped<'a>
+}
+
+/// `name: value` in a struct literal
+#[derive(Debug)]
+pub struct FieldInit<'a> {
+    pub name: Sp<'a, &'a str>,
+    pub value: AstNode<'a>
+}
+
 #[derive(Debug)]
 pub enum Statement<'a> {
     Declare {
-        id: Reference<'a>,
+        /// The lines of the `///` comments in front of it
+        docs: Vec<&'a str>,
+        /// Only ever lint levels, like `@allow(..)`
+        attributes: Vec<Attribute<'a>>,
+        id: Sp<'a, Reference<'a>>,
         with_type: MaybeTyped<'a>,
         value: AstNode<'a>
     },
@@ -177,16 +308,52 @@ pub enum Statement<'a> {
     },
     Return(AstNode<'a>),
     Out(AstNode<'a>), // <-
+    /// Without a label, these go to the innermost loop
     Continue {
-        label: Option<usize>
+        keyword: Sp<'a, ()>,
+        label: Option<Sp<'a, Reference<'a>>>
     },
     Break {
-        label: Option<usize>,
+        keyword: Sp<'a, ()>,
+        label: Option<Sp<'a, Reference<'a>>>,
         with: Option<AstNode<'a>>
     },
+    Function {
+        docs: Vec<&'a str>,
+        attributes: Vec<Attribute<'a>>,
+        name: Sp<'a, &'a str>,
+        /// The function's symbol, once names have been resolved
+        id: Option<usize>,
+        params: Vec<Param<'a>>,
+        /// The type after `->`. Without one, the function gives back `unit`,
+        /// unless its body comes straight after the `->`
+        returns: MaybeTyped<'a>,
+        /// Whether the body came straight after `->`, like `fn two() -> 2`.
+        /// What it gives back is then worked out from the body.
+        expression_body: bool,
+        body: AstNode<'a>
+    },
+    Struct {
+        docs: Vec<&'a str>,
+        name: Sp<'a, &'a str>,
+        /// The struct's type, once names have been resolved
+        id: Option<usize>,
+        /// `None` for a struct without braces, like `struct Empty`
+        fields: Option<Vec<FieldDecl<'a>>>
+    },
     Error
 }
 
+impl Statement<'_> {
+    /// Whether this is a function marked with `@test`
+    pub fn is_test(&self) -> bool {
+        match self {
+            Statement::Function { attributes, .. } => attributes.iter().any(|a| *a.name == "test"),
+            _ => false
+        }
+    }
+}
+
 /// The *Beeg* Space String Function®
 fn beegstr(len: u16) -> String {
     let mut s = String::with_capacity(len as usize);
@@ -196,78 +363,140 @@ fn beegstr(len: u16) -> String {
     s
 }
 
-pub fn print_tree(symbols: &SymbolTable, depth: u16, label: &str, node: &AstNode) {
+pub fn print_tree(out: &mut impl Write, symbols: &SymbolTable, depth: u16, label: &str, node: &AstNode) -> io::Result<()> {
     let s = format!("{}{}: ({:?})", beegstr(depth), label, node.type_data);
     match &*node.kind {
         AstNodeKind::BinOp { a, b, op } => {
-            println!("{}BinOp({:?})", s, **op);
-            print_tree(symbols, depth + 1, "a", &a);
-            print_tree(symbols, depth + 1, "b", &b);
+            writeln!(out, "{}BinOp({:?})", s, **op)?;
+            print_tree(out, symbols, depth + 1, "a", a)?;
+            print_tree(out, symbols, depth + 1, "b", b)?;
         },
-        AstNodeKind::Value(v) => { println!("{}Value({:?})", s, v) },
+        AstNodeKind::Value(v) => { writeln!(out, "{}Value({:?})", s, v)? },
         AstNodeKind::UnOp { op, target } => {
-            println!("{}UnOp({:?})", s, **op);
-            print_tree(symbols, depth + 1, "t", &target);
+            writeln!(out, "{}UnOp({:?})", s, **op)?;
+            print_tree(out, symbols, depth + 1, "t", target)?;
         }
         AstNodeKind::Reference(r) => {
-            println!("{}Reference({:?})", s, r);
+            writeln!(out, "{}Reference({:?})", s, r)?;
         },
         AstNodeKind::If { condition, body, else_body } => {
-            println!("{}If", s);
-            print_tree(symbols, depth + 1, "c", condition);
-            print_tree(symbols, depth + 1, "do", body);
+            writeln!(out, "{}If", s)?;
+            print_tree(out, symbols, depth + 1, "c", condition)?;
+            print_tree(out, symbols, depth + 1, "do", body)?;
             if let Some(eb) = else_body {
-                print_tree(symbols, depth + 1, "el", eb);
+                print_tree(out, symbols, depth + 1, "el", eb)?;
             }
         },
         AstNodeKind::Block { stmts } => {
-            print_statements(symbols, depth + 1, stmts);
+            print_statements(out, symbols, depth + 1, stmts)?;
         },
-        AstNodeKind::Loop { body } => {
-            println!("{}Loop", s);
-            print_tree(symbols, depth + 1, "body", body);
+        AstNodeKind::Loop { label, body } => {
+            writeln!(out, "{}Loop({:?})", s, label.as_ref().map(|l| &l.data))?;
+            print_tree(out, symbols, depth + 1, "body", body)?;
+        },
+        AstNodeKind::While { label, condition, body } => {
+            writeln!(out, "{}While({:?})", s, label.as_ref().map(|l| &l.data))?;
+            print_tree(out, symbols, depth + 1, "condition", condition)?;
+            print_tree(out, symbols, depth + 1, "body", body)?;
+        },
+        AstNodeKind::For { label, var, from, to, inclusive, body } => {
+            writeln!(out, "{}For({:?}) {:?} in {}", s, label.as_ref().map(|l| &l.data), var.data, if *inclusive { "..=" } else { ".." })?;
+            print_tree(out, symbols, depth + 1, "from", from)?;
+            print_tree(out, symbols, depth + 1, "to", to)?;
+            print_tree(out, symbols, depth + 1, "body", body)?;
+        },
+        AstNodeKind::Call { callee, args } => {
+            writeln!(out, "{}Call", s)?;
+            print_tree(out, symbols, depth + 1, "f", callee)?;
+            for a in args {
+                print_tree(out, symbols, depth + 1, "arg", a)?;
+            }
         }
+        AstNodeKind::Interpolation { parts } => {
+            writeln!(out, "{}Interpolation", s)?;
+            for p in parts {
+                print_tree(out, symbols, depth + 1, "part", p)?;
+            }
+        },
+        AstNodeKind::Match { value, arms } => {
+            writeln!(out, "{}Match", s)?;
+            print_tree(out, symbols, depth + 1, "value", value)?;
+            for arm in arms {
+                match &arm.pattern {
+                    Pattern::Value(v) => print_tree(out, symbols, depth + 1, "pattern", v)?,
+                    Pattern::Binding(b) => writeln!(out, "{}pattern: {:?}", beegstr(depth + 1), b.data)?,
+                    Pattern::Wildcard(..) => writeln!(out, "{}pattern: _", beegstr(depth + 1))?
+                }
+                print_tree(out, symbols, depth + 1, "->", &arm.body)?;
+            }
+        },
+        AstNodeKind::StructLiteral { name, fields } => {
+            writeln!(out, "{}StructLiteral {:?}", s, name.data)?;
+            for f in fields {
+                print_tree(out, symbols, depth + 1, f.name.data, &f.value)?;
+            }
+        },
+        AstNodeKind::Field { target, name } => {
+            writeln!(out, "{}Field .{}", s, name.data)?;
+            print_tree(out, symbols, depth + 1, "t", target)?;
+        },
         AstNodeKind::Error => {
-            println!("{}Error", s);
+            writeln!(out, "{}Error", s)?;
         }
     }
+    Ok(())
 }
 
-pub fn print_statements(symbols: &SymbolTable, depth: u16, stmts: &Vec<Statement>) {
+pub fn print_statements(out: &mut impl Write, symbols: &SymbolTable, depth: u16, stmts: &[Statement]) -> io::Result<()> {
     let s = beegstr(depth);
     for stmt in stmts {
         match stmt {
-            Statement::Declare { id, with_type, value } => {
-                println!("{}Declare {:?}: {:?}", s, id, with_type);
-                print_tree(symbols, depth + 1, "value", &value);
+            Statement::Declare { id, with_type, value, .. } => {
+                writeln!(out, "{}Declare {:?}: {:?}", s, id.data, with_type)?;
+                print_tree(out, symbols, depth + 1, "value", value)?;
             },
             Statement::Expression(e) => {
-                println!("{}Expression", s);
-                print_tree(symbols, depth + 1, "e", &e);
+                writeln!(out, "{}Expression", s)?;
+                print_tree(out, symbols, depth + 1, "e", e)?;
             },
             Statement::Return(e) => {
-                println!("{}Return", s);
-                print_tree(symbols, depth + 1, "r", &e);
+                writeln!(out, "{}Return", s)?;
+                print_tree(out, symbols, depth + 1, "r", e)?;
             },
             Statement::Out(e) => {
-                println!("{}Out", s);
-                print_tree(symbols, depth + 1, "<-", &e)
+                writeln!(out, "{}Out", s)?;
+                print_tree(out, symbols, depth + 1, "<-", e)?;
             },
             Statement::Import { paths } => {
-                println!("{}Import {:?}", s, paths);
+                writeln!(out, "{}Import {}", s, ImportElement::describe(paths))?;
             },
-            Statement::Break { label, with } => {
-                println!("{}Break({:?})", s, label);
+            Statement::Break { label, with, .. } => {
+                writeln!(out, "{}Break({:?})", s, label.as_ref().map(|l| &l.data))?;
                 if let Some(w) = with {
-                    print_tree(symbols, depth + 1, "with", &w);
+                    print_tree(out, symbols, depth + 1, "with", w)?;
+                }
+            },
+            Statement::Continue { label, .. } => {
+                writeln!(out, "{}Continue({:?})", s, label.as_ref().map(|l| &l.data))?;
+            },
+            Statement::Function { attributes, name, params, returns, body, .. } => {
+                let attrs: Vec<_> = attributes.iter().map(|a| *a.name).collect();
+                writeln!(out, "{}Function {} {:?} -> {:?}", s, name.data, attrs, returns)?;
+                for p in params {
+                    writeln!(out, "{} Param {:?}: {:?}", s, p.id.data, p.with_type)?;
+                }
+                print_tree(out, symbols, depth + 1, "body", body)?;
+            },
+            Statement::Struct { name, fields, .. } => {
+                writeln!(out, "{}Struct {}", s, name.data)?;
+                for f in fields.iter().flatten() {
+                    writeln!(out, "{} Field {}: {:?}", s, f.name.data, f.with_type)?;
                 }
             },
-            Statement::Continue { label } => {
-                println!("{}Continue({:?})", s, label);
-            }
             Statement::Error => {
-                println!("{}Error!", s);
+                writeln!(out, "{}Error!", s)?;
             },
         }
     }
+    Ok(())
 }
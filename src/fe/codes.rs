@@ -0,0 +1,701 @@
+//! Error Codes
+//!
+//! Every diagnostic the frontend reports has a code, so it can be searched for
+//! and looked up with `fig explain`. Codes are never reused: when a problem
+//! stops being an error, its code is retired along with it.
+//!
+//! E00xx come from the lexer, E01xx from the parser, E02xx from name
+//! resolution, E03xx from the type checker and E04xx from the backend.
+
+macro_rules! codes {
+    ($($name:ident = $code:literal: $explanation:expr),+ $(,)?) => {
+        $(
+        pub const $name: &str = $code;
+        )*
+
+        /// Every code there is, in order
+        pub const ALL: &[&str] = &[$($code),*];
+
+        /// The long explanation of a code, with an example of the problem and
+        /// how to fix it.
+        pub fn explain(code: &str) -> Option<&'static str> {
+            match code {
+                $(
+                $code => Some($explanation),
+                )*
+                _ => None
+            }
+        }
+    };
+}
+
+codes!(
+    UNKNOWN_CHARACTER = "E0001": "\
+A character that doesn't mean anything in Fig was found outside of a string or
+a comment.
+
+Erroneous code example:
+
+    let price = 5$;
+
+Take the character out, or put it in a string if it's meant to be text:
+
+    let price = 5;
+    let label = \"5$\";
+",
+    BAD_NUMBER = "E0002": "\
+A number literal couldn't be read, or it's too big for its type. Numbers can be
+written in hex (`0xFF`), octal (`0o17`) or binary (`0b1010`), with `_` to
+separate digits and a suffix like `u8` or `f32` to pick their type. Without a
+suffix, whole numbers are `i64`s and the rest are `f64`s.
+
+Erroneous code example:
+
+    let byte = 256u8;
+
+Use a type that's big enough, or a smaller number:
+
+    let byte = 255u8;
+    let big = 256u16;
+",
+    UNTERMINATED_STRING = "E0003": "\
+A string was opened with a quote but never closed, so it runs until the end of
+the file.
+
+Erroneous code example:
+
+    let greeting = \"hello;
+
+Close it with the same kind of quote it was opened with:
+
+    let greeting = \"hello\";
+",
+    UNTERMINATED_COMMENT = "E0004": "\
+A block comment was opened with `/*` but never closed with `*/`.
+
+Erroneous code example:
+
+    /* work out the answer
+    let answer = 42;
+
+Close the comment where it's meant to end:
+
+    /* work out the answer */
+    let answer = 42;
+
+Block comments nest, so every `/*` inside one needs its own `*/` too:
+
+    /* let a = 1 /* one */ */
+",
+    BAD_ESCAPE = "E0005": "\
+A `\\` in a string was followed by something that isn't an escape. The escapes
+are `\\n`, `\\t`, `\\r`, `\\0`, `\\\\`, `\\\"`, `\\'`, `\\{`, `\\}` and `\\u{..}` with the hex
+code of any character.
+
+Erroneous code example:
+
+    let path = \"C:\\Users\";
+
+Escape the backslash itself:
+
+    let path = \"C:\\\\Users\";
+    let smile = \"\\u{1F600}\";
+",
+    EXPECTED_EXPRESSION = "E0100": "\
+Something was needed to work out a value, but what came next can't start one.
+This often happens when a value is left out of a declaration or an operator is
+missing its right hand side.
+
+Erroneous code example:
+
+    let total = 1 + ;
+
+Fill in what's missing:
+
+    let total = 1 + 2;
+",
+    EXPECTED_SEMICOLON = "E0101": "\
+A statement has to end before the next one starts, either with a line break or
+a `;`. The last statement in a block can end with the block's `}` instead.
+
+Erroneous code example:
+
+    let a = 1 let b = 2
+
+Put them on separate lines, or add a `;` between them:
+
+    let a = 1
+    let b = 2; let c = a + b
+",
+    UNBALANCED_DELIMITER = "E0102": "\
+A bracket was opened and never closed, or closed without having been opened.
+
+Erroneous code example:
+
+    let a = (1 + 2;
+
+Make sure every `(` and `{` has a matching `)` or `}`:
+
+    let a = (1 + 2);
+",
+    BAD_DECLARATION = "E0103": "\
+A `let` statement wasn't written the way Fig expects. A declaration is `let`,
+a name, optionally `:` and a type, then `=` and a value.
+
+Erroneous code example:
+
+    let count 10;
+
+Add the missing parts:
+
+    let count = 10;
+    let total: i64 = 10;
+",
+    BAD_FUNCTION = "E0104": "\
+A function wasn't written the way Fig expects. A function is `fn`, a name, its
+parameters in parentheses, each a name with `:` and a type, then `->` and the
+type it gives back, if it gives anything back, and a body in braces. A function
+whose body is a single expression can put it straight after the `->` instead.
+
+Erroneous code example:
+
+    fn add(a, b) {
+        return a + b;
+    }
+
+Give each parameter a type, and say what the function gives back:
+
+    fn add(a: i64, b: i64) -> i64 {
+        return a + b;
+    }
+
+    fn double(a: i64) -> a * 2
+",
+    BAD_ATTRIBUTE = "E0105": "\
+An attribute was written wrongly, or was put somewhere it doesn't belong.
+Attributes are `@` followed by a name, and go in front of `fn`. Lint levels,
+like `@allow(unused)`, can go in front of `let` as well.
+
+Erroneous code example:
+
+    @test
+    let a = 1;
+
+Put it on a function instead:
+
+    @test
+    fn a_is_one() {
+        let a = 1;
+        assert(a == 1);
+    }
+",
+    EXPECTED_BLOCK = "E0106": "\
+`if`, `else` and loops are always followed by a block in braces, even when
+there's only one statement in it.
+
+Erroneous code example:
+
+    let a = 1;
+    if a > 0 a = 0;
+
+Wrap the body in braces:
+
+    let a = 1;
+    if a > 0 { a = 0; };
+",
+    BAD_IMPORT = "E0107": "\
+An `import` statement wasn't written the way Fig expects. Imports are a path of
+names separated by `.`, and several items can be grouped in parentheses.
+
+Erroneous code example:
+
+    import 5;
+
+Import a name instead (though nothing can be imported just yet, see E0108):
+
+    import math.(sqrt, abs);
+",
+    UNSUPPORTED = "E0108": "\
+The code uses a part of the language that has been planned, but not built yet.
+Right now, that means methods, `nil`, `import` and using a function as a value
+instead of calling it.
+
+Erroneous code example:
+
+    let nothing = nil;
+
+There's no way around this yet other than leaving it out.
+",
+    BAD_FOR = "E0109": "\
+A `for` loop wasn't written the way Fig expects. It takes a name for the loop
+variable, `in`, and a range of integers: `from..to` counts up to just before
+`to`, and `from..=to` includes it.
+
+Erroneous code example:
+
+    for 0..10 {
+    };
+
+Name the variable that counts:
+
+    for i in 0..10 {
+        assert(i < 10);
+    };
+",
+    BAD_MATCH = "E0110": "\
+A `match` wasn't written the way Fig expects. Each arm is a pattern, `->` and
+the result, and arms are separated by commas or line breaks. Patterns can be
+literals like `1`, `-1`, `true` or `\"a\"`, a name that takes whatever the
+value is, or `_`, which matches anything.
+
+Erroneous code example:
+
+    let n = 2;
+    let name = match n {
+        1 => \"one\",
+        _ => \"many\"
+    };
+
+Use `->` between the pattern and the result:
+
+    let n = 2;
+    let name = match n {
+        1 -> \"one\",
+        _ -> \"many\"
+    };
+",
+    BAD_STRUCT = "E0111": "\
+A struct, or a use of one, wasn't written the way Fig expects. A struct is
+declared with its name and `name: type` for each field, separated by commas or
+line breaks, and each field can only be declared once. Struct literals give
+each field a value in the same way, and `.` has to be followed by the name of a
+field.
+
+Erroneous code example:
+
+    struct Point { x: i64, y: i64, x: i64 };
+
+Give each field a name of its own:
+
+    struct Point { x: i64, y: i64, z: i64 };
+",
+    UNRESOLVED_NAME = "E0200": "\
+A name was used that doesn't refer to anything. Either it was never declared,
+it's declared later on, or it was declared in a block that has already ended.
+
+Erroneous code example:
+
+    {
+        let inner = 1;
+    };
+    let outer = inner + 1;
+
+Declare it somewhere that can be seen from where it's used:
+
+    let inner = 1;
+    let outer = inner + 1;
+
+Functions can't use the variables of the code around them either, but they can
+call any function declared in the same block as them, or around it.
+",
+    UNKNOWN_LABEL = "E0201": "\
+A `break` or `continue` named a label that isn't on any of the loops it's in.
+Labels can only be used inside the loop they're on, and not from a function
+declared in that loop.
+
+Erroneous code example:
+
+    'outer: loop {
+        break;
+    };
+    loop {
+        break 'outer;
+    };
+
+Use the label from inside its loop:
+
+    'outer: loop {
+        loop {
+            break 'outer;
+        };
+    };
+",
+    SHADOWED_LABEL = "E0202": "\
+A loop was given the same label as a loop it's inside of, so a `break` or
+`continue` with that label could mean either of them.
+
+Erroneous code example:
+
+    'a: loop {
+        'a: loop {
+            break 'a;
+        };
+    };
+
+Give the inner loop a name of its own:
+
+    'a: loop {
+        'b: loop {
+            break 'a;
+        };
+    };
+",
+    OUTSIDE_LOOP = "E0203": "\
+A `break` or `continue` isn't inside of any loop, so there's nowhere for it to
+go. A function declared in a loop doesn't count as being inside of it.
+
+Erroneous code example:
+
+    let total = 10;
+    break;
+
+Put it inside the loop it's meant for:
+
+    let total = 0;
+    loop {
+        total += 1;
+        if total == 10 { break };
+    };
+",
+    MISMATCHED_OPERANDS = "E0300": "\
+Both sides of an operator have to be the same type. Fig never converts between
+types behind your back.
+
+Erroneous code example:
+
+    let a = 1;
+    let b = 2.5;
+    let c = a * b;
+
+Make both sides the same type:
+
+    let a = 1.0;
+    let b = 2.5;
+    let c = a * b;
+",
+    WRONG_OPERAND_TYPE = "E0301": "\
+An operator was used on a type it doesn't work on. Arithmetic and ordering
+(`<`, `>=` and friends) need numbers, negation needs a signed number, and `!`,
+`&&` and `||` need `bool`s.
+
+Erroneous code example:
+
+    let yes = true;
+    let no = -yes;
+
+Use the operator that's meant for the type:
+
+    let yes = true;
+    let no = !yes;
+",
+    MISMATCHED_DECLARATION = "E0302": "\
+A declaration says its variable has one type, but the value it's given has
+another.
+
+Erroneous code example:
+
+    let done: bool = 0;
+
+Either change the type or change the value:
+
+    let done: bool = false;
+",
+    UNKNOWN_TYPE = "E0303": "\
+A type was named that doesn't exist. The types Fig knows about are `bool`,
+`string`, `i8` to `i64`, `u8` to `u64`, `isize`, `usize`, `f32` and `f64`, along
+with the structs declared where the name is used, or around it.
+
+Erroneous code example:
+
+    let count: int = 0;
+
+Use one of the types that exist:
+
+    let count: i64 = 0;
+",
+    NON_BOOL_CONDITION = "E0304": "\
+A condition has to be a `bool`. Numbers aren't true or false by themselves.
+
+Erroneous code example:
+
+    let left = 3;
+    if left { left = left - 1; };
+
+Compare it with something to get a `bool`:
+
+    let left = 3;
+    if left > 0 { left = left - 1; };
+",
+    BAD_BUILTIN_CALL = "E0305": "\
+A builtin function was used wrongly. Builtins can only be called, and only with
+the arguments they expect: `assert` takes a single `bool`.
+
+Erroneous code example:
+
+    @test
+    fn two_is_even() {
+        assert(2 % 2);
+    }
+
+Pass it what it expects:
+
+    @test
+    fn two_is_even() {
+        assert(2 % 2 == 0);
+    }
+",
+    NOT_CALLABLE = "E0306": "\
+Something was called that isn't a function.
+
+Erroneous code example:
+
+    let a = 1;
+    a();
+
+Only call functions:
+
+    fn one() -> 1
+
+    let a = one();
+",
+    BAD_ARGUMENTS = "E0307": "\
+A function was called with the wrong number of arguments, or with an argument
+that isn't the type of its parameter.
+
+Erroneous code example:
+
+    fn add(a: i64, b: i64) -> a + b
+
+    let three = add(1);
+
+Pass it what it expects:
+
+    fn add(a: i64, b: i64) -> a + b
+
+    let three = add(1, 2);
+",
+    MISMATCHED_RETURN = "E0308": "\
+A function gave back something other than what it says it gives back, either
+with `return` or at the end of its body. Functions without a `-> type` give
+back `unit`.
+
+Erroneous code example:
+
+    fn is_big(n: i64) -> bool {
+        return n;
+    }
+
+Give back the type the function says it does:
+
+    fn is_big(n: i64) -> bool {
+        return n > 100;
+    }
+",
+    NEEDS_RETURN_TYPE = "E0309": "\
+A function that gives back whatever its body does, like `fn two() -> 2`, was
+called before its body had been checked, so what it gives back isn't known
+yet. That happens when it's called from its own body, or from one of those
+functions declared before it.
+
+Erroneous code example:
+
+    fn count(n: i64) -> if n > 0 { <- count(n - 1) } else { <- 0 }
+
+Write out what it gives back:
+
+    fn count(n: i64) -> i64 {
+        return if n > 0 { <- count(n - 1) } else { <- 0 };
+    }
+",
+    MISMATCHED_BREAK = "E0310": "\
+A loop gives back the value it's broken out of with, so every `break` that
+leaves it has to give back the same type. A `break` without a value gives back
+`unit`. `while` and `for` loops can also finish on their own, so they always
+give back `unit`.
+
+Erroneous code example:
+
+    let i = 0;
+    let found = 'search: loop {
+        loop {
+            if i > 10 { break 'search false };
+            i += 1;
+            if i == 7 { break 'search i };
+        };
+    };
+
+Break out with the same type every time:
+
+    let i = 0;
+    let found = 'search: loop {
+        loop {
+            if i > 10 { break 'search false };
+            i += 1;
+            if i == 7 { break 'search true };
+        };
+    };
+",
+    BAD_RANGE = "E0311": "\
+The ends of a range have to be integers of the same type, which the loop
+variable of a `for` loop takes on.
+
+Erroneous code example:
+
+    for i in 0.5..2.5 {
+    };
+
+Use integers at both ends:
+
+    for i in 0..3 {
+        assert(i < 3);
+    };
+",
+    MISMATCHED_PATTERN = "E0312": "\
+A pattern in a `match` is a different type to the value being matched, so it
+could never match.
+
+Erroneous code example:
+
+    let n = 2;
+    let name = match n {
+        \"one\" -> 1,
+        _ -> 0
+    };
+
+Match against values of the right type:
+
+    let n = 2;
+    let name = match n {
+        1 -> \"one\",
+        _ -> \"many\"
+    };
+",
+    MISMATCHED_ARMS = "E0313": "\
+Every arm of a `match` has to give back the same type, since any of them could
+be the one that runs.
+
+Erroneous code example:
+
+    let n = 2;
+    let name = match n {
+        1 -> \"one\",
+        _ -> n
+    };
+
+Give back the same type from every arm:
+
+    let n = 2;
+    let name = match n {
+        1 -> \"one\",
+        _ -> \"{n}\"
+    };
+",
+    NON_EXHAUSTIVE = "E0314": "\
+A `match` doesn't have an arm for every value it could be given. Unless its
+patterns cover every value, like `true` and `false` for a `bool`, the last arm
+has to be a name or `_` to catch everything else.
+
+Erroneous code example:
+
+    let n = 2;
+    let name = match n {
+        1 -> \"one\",
+        2 -> \"two\"
+    };
+
+Add an arm for everything else:
+
+    let n = 2;
+    let name = match n {
+        1 -> \"one\",
+        2 -> \"two\",
+        _ -> \"many\"
+    };
+",
+    UNKNOWN_FIELD = "E0315": "\
+A field was used that the value doesn't have. Only structs have fields, and only
+the ones they're declared with.
+
+Erroneous code example:
+
+    struct Point { x: i64, y: i64 };
+    let p = Point { x: 1, y: 2 };
+    let z = p.z;
+
+Use one of the fields the struct has:
+
+    struct Point { x: i64, y: i64 };
+    let p = Point { x: 1, y: 2 };
+    let y = p.y;
+",
+    BAD_STRUCT_LITERAL = "E0316": "\
+A struct literal has to give every field of the struct a value of the field's
+type, and only once. A struct without any fields is written as just its name,
+but one with fields can't be.
+
+Erroneous code example:
+
+    struct Point { x: i64, y: i64 };
+    let p = Point { x: 1 };
+
+Give every field a value:
+
+    struct Point { x: i64, y: i64 };
+    let p = Point { x: 1, y: 0 };
+",
+    RECURSIVE_STRUCT = "E0317": "\
+A struct contains itself, either directly or through the fields of another
+struct. Fields are stored inside the struct they're in, so it would never end.
+
+Erroneous code example:
+
+    struct Node { value: i64, next: Node };
+
+Keep what it would contain somewhere else:
+
+    struct Node { value: i64 };
+    let first = Node { value: 1 };
+    let second = Node { value: 2 };
+",
+    NOT_ASSIGNABLE = "E0318": "\
+Only variables and their fields can be assigned to, with `=` or the likes of
+`+=`. Anything else is a value that isn't kept anywhere, so changing it would
+do nothing.
+
+Erroneous code example:
+
+    let a = 1;
+    a + 1 = 2;
+
+Assign to the variable instead:
+
+    let a = 1;
+    a = 2 - 1;
+",
+    MISMATCHED_BRANCHES = "E0319": "\
+Both branches of an `if` have to give back the same type, since either of them
+could be the one that runs. An `if` without an `else` gives back `unit`.
+
+Erroneous code example:
+
+    let n = 2;
+    let size = if n > 10 { <- \"big\" } else { <- n };
+
+Give back the same type from both branches:
+
+    let n = 2;
+    let size = if n > 10 { <- \"big\" } else { <- \"small\" };
+",
+    NOT_NATIVE = "E0400": "\
+The code is fine, and `fig test` and the REPL can run it, but it uses something
+that can't be compiled to native code yet. Right now, that means floats, string
+interpolation, comparing or matching strings, `assert` outside of tests, and
+functions that need more values at once than the processor has registers for.
+
+Erroneous code example:
+
+    let half = 0.5;
+
+There's no way around this yet other than leaving it out when building.
+",
+);
@@ -0,0 +1,270 @@
+//! The Concrete Syntax Tree
+//!
+//! Keeps every byte of the source: the whitespace and comments the parser
+//! throws away are attached to the tokens around them as trivia, so tools can
+//! change one part of a file and write the rest back exactly as it was.
+//!
+//! The tree itself only knows about statements and brackets, which is as much
+//! structure as can be found without understanding the code.
+
+use std::{fmt::Display, io::{self, Write}, iter::Peekable, vec::IntoIter};
+
+use super::{lexer::Lexer, token::{CommentType, Token}, Sp};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Trivia {
+    /// Spaces, tabs and stray carriage returns
+    Whitespace,
+    Newline,
+    /// Without the newline that ends it, if it's a line comment
+    Comment(CommentType)
+}
+
+/// A token with everything around it. Trailing trivia runs up to the end of
+/// the line, and anything after that leads into the next token.
+#[derive(Debug)]
+pub struct FullToken<'a> {
+    pub leading: Vec<Sp<'a, Trivia>>,
+    pub token: Sp<'a, Token<'a>>,
+    pub trailing: Vec<Sp<'a, Trivia>>
+}
+
+impl Display for FullToken<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for t in &self.leading {
+            f.write_str(t.text())?;
+        }
+        f.write_str(self.token.text())?;
+        for t in &self.trailing {
+            f.write_str(t.text())?;
+        }
+        Ok(())
+    }
+}
+
+/// Lexes `source` without losing anything. The last token is always
+/// `Token::Nothing`, which holds whatever comes after the last real one.
+pub fn tokens(source: &str) -> Vec<FullToken<'_>> {
+    let end = source.len();
+    let eof = Sp::new(source, end..end, Token::Nothing);
+
+    let mut out: Vec<FullToken> = vec![];
+    // trivia since the last token
+    let mut pending = vec![];
+    let mut at = 0;
+    for t in Lexer::new(source).chain(Some(eof)) {
+        whitespace(source, at..t.span.start, &mut pending);
+        at = t.span.end;
+        if let Token::Comment(ty, _) = t.data {
+            // line comments take their newline with them
+            let text = t.text();
+            let newline = text.len() - text.trim_end_matches(['\n', '\r']).len();
+            pending.push(Sp::new(source, t.span.start..t.span.end - newline, Trivia::Comment(ty)));
+            whitespace(source, t.span.end - newline..t.span.end, &mut pending);
+            continue
+        }
+
+        if let Some(prev) = out.last_mut() {
+            let line_end = pending.iter()
+                .position(|t| t.data == Trivia::Newline)
+                .map_or(pending.len(), |i| i + 1);
+            prev.trailing = pending.drain(..line_end).collect();
+        }
+        out.push(FullToken { leading: std::mem::take(&mut pending), token: t, trailing: vec![] });
+    }
+    out
+}
+
+/// Splits the whitespace in `span` into trivia. `\r\n` is one newline.
+fn whitespace<'a>(source: &'a str, span: std::ops::Range<usize>, out: &mut Vec<Sp<'a, Trivia>>) {
+    let mut start = span.start;
+    for (i, ch) in source[span.clone()].char_indices() {
+        if ch == '\n' {
+            let i = span.start + i;
+            let cr = if source[start..i].ends_with('\r') { i - 1 } else { i };
+            if start < cr {
+                out.push(Sp::new(source, start..cr, Trivia::Whitespace));
+            }
+            out.push(Sp::new(source, cr..i + 1, Trivia::Newline));
+            start = i + 1;
+        }
+    }
+    if start < span.end {
+        out.push(Sp::new(source, start..span.end, Trivia::Whitespace));
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NodeKind {
+    File,
+    /// Ends with its `;` if it has one. Line breaks that end statements are
+    /// trivia of the statement's last token.
+    Statement,
+    /// A bracket, what's in it and the bracket that closes it, if there is
+    /// one. What's in braces is statements.
+    Group
+}
+
+#[derive(Debug)]
+pub enum Element<'a> {
+    Node(Node<'a>),
+    Token(FullToken<'a>)
+}
+
+impl Display for Element<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Element::Node(n) => n.fmt(f),
+            Element::Token(t) => t.fmt(f)
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Node<'a> {
+    pub kind: NodeKind,
+    pub children: Vec<Element<'a>>
+}
+
+/// Writes out exactly the source the node was made from.
+impl Display for Node<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for c in &self.children {
+            c.fmt(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl Node<'_> {
+    pub fn print(&self, out: &mut impl Write, depth: usize) -> io::Result<()> {
+        let indent = "  ".repeat(depth);
+        writeln!(out, "{}{:?}", indent, self.kind)?;
+        for c in &self.children {
+            match c {
+                Element::Node(n) => n.print(out, depth + 1)?,
+                Element::Token(t) => {
+                    let trivia = |ts: &[Sp<Trivia>]| ts.iter()
+                        .map(|t| format!("{:?} {:?}", t.data, t.text()))
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    writeln!(
+                        out, "{}  {:?} {:?} [{}] [{}]",
+                        indent, t.token.data, t.token.text(), trivia(&t.leading), trivia(&t.trailing)
+                    )?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Builds the concrete syntax tree of `source`. This never fails: code that
+/// doesn't parse still ends up in the tree, just not where it was meant to go.
+pub fn parse(source: &str) -> Node<'_> {
+    let mut builder = Builder { source, tokens: tokens(source).into_iter().peekable() };
+    let mut children = builder.statements(false);
+    children.extend(builder.tokens.map(Element::Token));
+    Node { kind: NodeKind::File, children }
+}
+
+struct Builder<'a> {
+    source: &'a str,
+    tokens: Peekable<IntoIter<FullToken<'a>>>
+}
+
+impl<'a> Builder<'a> {
+    /// Reads statements until the end of the file, or with `nested`, until
+    /// whatever closes the group they're in.
+    fn statements(&mut self, nested: bool) -> Vec<Element<'a>> {
+        let mut stmts = vec![];
+        while let Some(t) = self.tokens.peek() {
+            match t.token.data {
+                Token::Nothing => break,
+                Token::RBrace | Token::RParen | Token::RBracket if nested => break,
+                _ => stmts.push(Element::Node(self.statement(nested)))
+            }
+        }
+        stmts
+    }
+
+    /// Reads a statement the way the parser would end it: at a `;`, at a line
+    /// break that stands for one, or before the end of the group it's in.
+    fn statement(&mut self, nested: bool) -> Node<'a> {
+        let mut children = vec![];
+        // where the last token ended, and whether a statement could end there
+        let mut last: Option<(usize, bool)> = None;
+        while let Some(t) = self.tokens.peek() {
+            let closer = matches!(t.token.data, Token::RBrace | Token::RParen | Token::RBracket);
+            if matches!(t.token.data, Token::Nothing) || (closer && nested) {
+                break
+            }
+            if let Some((end, true)) = last {
+                let line_break = self.source[end..t.token.span.start].contains('\n');
+                if line_break && t.token.can_start_stmt() && !attributes_only(&children) {
+                    break
+                }
+            }
+
+            let t = self.tokens.next().unwrap();
+            let semicolon = matches!(t.token.data, Token::Semicolon);
+            last = Some((t.token.span.end, t.token.can_end_stmt()));
+            if matches!(t.token.data, Token::LBrace | Token::LParen | Token::LBracket) {
+                let group = self.group(t);
+                if let Some(Element::Token(close)) = group.children.last() {
+                    last = Some((close.token.span.end, close.token.can_end_stmt()));
+                }
+                children.push(Element::Node(group));
+            } else {
+                children.push(Element::Token(t));
+            }
+            if semicolon || (closer && !nested) {
+                break
+            }
+        }
+        Node { kind: NodeKind::Statement, children }
+    }
+
+    fn group(&mut self, open: FullToken<'a>) -> Node<'a> {
+        let braces = matches!(open.token.data, Token::LBrace);
+        let mut children = vec![Element::Token(open)];
+        if braces {
+            children.extend(self.statements(true));
+        } else {
+            while let Some(t) = self.tokens.peek() {
+                match t.token.data {
+                    Token::Nothing | Token::RBrace | Token::RParen | Token::RBracket => break,
+                    Token::LBrace | Token::LParen | Token::LBracket => {
+                        let open = self.tokens.next().unwrap();
+                        children.push(Element::Node(self.group(open)));
+                    },
+                    _ => children.push(Element::Token(self.tokens.next().unwrap()))
+                }
+            }
+        }
+        if self.tokens.peek().is_some_and(|t| matches!(t.token.data, Token::RBrace | Token::RParen | Token::RBracket)) {
+            children.push(Element::Token(self.tokens.next().unwrap()));
+        }
+        Node { kind: NodeKind::Group, children }
+    }
+}
+
+/// Whether a statement so far is only attributes, which go on the line before
+/// what they're for.
+fn attributes_only(children: &[Element]) -> bool {
+    let token = |i: usize| match children.get(i) {
+        Some(Element::Token(t)) => Some(&t.token.data),
+        _ => None
+    };
+    let mut i = 0;
+    while i < children.len() {
+        if !matches!((token(i), token(i + 1)), (Some(Token::At), Some(Token::Identifier(..)))) {
+            return false
+        }
+        i += 2;
+        if let Some(Element::Node(Node { kind: NodeKind::Group, .. })) = children.get(i) {
+            i += 1;
+        }
+    }
+    !children.is_empty()
+}
@@ -1,13 +1,57 @@
-use std::ops::Range;
+use std::{fmt::Display, ops::Range};
 
-use super::{token::{Token, CommentType}, Sp};
+use super::{token::{Token, CommentType}, codes, Sp};
 
 #[derive(Debug)]
 pub enum LexError {
     UnknownCharacter(char),
-    NumberParseFailed,
+    /// Bigger than even a `u64`
+    NumberTooBig,
+    /// A prefix like `0x` or an exponent with nothing after it
+    MissingDigits(&'static str),
+    /// A digit that's too big for the base, like the `2` in `0b102`
+    BadDigit(char, u32),
+    UnknownSuffix(String),
+    /// A suffix that can't go on this kind of number, and why
+    WrongSuffix(String, &'static str),
     UnterminatedString(String),
-    UnterminatedComment(String)
+    UnterminatedComment(String),
+    UnknownEscape(char),
+    /// `\u` that isn't followed by `{`, up to six hex digits of a valid character, and `}`
+    BadUnicodeEscape
+}
+
+impl LexError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            LexError::UnknownCharacter(..) => codes::UNKNOWN_CHARACTER,
+            LexError::NumberTooBig
+                | LexError::MissingDigits(..)
+                | LexError::BadDigit(..)
+                | LexError::UnknownSuffix(..)
+                | LexError::WrongSuffix(..) => codes::BAD_NUMBER,
+            LexError::UnterminatedString(..) => codes::UNTERMINATED_STRING,
+            LexError::UnterminatedComment(..) => codes::UNTERMINATED_COMMENT,
+            LexError::UnknownEscape(..) | LexError::BadUnicodeEscape => codes::BAD_ESCAPE
+        }
+    }
+}
+
+impl Display for LexError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LexError::UnknownCharacter(ch) => write!(f, "unknown character `{}`", ch.escape_debug()),
+            LexError::NumberTooBig => write!(f, "this number is too big for any integer type, which go up to `u64`"),
+            LexError::MissingDigits(after) => write!(f, "expected digits after `{}`", after),
+            LexError::BadDigit(digit, base) => write!(f, "`{}` isn't a digit in base {}", digit, base),
+            LexError::UnknownSuffix(suffix) => write!(f, "unknown number suffix `{}`, it has to be a number type like `u8` or `f32`", suffix),
+            LexError::WrongSuffix(suffix, why) => write!(f, "{} can't be a `{}`", why, suffix),
+            LexError::UnterminatedString(quote) => write!(f, "unterminated string, expected a closing {}", quote),
+            LexError::UnterminatedComment(end) => write!(f, "unterminated comment, expected a closing `{}`", end.escape_debug()),
+            LexError::UnknownEscape(ch) => write!(f, "unknown escape `\\{}`", ch.escape_debug()),
+            LexError::BadUnicodeEscape => write!(f, "unicode escapes look like `\\u{{1F600}}`, with at most six hex digits")
+        }
+    }
 }
 
 fn is_ident_char(ch: char, start: bool) -> bool {
@@ -22,32 +66,26 @@ fn is_digit(ch: char) -> bool {
 pub struct Lexer<'a> {
     pub prog: &'a str,
     at: usize,
+    /// Where to stop, which is only ever short of the end for code in strings
+    end: usize,
     at_char: usize,
     line: usize,
     col: usize,
     len: usize,
-    semi_insert: bool
+    /// The line of the last token, if it was `break` or `continue`, which a
+    /// label can follow on the same line
+    after_jump: Option<usize>
 }
 
-impl<'a> Lexer<'a> {
-    pub fn new(prog: &'a str) -> Lexer<'a> {
-        Self {
-            prog,
-            at: 0,
-            at_char: 0,
-            line: 1,
-            col: 1,
-            len: 0,
-            semi_insert: false
-        }
-    }
+impl<'a> Iterator for Lexer<'a> {
+    type Item = Sp<'a, Token<'a>>;
 
-    pub fn next(&mut self) -> Option<Sp<'a, Token<'a>>> {
+    fn next(&mut self) -> Option<Self::Item> {
         self.skip_whitespace();
-        self.semi_insert = false;
 
         self.len = 0;
         if self.at_end() { return None }
+        let (line, col) = (self.line, self.col);
         use Token::*;
         let token = match self.advance() {
             '+' => self.eq_variant(Add, AddEq),
@@ -66,21 +104,23 @@ impl<'a> Lexer<'a> {
             '/' => {
                 if self.pick('/') {
                     if self.pick('/') {
-                        self.comment(3, CommentType::Doc, "\n")
+                        self.line_comment(3, CommentType::Doc)
                     } else if self.pick('!') {
-                        self.comment(3, CommentType::ModuleDoc, "\n") 
+                        self.line_comment(3, CommentType::ModuleDoc)
                     } else {
-                        self.comment(2, CommentType::Regular, "\n")
+                        self.line_comment(2, CommentType::Regular)
                     }
                 } else if self.pick('*') {
-                    self.comment(2, CommentType::Regular, "*/")
+                    self.block_comment()
                 } else {
                     self.eq_variant(Div, DivEq)
                 }
             },
             '%' => self.eq_variant(Mod, ModEq),
             '.' => { 
-                if is_digit(self.peek()) {
+                if self.pick('.') {
+                    if self.pick('=') { DotDotEq } else { DotDot }
+                } else if is_digit(self.peek()) {
                     self.number(true)
                 } else { Dot }
             }
@@ -97,50 +137,120 @@ impl<'a> Lexer<'a> {
             '(' => LParen, ')' => RParen,
             '[' => LBracket, ']' => RBracket,
             ',' => Comma,
+            ':' => Colon,
             ';' => Semicolon,
-            
-            // '\n' => Newline,
-            
             '@' => At,
-            '"' | '\'' => self.string(),
+            '\'' => self.label_or_string(),
+            '"' => self.string(),
             x if is_ident_char(x, true) => self.identifier(),
             n if is_digit(n) => self.number(false),
             x => Error(LexError::UnknownCharacter(x))
         };
+        if !matches!(token, Comment(..)) {
+            self.after_jump = matches!(token, Break | Continue).then_some(line);
+        }
         Some(Sp {
-            line: self.line,
-            col: self.col,
+            line,
+            col,
             of: self.prog, 
             span: self.lexeme_range(),
             data: token
         })
     }
+}
+
+impl<'a> Lexer<'a> {
+    pub fn new(prog: &'a str) -> Lexer<'a> {
+        Self {
+            prog,
+            at: 0,
+            end: prog.len(),
+            at_char: 0,
+            line: 1,
+            col: 1,
+            len: 0,
+            after_jump: None
+        }
+    }
+
+    /// Lexes only `range` of `prog`, as if everything before it had already
+    /// been read.
+    pub fn within(prog: &'a str, range: Range<usize>) -> Lexer<'a> {
+        let at = range.start;
+        let before = &prog[..at];
+        let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+        Self {
+            at,
+            at_char: before.chars().count(),
+            line: before.matches('\n').count() + 1,
+            col: before[line_start..].chars().count() + 1,
+            end: range.end,
+            ..Lexer::new(prog)
+        }
+    }
+
+    /// `'name` is a label when it comes after `break` or `continue` on the
+    /// same line, or in front of a loop, like `'outer: loop`. Anything else,
+    /// or anything that's closed by a quote later on the line, is a string in
+    /// single quotes.
+    fn label_or_string(&mut self) -> Token<'a> {
+        let rest = &self.prog[self.at..self.end];
+        let name = &rest[..rest.find(|ch| !is_ident_char(ch, false)).unwrap_or(rest.len())];
+        let after = &rest[name.len()..];
+        let line = &rest[..rest.find('\n').unwrap_or(rest.len())];
+        // a name right after the closing quote means it wasn't one, but the
+        // start of another label, like in `'a: loop { break 'a }`
+        let closed = string_len(line, '\'').is_some_and(|len| !line[len..].starts_with(|ch| is_ident_char(ch, false)));
+        let label = name.starts_with(|ch| is_ident_char(ch, true))
+            && !closed
+            && (self.after_jump == Some(self.line) || labels_loop(after));
+        if !label {
+            return self.string()
+        }
+        for _ in name.chars() {
+            self.advance();
+        }
+        Token::Label(name)
+    }
 
+    /// Strings are kept as they're written. Escapes and `{..}` are dealt with
+    /// by the parser, all we need to know is where the string ends.
     fn string(&mut self) -> Token<'a> {
-        let start_seq = self.lexeme();
-        if let Err(e) = self.eat_until(start_seq, LexError::UnterminatedString(start_seq.to_owned())) { return e }
+        let quote = self.lexeme();
+        let Some(len) = string_len(&self.prog[self.at..self.end], quote.chars().next().unwrap_or('"')) else {
+            while !self.at_end() { self.advance(); }
+            return Token::Error(LexError::UnterminatedString(quote.to_owned()))
+        };
+        let end = self.at + len;
+        while self.at < end { self.advance(); }
         let total = self.lexeme();
-        Token::String(&total[start_seq.len()..total.len() - start_seq.len()])
+        Token::String(&total[quote.len()..total.len() - quote.len()])
     }
 
-    fn eat_until(&mut self, end_seq: &str, unterminated_err: LexError) -> Result<(), Token<'a>> {
-        loop {
-            self.advance();
-            let latest_seq = &self.prog.as_bytes()[self.at - end_seq.len()..self.at];
-            if latest_seq == end_seq.as_bytes() {
-                return Ok(());
-            }
+    /// Line comments run up to and including the next line break, or to the
+    /// end of the file.
+    fn line_comment(&mut self, start_len: usize, ty: CommentType) -> Token<'a> {
+        while !self.at_end() && self.advance() != '\n' {}
+        let l = self.lexeme();
+        Token::Comment(ty, l[start_len..].trim_end_matches(['\n', '\r']))
+    }
 
+    /// Block comments nest, so a block of code with comments in it can still
+    /// be commented out.
+    fn block_comment(&mut self) -> Token<'a> {
+        let mut depth = 1;
+        while depth > 0 {
             if self.at_end() {
-                return Err(Token::Error(unterminated_err));
+                return Token::Error(LexError::UnterminatedComment("*/".to_owned()))
+            }
+            match self.advance() {
+                '/' if self.pick('*') => depth += 1,
+                '*' if self.pick('/') => depth -= 1,
+                _ => {}
             }
         }
-    }
-
-    fn comment(&mut self, start_len: usize, ty: CommentType, end_seq: &str) -> Token<'a> {
-        if let Err(e) = self.eat_until(end_seq, LexError::UnterminatedComment(end_seq.to_owned())) { return e }
         let l = self.lexeme();
-        Token::Comment(ty, &l[start_len..l.len()-end_seq.len()])
+        Token::Comment(CommentType::Regular, &l[2..l.len() - 2])
     }
 
     fn identifier(&mut self) -> Token<'a> {
@@ -158,10 +268,11 @@ impl<'a> Lexer<'a> {
             "fn" => Fn,
             "for" => For,
             "while" => While,
+            "in" => In,
             "loop" => Loop,
-            "break" => { self.semi_insert = true; Break },
-            "continue" => { self.semi_insert = true; Continue },
-            "return" => { self.semi_insert = true; Return },
+            "break" => Break,
+            "continue" => Continue,
+            "return" => Return,
             "enum" => Enum,
             "struct" => Struct,
             "pub" => Pub,
@@ -169,9 +280,9 @@ impl<'a> Lexer<'a> {
             "match" => Match,
             "default" => Default,
 
-            "true" => { self.semi_insert = true; True },
-            "false" => { self.semi_insert = true; False },
-            "nil" => { self.semi_insert = true; Nil },
+            "true" => True,
+            "false" => False,
+            "nil" => Nil,
 
             _ => Identifier(ident)
         }
@@ -179,22 +290,76 @@ impl<'a> Lexer<'a> {
 
     // dotted = whether the number started with a dot
     fn number(&mut self, dotted: bool) -> Token<'a> {
-        if !dotted {
-            while is_digit(self.peek()) { self.advance(); }
+        let (radix, prefix) = match (dotted, self.lexeme(), self.peek()) {
+            (false, "0", 'x') => (16, "0x"),
+            (false, "0", 'o') => (8, "0o"),
+            (false, "0", 'b') => (2, "0b"),
+            _ => (10, "")
+        };
+        if radix != 10 {
+            self.advance();
         }
-        if dotted || self.pick('.') {
-            while is_digit(self.peek()) { self.advance(); }
-            let Ok(parsed) = self.lexeme().parse::<f64>() else {
-                return Token::Error(LexError::NumberParseFailed)
-            };
-            Token::CompFloat(parsed)
-        } else {
-            let Ok(parsed) = self.lexeme().parse::<i64>() else {
-                return Token::Error(LexError::NumberParseFailed)
-            };
-            Token::CompInt(parsed)
+        let start = self.at - self.len + prefix.len();
+
+        self.digits(radix);
+        let mut float = dotted;
+        if radix == 10 {
+            // `1..2` is a range, not `1.` and then `.2`
+            if !dotted && self.peek() == '.' && self.peek_to(1) != '.' {
+                self.advance();
+                float = true;
+                self.digits(10);
+            }
+            if self.pick('e') || self.pick('E') {
+                float = true;
+                if !self.pick('-') { self.pick('+'); }
+                if !is_digit(self.peek()) {
+                    return Token::Error(LexError::MissingDigits("e"))
+                }
+                self.digits(10);
+            }
+        }
+        let digits = self.prog[start..self.at].replace('_', "");
+        if digits.is_empty() {
+            return Token::Error(LexError::MissingDigits(prefix))
+        }
+
+        // anything stuck to the digits is read too, it's either a suffix or a mistake
+        let suffix_start = self.at;
+        while self.peek().is_ascii_alphanumeric() || self.peek() == '_' {
+            self.advance();
+        }
+        let suffix = &self.prog[suffix_start..self.at];
+        if let Some(ch) = suffix.chars().next().filter(char::is_ascii_digit) {
+            return Token::Error(LexError::BadDigit(ch, radix))
         }
+        let suffix = (!suffix.is_empty()).then_some(suffix);
 
+        let float_suffix = match suffix {
+            None => false,
+            Some("f32" | "f64") => true,
+            Some("i8" | "i16" | "i32" | "i64" | "isize" | "u8" | "u16" | "u32" | "u64" | "usize") => false,
+            Some(other) => return Token::Error(LexError::UnknownSuffix(other.to_owned()))
+        };
+        match (float, float_suffix, suffix) {
+            (true, false, Some(s)) => Token::Error(LexError::WrongSuffix(s.to_owned(), "a number with a `.` or an exponent")),
+            (false, true, Some(s)) if radix != 10 => {
+                let why = if radix == 2 { "a binary number" } else { "an octal number" };
+                Token::Error(LexError::WrongSuffix(s.to_owned(), why))
+            },
+            // rust's float parsing is the same as ours, once the `_`s are gone
+            (true, ..) | (_, true, _) => Token::CompFloat(digits.parse().unwrap_or(f64::NAN), suffix),
+            _ => match u64::from_str_radix(&digits, radix) {
+                Ok(n) => Token::CompInt(n, suffix),
+                Err(_) => Token::Error(LexError::NumberTooBig)
+            }
+        }
+    }
+
+    fn digits(&mut self, radix: u32) {
+        while self.peek().is_digit(radix) || self.peek() == '_' {
+            self.advance();
+        }
     }
 
     fn eq_variant(&mut self, base: Token<'a>, with: Token<'a>) -> Token<'a> {
@@ -218,7 +383,7 @@ impl<'a> Lexer<'a> {
     fn skip_whitespace(&mut self) {
         while !self.at_end() {
             match self.peek() {
-                ' ' | '\t' | '\n' => { self.advance(); },
+                ' ' | '\t' | '\n' | '\r' => { self.advance(); },
                 _ => return
             }
         }
@@ -232,7 +397,7 @@ impl<'a> Lexer<'a> {
     }
 
     fn at_end(&self) -> bool {
-        self.peek() == '\0' 
+        self.at >= self.end
     }
 
     fn peek_to_with_loc(&self, n: usize) -> (char, usize) {
@@ -241,10 +406,10 @@ impl<'a> Lexer<'a> {
         for _ in 0..=n {
             char_start = at;
             at += 1;
-            while !self.prog.is_char_boundary(at.min(self.prog.len())) {
+            while !self.prog.is_char_boundary(at.min(self.end)) {
                 at += 1;
             }
-            at = at.min(self.prog.len());
+            at = at.min(self.end);
         }
         (self.prog[char_start..at].chars().next().unwrap_or('\0'), at - self.at)
     }
@@ -259,6 +424,7 @@ impl<'a> Lexer<'a> {
     }
 
     fn advance(&mut self) -> char {
+        if self.at_end() { return '\0' }
         let (ch, dist) = self.peek_to_with_loc(0);
         self.at += dist;
         self.at_char += 1;
@@ -272,3 +438,91 @@ impl<'a> Lexer<'a> {
     }
 }
 
+/// How long the rest of a string is, from just after its opening `quote` up
+/// to and including the closing one.
+pub fn string_len(text: &str, quote: char) -> Option<usize> {
+    scan(text, Some(quote)).or_else(|| {
+        // a `{` that's never closed. Ending at the first quote lets the parser
+        // point at it, instead of everything to the end of the file
+        let mut escaped = false;
+        let end = text.find(|ch| {
+            let end = ch == quote && !escaped;
+            escaped = ch == '\\' && !escaped;
+            end
+        })?;
+        Some(end + quote.len_utf8())
+    })
+}
+
+/// Whether `after` is what follows a label in front of a loop. A quote right
+/// after the keyword, as in `'a: loop'`, makes it a string instead.
+fn labels_loop(after: &str) -> bool {
+    let Some(after) = after.strip_prefix(':') else { return false };
+    let after = after.trim_start_matches([' ', '\t']);
+    ["loop", "while", "for"].iter().any(|kw| after.strip_prefix(kw).is_some_and(|rest| {
+        let keyword = !rest.starts_with(|ch| is_ident_char(ch, false) || ch == '\'');
+        // nothing but a block can follow `loop`
+        keyword && (*kw != "loop" || rest.trim_start().starts_with('{'))
+    }))
+}
+
+/// How long the rest of a `{..}` in a string is, from just after the `{` up to
+/// and including the `}`.
+pub fn interpolation_len(text: &str) -> Option<usize> {
+    scan(text, None)
+}
+
+/// Finds the end of a string, or with no `quote`, the end of some code in a
+/// string. Escapes are skipped, and so are strings in the code.
+fn scan(text: &str, quote: Option<char>) -> Option<usize> {
+    // how many `{` we're inside of
+    let mut depth = quote.is_none() as usize;
+    let mut i = 0;
+    while let Some(ch) = text[i..].chars().next() {
+        i += ch.len_utf8();
+        match ch {
+            '\\' => i += text[i..].chars().next().map_or(0, char::len_utf8),
+            '{' => depth += 1,
+            '}' if depth > 0 => {
+                depth -= 1;
+                if depth == 0 && quote.is_none() {
+                    return Some(i)
+                }
+            },
+            '"' | '\'' if depth > 0 => i += scan(&text[i..], Some(ch))?,
+            ch if Some(ch) == quote => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Works out the escape at the start of `text`, which begins with a `\\`.
+/// Gives back the character it stands for and how many bytes it took up, or
+/// what's wrong with it and how many bytes of it there are to blame.
+pub fn unescape(text: &str) -> Result<(char, usize), (LexError, usize)> {
+    let mut chars = text.chars();
+    chars.next();
+    let Some(ch) = chars.next() else { return Err((LexError::UnknownEscape(' '), 1)) };
+    let len = 1 + ch.len_utf8();
+    Ok((match ch {
+        'n' => '\n',
+        't' => '\t',
+        'r' => '\r',
+        '0' => '\0',
+        '\\' | '"' | '\'' | '{' | '}' => ch,
+        'u' => {
+            // `\u{` hex digits `}`
+            let rest = &text[len..];
+            let end = rest.find('}').filter(|_| rest.starts_with('{'));
+            let Some(end) = end else { return Err((LexError::BadUnicodeEscape, len)) };
+            let digits = &rest[1..end];
+            let valid = (1..=6).contains(&digits.len()) && digits.chars().all(|c| c.is_ascii_hexdigit());
+            return match u32::from_str_radix(digits, 16).ok().filter(|_| valid).and_then(char::from_u32) {
+                Some(ch) => Ok((ch, len + end + 1)),
+                None => Err((LexError::BadUnicodeEscape, len + end + 1))
+            }
+        },
+        _ => return Err((LexError::UnknownEscape(ch), len))
+    }, len))
+}
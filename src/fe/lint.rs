@@ -0,0 +1,346 @@
+//! The Linter
+//!
+//! Looks over checked code for things that are allowed, but probably aren't
+//! what was meant. Each lint warns by default, and `@allow(..)`, `@warn(..)`
+//! and `@deny(..)` on a function or a `let` change that for everything in it.
+
+use std::collections::HashMap;
+
+use super::{
+    ast::{AstNode, AstNodeKind, Attribute, ImportElement, Pattern, Reference, Statement},
+    CompileError, Severity, Sp
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Lint {
+    UnusedVariables,
+    UnreachableCode,
+    Shadowing
+}
+
+impl Lint {
+    pub const ALL: [Lint; 3] = [Lint::UnusedVariables, Lint::UnreachableCode, Lint::Shadowing];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Lint::UnusedVariables => "unused_variables",
+            Lint::UnreachableCode => "unreachable_code",
+            Lint::Shadowing => "shadowing"
+        }
+    }
+
+    /// The lints a name in `@allow(..)` stands for. `unused` covers every lint
+    /// about something unused.
+    fn named(name: &str) -> Option<Vec<Lint>> {
+        match name {
+            "unused" => Some(vec![Lint::UnusedVariables]),
+            _ => Lint::ALL.iter().find(|l| l.name() == name).map(|l| vec![*l])
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Level {
+    Allow,
+    Warn,
+    Deny
+}
+
+/// Lints a checked program. Denied lints come back as errors.
+pub fn lint<'a>(stmts: &[Statement<'a>]) -> Vec<CompileError<'a>> {
+    let mut linter = Linter {
+        uses: HashMap::new(),
+        levels: vec![],
+        visible: vec![],
+        barrier: 0,
+        warnings: vec![]
+    };
+    for stmt in stmts {
+        linter.count_uses(stmt);
+    }
+    linter.block(stmts);
+    linter.warnings
+}
+
+struct Linter<'a> {
+    /// How many times each variable is used
+    uses: HashMap<usize, usize>,
+    /// Levels set by the attributes we're inside of, innermost last
+    levels: Vec<(Lint, Level)>,
+    /// The variables that can be seen from here, and where they were declared
+    visible: Vec<(&'a str, Sp<'a, ()>)>,
+    /// Variables before this belong to an enclosing function, which can't see them
+    barrier: usize,
+    warnings: Vec<CompileError<'a>>
+}
+
+impl<'a> Linter<'a> {
+    fn count_uses(&mut self, stmt: &Statement<'a>) {
+        match stmt {
+            Statement::Declare { value: e, .. }
+                | Statement::Expression(e)
+                | Statement::Return(e)
+                | Statement::Out(e)
+                | Statement::Break { with: Some(e), .. }
+                | Statement::Function { body: e, .. } => self.count_node_uses(e),
+            Statement::Import { .. } | Statement::Continue { .. } | Statement::Break { .. }
+                | Statement::Struct { .. } | Statement::Error => {}
+        }
+    }
+
+    fn count_node_uses(&mut self, node: &AstNode<'a>) {
+        match &*node.kind {
+            AstNodeKind::Reference(Reference::Resolved(id)) => *self.uses.entry(*id).or_default() += 1,
+            AstNodeKind::BinOp { a, b, .. } => {
+                self.count_node_uses(a);
+                self.count_node_uses(b);
+            },
+            AstNodeKind::UnOp { target, .. } => self.count_node_uses(target),
+            AstNodeKind::If { condition, body, else_body } => {
+                self.count_node_uses(condition);
+                self.count_node_uses(body);
+                if let Some(eb) = else_body {
+                    self.count_node_uses(eb);
+                }
+            },
+            AstNodeKind::Loop { body, .. } => self.count_node_uses(body),
+            AstNodeKind::While { condition, body, .. } => {
+                self.count_node_uses(condition);
+                self.count_node_uses(body);
+            },
+            AstNodeKind::For { from, to, body, .. } => {
+                self.count_node_uses(from);
+                self.count_node_uses(to);
+                self.count_node_uses(body);
+            },
+            AstNodeKind::Block { stmts } => {
+                for s in stmts {
+                    self.count_uses(s);
+                }
+            },
+            AstNodeKind::Call { callee, args } => {
+                self.count_node_uses(callee);
+                for a in args {
+                    self.count_node_uses(a);
+                }
+            },
+            AstNodeKind::Interpolation { parts } => {
+                for p in parts {
+                    self.count_node_uses(p);
+                }
+            },
+            AstNodeKind::Match { value, arms } => {
+                self.count_node_uses(value);
+                for arm in arms {
+                    if let Pattern::Value(v) = &arm.pattern {
+                        self.count_node_uses(v);
+                    }
+                    self.count_node_uses(&arm.body);
+                }
+            },
+            AstNodeKind::StructLiteral { fields, .. } => {
+                for f in fields {
+                    self.count_node_uses(&f.value);
+                }
+            },
+            AstNodeKind::Field { target, .. } => self.count_node_uses(target),
+            AstNodeKind::Reference(..) | AstNodeKind::Value(..) | AstNodeKind::Error => {}
+        }
+    }
+
+    fn block(&mut self, stmts: &[Statement<'a>]) {
+        let visible = self.visible.len();
+        // the statement after which nothing runs, and whether we've said so
+        let mut diverged: Option<Option<Sp<'a, ()>>> = None;
+        let mut reported = false;
+
+        for stmt in stmts {
+            let levels = self.levels.len();
+            self.enter(stmt);
+            if let (Some(cause), false, Some(at)) = (&diverged, reported, span(stmt)) {
+                let mut e = CompileError::error(&at, "unreachable statement");
+                if let Some(cause) = cause {
+                    e = e.with_label(cause, "any code after this is unreachable");
+                }
+                self.report(Lint::UnreachableCode, e);
+                reported = true;
+            }
+
+            match stmt {
+                Statement::Declare { id, value, .. } => {
+                    self.node(value);
+                    self.declare(id);
+                },
+                Statement::Expression(e) => self.node(e),
+                Statement::Return(e) | Statement::Out(e) | Statement::Break { with: Some(e), .. } => {
+                    self.node(e);
+                    diverged.get_or_insert(Some(e.ditch()));
+                },
+                Statement::Continue { .. } | Statement::Break { .. } => { diverged.get_or_insert(None); },
+                Statement::Function { params, body, .. } => {
+                    let outside = std::mem::replace(&mut self.barrier, self.visible.len());
+                    for p in params {
+                        self.declare(&p.id);
+                    }
+                    self.node(body);
+                    self.visible.truncate(self.barrier);
+                    self.barrier = outside;
+                },
+                Statement::Import { .. } | Statement::Struct { .. } | Statement::Error => {}
+            }
+            self.levels.truncate(levels);
+        }
+        self.visible.truncate(visible);
+    }
+
+    fn node(&mut self, node: &AstNode<'a>) {
+        match &*node.kind {
+            AstNodeKind::BinOp { a, b, .. } => {
+                self.node(a);
+                self.node(b);
+            },
+            AstNodeKind::UnOp { target, .. } => self.node(target),
+            AstNodeKind::If { condition, body, else_body } => {
+                self.node(condition);
+                self.node(body);
+                if let Some(eb) = else_body {
+                    self.node(eb);
+                }
+            },
+            AstNodeKind::Loop { body, .. } => self.node(body),
+            AstNodeKind::While { condition, body, .. } => {
+                self.node(condition);
+                self.node(body);
+            },
+            AstNodeKind::For { var, from, to, body, .. } => {
+                self.node(from);
+                self.node(to);
+                // the loop variable is only around for the body
+                let visible = self.visible.len();
+                self.declare(var);
+                self.node(body);
+                self.visible.truncate(visible);
+            },
+            AstNodeKind::Block { stmts } => self.block(stmts),
+            AstNodeKind::Call { callee, args } => {
+                self.node(callee);
+                for a in args {
+                    self.node(a);
+                }
+            },
+            AstNodeKind::Interpolation { parts } => {
+                for p in parts {
+                    self.node(p);
+                }
+            },
+            AstNodeKind::Match { value, arms } => {
+                self.node(value);
+                // the arm that matches everything, after which none can
+                let mut catch_all = None;
+                for arm in arms {
+                    if let Some(earlier) = &catch_all {
+                        let e = CompileError::error(&arm.pattern.at(), "unreachable match arm")
+                            .with_label(earlier, "this arm matches everything");
+                        self.report(Lint::UnreachableCode, e);
+                    }
+                    let visible = self.visible.len();
+                    if let Pattern::Binding(id) = &arm.pattern {
+                        self.declare(id);
+                    }
+                    self.node(&arm.body);
+                    self.visible.truncate(visible);
+                    if arm.pattern.catches_all() && catch_all.is_none() {
+                        catch_all = Some(arm.pattern.at());
+                    }
+                }
+            },
+            AstNodeKind::StructLiteral { fields, .. } => {
+                for f in fields {
+                    self.node(&f.value);
+                }
+            },
+            AstNodeKind::Field { target, .. } => self.node(target),
+            AstNodeKind::Reference(..) | AstNodeKind::Value(..) | AstNodeKind::Error => {}
+        }
+    }
+
+    fn declare(&mut self, id: &Sp<'a, Reference<'a>>) {
+        let name = id.text();
+        if let Some((_, earlier)) = self.visible[self.barrier..].iter().rev().find(|(n, _)| *n == name) {
+            let e = CompileError::error(id, format!("`{}` shadows an earlier variable", name))
+                .with_label(earlier, format!("the earlier `{}` is declared here", name));
+            self.report(Lint::Shadowing, e);
+        }
+        // `_` is how to say it's on purpose
+        let used = match &id.data {
+            Reference::Resolved(sym) => self.uses.get(sym).is_some_and(|n| *n > 0),
+            _ => true
+        };
+        if !used && !name.starts_with('_') {
+            let e = CompileError::error(id, format!("unused variable `{}`", name));
+            self.report(Lint::UnusedVariables, e);
+        }
+        self.visible.push((name, id.ditch()));
+    }
+
+    /// Applies the lint levels on a statement to everything in it.
+    fn enter(&mut self, stmt: &Statement<'a>) {
+        let attributes: &[Attribute] = match stmt {
+            Statement::Declare { attributes, .. } | Statement::Function { attributes, .. } => attributes,
+            _ => &[]
+        };
+        for a in attributes {
+            let level = match *a.name {
+                "allow" => Level::Allow,
+                "warn" => Level::Warn,
+                "deny" => Level::Deny,
+                _ => continue
+            };
+            for arg in &a.args {
+                match Lint::named(arg.data) {
+                    Some(lints) => self.levels.extend(lints.into_iter().map(|l| (l, level))),
+                    None => {
+                        let message = match arg.data {
+                            // it can come back along with imports
+                            "unused_imports" => "`unused_imports` isn't a lint yet, since `import` isn't supported".to_string(),
+                            name => format!("unknown lint `{}`", name)
+                        };
+                        let mut e = CompileError::error(arg, message);
+                        e.severity = Severity::Warning;
+                        self.warnings.push(e);
+                    }
+                }
+            }
+        }
+    }
+
+    fn report(&mut self, lint: Lint, mut e: CompileError<'a>) {
+        let level = self.levels.iter().rev()
+            .find(|(l, _)| *l == lint)
+            .map(|(_, level)| *level)
+            .unwrap_or(Level::Warn);
+        e.severity = match level {
+            Level::Allow => return,
+            Level::Warn => Severity::Warning,
+            Level::Deny => Severity::Error
+        };
+        self.warnings.push(e.with_code(lint.name()));
+    }
+}
+
+/// Somewhere to point at for a statement, if it has anything to point at
+fn span<'a>(stmt: &Statement<'a>) -> Option<Sp<'a, ()>> {
+    match stmt {
+        Statement::Declare { id, .. } => Some(id.ditch()),
+        Statement::Expression(e)
+            | Statement::Return(e)
+            | Statement::Out(e)
+            | Statement::Break { with: Some(e), .. } => Some(e.ditch()),
+        Statement::Function { name, .. } | Statement::Struct { name, .. } => Some(name.ditch()),
+        Statement::Import { paths } => paths.first().map(|p| match p {
+            ImportElement::Item(name) | ImportElement::Access(name, _) => name.ditch()
+        }),
+        Statement::Continue { keyword, .. } | Statement::Break { keyword, .. } => Some(keyword.ditch()),
+        Statement::Error => None
+    }
+}
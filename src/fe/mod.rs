@@ -1,5 +1,5 @@
 //! The Frontend
-use std::{fmt::Debug, ops::{Deref, DerefMut, Range}};
+use std::{fmt::{Debug, Display}, ops::{Deref, DerefMut, Range}};
 
 pub mod token;
 pub mod lexer;
@@ -9,6 +9,10 @@ pub mod types;
 pub mod symbols;
 pub mod scope;
 pub mod item;
+pub mod render;
+pub mod codes;
+pub mod lint;
+pub mod cst;
 
 #[derive(Debug)]
 pub struct Sp<'a, T: Debug> {
@@ -20,6 +24,13 @@ pub struct Sp<'a, T: Debug> {
 }
 
 impl<'a, T: Debug> Sp<'a, T> {
+    /// Covers `span` of `of`, working out where that is.
+    pub fn new(of: &'a str, span: Range<usize>, data: T) -> Sp<'a, T> {
+        let mut sp = Sp { line: 0, col: 0, span, of, data };
+        (sp.line, sp.col) = sp.line_col(sp.span.start);
+        sp
+    }
+
     pub fn map<O: Debug>(&self, map_fn: impl FnOnce(&T) -> O) -> Sp<'a, O> {
         let d = map_fn(&self.data);
         Sp {
@@ -35,6 +46,20 @@ impl<'a, T: Debug> Sp<'a, T> {
         self.map(|_| ())
     }
 
+    /// The source code this covers
+    pub fn text(&self) -> &'a str {
+        self.of.get(self.span.clone()).unwrap_or_default()
+    }
+
+    /// The 1-based line and column (counted in characters) of a byte offset
+    /// into the code this was taken from.
+    pub fn line_col(&self, byte: usize) -> (usize, usize) {
+        let before = self.of.get(..byte).unwrap_or(self.of);
+        let line = before.matches('\n').count() + 1;
+        let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+        (line, before[line_start..].chars().count() + 1)
+    }
+
     pub fn builtin(t: T) -> Sp<'static, T> {
         let d = "<builtin>";
         Sp {
@@ -62,8 +87,62 @@ impl<T> DerefMut for Sp<'_, T> where T: Debug {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning
+}
+
+impl Severity {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning"
+        }
+    }
+}
+
+/// Points out some other code that's relevant to a diagnostic
+#[derive(Debug)]
+pub struct Label<'a> {
+    pub at: Sp<'a, ()>,
+    pub message: String
+}
+
 #[derive(Debug)]
 pub struct CompileError<'a> {
-    span: &'a str,
-    message: String
+    pub at: Sp<'a, ()>,
+    pub severity: Severity,
+    /// A stable identifier for this kind of problem, if it has one
+    pub code: Option<&'static str>,
+    pub message: String,
+    pub labels: Vec<Label<'a>>
+}
+
+impl<'a> CompileError<'a> {
+    pub fn error<T: Debug>(at: &Sp<'a, T>, message: impl ToString) -> Self {
+        Self {
+            at: at.ditch(),
+            severity: Severity::Error,
+            code: None,
+            message: message.to_string(),
+            labels: vec![]
+        }
+    }
+
+    pub fn with_code(mut self, code: &'static str) -> Self {
+        self.code = Some(code);
+        self
+    }
+
+    pub fn with_label<T: Debug>(mut self, at: &Sp<'a, T>, message: impl ToString) -> Self {
+        self.labels.push(Label { at: at.ditch(), message: message.to_string() });
+        self
+    }
+}
+
+impl Display for CompileError<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {} (at `{}`)", self.severity.name(), self.message, self.at.text())
+    }
 }
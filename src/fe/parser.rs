@@ -1,13 +1,27 @@
-use std::mem;
+use std::{borrow::Cow, fmt::Debug, mem, ops::Range};
 
-use super::{token::Token, Sp, lexer::Lexer, ast::{self, AstNodeKind, RawAstNode, UnOp, Statement, ImportElement, AstNode, MaybeTyped, Reference}, CompileError};
+use super::{token::{Token, CommentType}, Sp, lexer::{self, Lexer}, ast::{self, AstNodeKind, RawAstNode, UnOp, Statement, ImportElement, AstNode, MaybeTyped, Reference, Attribute, MatchArm, Pattern, FieldDecl, FieldInit}, codes, CompileError};
 
 pub struct Parser<'a> {
     lexer: Lexer<'a>,
     current: Sp<'a, Token<'a>>,
     next: Sp<'a, Token<'a>>,
-    panicking: bool, // TODO: error recovery
+    /// The token after a `;` we put in for a line break, which comes next
+    pending: Option<Sp<'a, Token<'a>>>,
+    /// Set after an error until we've found a good place to carry on from,
+    /// so one mistake doesn't bury the user in errors about the code after it
+    panicking: bool,
+    /// Set when the number we're about to read has a `-` in front, which then
+    /// becomes part of it so that `-128i8` fits
+    negated: bool,
+    /// Set while parsing what comes before a block, like the condition of an
+    /// `if`, where `name {` opens the block instead of a struct literal
+    no_struct_literals: bool,
     errors: Vec<CompileError<'a>>,
+    /// Doc comments since the last token we read
+    docs: Vec<&'a str>,
+    /// Doc comments in front of `next`
+    next_docs: Vec<&'a str>
 }
 
 macro_rules! precs {
@@ -37,13 +51,21 @@ precs!(
 
 impl<'a> Parser<'a> {
     pub fn new(prog: &'a str) -> Parser<'a> {
-        let lexer = Lexer::new(prog);
+        Parser::with_lexer(Lexer::new(prog))
+    }
+
+    fn with_lexer(lexer: Lexer<'a>) -> Parser<'a> {
         let mut s = Self {
             next: Token::nothing_span(),
             current: Token::nothing_span(),
+            pending: None,
             lexer,
             errors: Vec::new(),
-            panicking: false
+            panicking: false,
+            negated: false,
+            no_struct_literals: false,
+            docs: vec![],
+            next_docs: vec![]
         };
         s.advance();
         s
@@ -57,18 +79,106 @@ impl<'a> Parser<'a> {
     pub fn parse_block(&mut self, end: &Token) -> Vec<Statement<'a>> {
         let mut stmts = vec![];
         while !self.pick(end) {
+            match *self.next {
+                // empty, like the line break after a function
+                Token::Semicolon => {
+                    self.advance();
+                    continue
+                },
+                Token::Nothing => {
+                    self.error(codes::UNBALANCED_DELIMITER, "expected `}` to close the block");
+                    break
+                },
+                Token::RBrace => {
+                    // only the top level gets here, blocks stop at their `}`
+                    self.error(codes::UNBALANCED_DELIMITER, "unexpected `}`");
+                    self.advance();
+                    self.panicking = false;
+                    continue
+                },
+                _ => {}
+            }
             let stmt = self.statement();
             stmts.push(stmt);
+            if self.panicking {
+                self.synchronize();
+            }
         }
         stmts
     }
 
+    /// Skips to the end of the statement we're in, or to the start of the
+    /// next one, whichever comes first.
+    fn synchronize(&mut self) {
+        self.panicking = false;
+        // braces opened while skipping, whose contents don't count
+        let mut depth = 0usize;
+        loop {
+            match *self.next {
+                Token::Nothing => return,
+                Token::Semicolon if depth == 0 => {
+                    self.advance();
+                    return
+                },
+                Token::RBrace if depth == 0 => return,
+                Token::Let | Token::Fn | Token::Struct | Token::Import | Token::Return | Token::Break
+                    | Token::Continue | Token::LeftArrow | Token::At if depth == 0 => return,
+                Token::LBrace => depth += 1,
+                Token::RBrace => depth -= 1,
+                _ => {}
+            }
+            self.advance();
+        }
+    }
+
+    /// Skips past the `}` that closes the braces we're in, for when what's
+    /// inside them can't be made sense of.
+    fn skip_past_brace(&mut self) {
+        let mut depth = 0usize;
+        loop {
+            match *self.next {
+                Token::Nothing => return,
+                Token::RBrace if depth == 0 => {
+                    self.advance();
+                    return
+                },
+                Token::LBrace => depth += 1,
+                Token::RBrace => depth -= 1,
+                _ => {}
+            }
+            self.advance();
+        }
+    }
+
     fn statement(&mut self) -> Statement<'a> {
+        let docs = mem::take(&mut self.next_docs);
+        let attributes = self.attributes();
+        if self.pick(&Token::Fn) {
+            return self.function(docs, attributes)
+        }
+        let declaration = matches!(*self.next, Token::Let);
+        for a in &attributes {
+            if !(declaration && is_lint_level(&a.name)) {
+                let message = if declaration {
+                    "only lint levels, like `@allow(..)`, can be put on a `let`"
+                } else {
+                    "attributes can only be put on functions and `let`s"
+                };
+                self.errors.push(CompileError::error(&a.name, message).with_code(codes::BAD_ATTRIBUTE));
+            }
+        }
+
         // TODO: low hanging optimization fruit
         let stmt = if self.pick(&Token::Import) {
+            // TODO: modules
+            self.errors.push(
+                CompileError::error(&self.current, "`import` isn't supported yet").with_code(codes::UNSUPPORTED)
+            );
             self.import()
         } else if self.pick(&Token::Let) {
-            self.decl()
+            self.decl(docs, attributes)
+        } else if self.pick(&Token::Struct) {
+            self.struct_decl(docs)
         } else if self.pick(&Token::LeftArrow) {
             self.out()
         } else if self.pick(&Token::Return) {
@@ -80,13 +190,31 @@ impl<'a> Parser<'a> {
         } else {
             Statement::Expression(self.top_parse())
         };
-        if !self.pick(&Token::Semicolon) {
-            self.error("expected `;` to end statement");
-            return Statement::Error
-        }
+        self.end_statement();
         stmt
     }
 
+    fn end_statement(&mut self) {
+        if self.pick(&Token::Semicolon) {
+            // whatever went wrong, it's over now
+            self.panicking = false;
+        } else if !self.at_block_end() {
+            let after = self.after_current();
+            self.report(&after, codes::EXPECTED_SEMICOLON, "expected `;` or a line break to end statement");
+        }
+    }
+
+    /// Whether the statement we're in is the last in its block, which means it
+    /// doesn't need a `;`.
+    fn at_block_end(&self) -> bool {
+        matches!(*self.next, Token::RBrace | Token::Nothing)
+    }
+
+    /// The type named by the identifier we just read
+    fn type_name(&self) -> MaybeTyped<'a> {
+        MaybeTyped::TypeProvided(self.current.map(|_| Reference::Unresolved(self.unwrap_current_id_unchecked())))
+    }
+
     fn unwrap_current_id_unchecked(&self) -> &'a str {
         match *self.current {
             Token::Identifier(i) => i,
@@ -100,49 +228,192 @@ impl<'a> Parser<'a> {
     }
 
     fn ret(&mut self) -> Statement<'a> {
+        // a bare `return` gives back `unit`, just like an empty block
+        if matches!(*self.next, Token::Semicolon) || self.at_block_end() {
+            let at = self.current.ditch();
+            return Statement::Return(self.sp_from(at, AstNodeKind::Block { stmts: vec![] }))
+        }
         let expr = self.top_parse();
         Statement::Return(expr)
     }
 
-    fn decl(&mut self) -> Statement<'a> {
+    fn decl(&mut self, docs: Vec<&'a str>, attributes: Vec<Attribute<'a>>) -> Statement<'a> {
         if !self.pick(&Token::Identifier("")) {
-            self.error("expected identifier to follow start of declaration");
+            self.error(codes::BAD_DECLARATION, "expected identifier to follow start of declaration");
             return Statement::Error
         }
-        let id = self.unwrap_current_id_unchecked();
+        let id = self.current.map(|_| Reference::Unresolved(self.unwrap_current_id_unchecked()));
         let mut type_spec = MaybeTyped::NotTyped;
         if self.pick(&Token::Colon) {
             // yay, types!
             if !self.pick(&Token::Identifier("")) {
                 // we can still try to continue without explicit type info, so we're not returning
-                self.error("expected type identifier following start of type specification (`:`)");
+                self.error(codes::BAD_DECLARATION, "expected type identifier following start of type specification (`:`)");
             } else {
-                type_spec = MaybeTyped::TypeProvided(self.unwrap_current_id_unchecked());
+                type_spec = self.type_name();
             }
         }
         if !self.pick(&Token::Assign) {
-            self.error("expected `=` to follow name of declaration");
+            self.error(codes::BAD_DECLARATION, "expected `=` to follow name of declaration");
             return Statement::Error
         }
         let initializer = self.top_parse();
         Statement::Declare {
-            id: Reference::Unresolved(id),
+            docs,
+            attributes,
+            id,
             with_type: type_spec,
             value: initializer 
         }
     }
 
-    // TODO: labels
+    fn attributes(&mut self) -> Vec<Attribute<'a>> {
+        let mut attrs = vec![];
+        while self.pick(&Token::At) {
+            if !self.pick(&Token::Identifier("")) {
+                self.error(codes::BAD_ATTRIBUTE, "expected the name of an attribute after `@`");
+                continue
+            }
+            let name = self.current.map(|_| self.unwrap_current_id_unchecked());
+            let mut args = vec![];
+            if self.pick(&Token::LParen) {
+                while self.pick(&Token::Identifier("")) {
+                    args.push(self.current.map(|_| self.unwrap_current_id_unchecked()));
+                    if !self.pick(&Token::Comma) { break }
+                }
+                if !self.pick(&Token::RParen) {
+                    self.error(codes::UNBALANCED_DELIMITER, "expected `)` to end the arguments of an attribute");
+                }
+            }
+            attrs.push(Attribute { name, args });
+            // what they're on goes on the next line
+            if matches!(*self.next, Token::Semicolon) && self.next.span.is_empty() {
+                self.advance();
+            }
+        }
+        attrs
+    }
+
+    fn function(&mut self, docs: Vec<&'a str>, attributes: Vec<Attribute<'a>>) -> Statement<'a> {
+        if !self.pick(&Token::Identifier("")) {
+            self.error(codes::BAD_FUNCTION, "expected a name for the function");
+            return Statement::Error
+        }
+        let name = self.current.map(|_| self.unwrap_current_id_unchecked());
+        if !self.pick(&Token::LParen) {
+            self.error(codes::BAD_FUNCTION, "expected `(` to start the parameters of the function");
+            return Statement::Error
+        }
+        let mut params = vec![];
+        while self.pick(&Token::Identifier("")) {
+            let id = self.current.map(|_| Reference::Unresolved(self.unwrap_current_id_unchecked()));
+            if !self.pick(&Token::Colon) || !self.pick(&Token::Identifier("")) {
+                self.error(codes::BAD_FUNCTION, "expected `:` and a type to follow the name of a parameter");
+                return Statement::Error
+            }
+            let with_type = self.type_name();
+            params.push(ast::Param { id, with_type });
+            if !self.pick(&Token::Comma) { break }
+        }
+        if !self.pick(&Token::RParen) {
+            self.error(codes::BAD_FUNCTION, "expected `)` to end the parameters of the function");
+            return Statement::Error
+        }
+
+        let mut returns = MaybeTyped::NotTyped;
+        if self.pick(&Token::Arrow) {
+            // `-> type { .. }`, or `-> value` without any braces
+            let expression_body = if self.pick(&Token::Identifier("")) {
+                if matches!(*self.next, Token::LBrace) {
+                    returns = self.type_name();
+                    None
+                } else {
+                    let lhs = self.ident();
+                    Some(self.infix(lhs, prec::ASSIGN))
+                }
+            } else {
+                Some(self.top_parse())
+            };
+            if let Some(body) = expression_body {
+                self.end_statement();
+                return Statement::Function { docs, attributes, name, id: None, params, returns, expression_body: true, body }
+            }
+        }
+        if !self.pick(&Token::LBrace) {
+            self.error(codes::BAD_FUNCTION, "expected `{` to open the body of the function");
+            return Statement::Error
+        }
+        // functions end with their body, no `;` needed
+        let body = self.block_expr();
+        Statement::Function { docs, attributes, name, id: None, params, returns, expression_body: false, body }
+    }
+
+    /// `struct Name { field: type, .. }`, or just `struct Name` for one
+    /// without any fields.
+    fn struct_decl(&mut self, docs: Vec<&'a str>) -> Statement<'a> {
+        if !self.pick(&Token::Identifier("")) {
+            self.error(codes::BAD_STRUCT, "expected a name for the struct");
+            return Statement::Error
+        }
+        let name = self.current.map(|_| self.unwrap_current_id_unchecked());
+        if !self.pick(&Token::LBrace) {
+            return Statement::Struct { docs, name, id: None, fields: None }
+        }
+        let mut fields: Vec<FieldDecl> = vec![];
+        while !self.pick(&Token::RBrace) {
+            if !self.pick(&Token::Identifier("")) {
+                let found = self.found();
+                self.error(codes::BAD_STRUCT, format!("expected the name of a field, found {}", found));
+                self.skip_past_brace();
+                return Statement::Error
+            }
+            let field = self.current.map(|_| self.unwrap_current_id_unchecked());
+            if !self.pick(&Token::Colon) || !self.pick(&Token::Identifier("")) {
+                self.error(codes::BAD_STRUCT, "expected `:` and a type to follow the name of a field");
+                self.skip_past_brace();
+                return Statement::Error
+            }
+            if let Some(earlier) = fields.iter().find(|f| f.name.data == field.data) {
+                self.errors.push(
+                    CompileError::error(&field, format!("`{}` already has a field called `{}`", name.data, field.data))
+                        .with_code(codes::BAD_STRUCT)
+                        .with_label(&earlier.name, "it's declared here")
+                );
+            }
+            fields.push(FieldDecl { name: field, with_type: self.type_name() });
+            // fields on their own lines don't need a comma
+            if !(self.pick(&Token::Comma) || self.pick(&Token::Semicolon)) {
+                if !self.pick(&Token::RBrace) {
+                    let found = self.found();
+                    self.error(codes::BAD_STRUCT, format!("expected `,` or `}}` after the field, found {}", found));
+                    self.skip_past_brace();
+                    return Statement::Error
+                }
+                break
+            }
+        }
+        Statement::Struct { docs, name, id: None, fields: Some(fields) }
+    }
+
     fn break_stmt(&mut self) -> Statement<'a> {
-        let val = if let Token::Semicolon = *self.next { None }
+        let keyword = self.current.ditch();
+        let label = self.label();
+        let val = if matches!(*self.next, Token::Semicolon) || self.at_block_end() { None }
         else {
             Some(self.top_parse())
         };
-        Statement::Break { label: None, with: val }
+        Statement::Break { keyword, label, with: val }
     }
 
     fn continue_stmt(&mut self) -> Statement<'a> {
-        Statement::Continue { label: None }
+        Statement::Continue { keyword: self.current.ditch(), label: self.label() }
+    }
+
+    /// The label after a `break` or `continue`, if there is one.
+    fn label(&mut self) -> Option<Sp<'a, Reference<'a>>> {
+        let Token::Label(name) = *self.next else { return None };
+        self.advance();
+        Some(self.current.map(|_| Reference::Unresolved(name)))
     }
 
     fn import(&mut self) -> Statement<'a> {
@@ -153,33 +424,29 @@ impl<'a> Parser<'a> {
     }
 
     fn import_elem(&mut self) -> Vec<ImportElement<'a>> {
-        match self.advance() {
-            Token::Identifier(id) => {
-                let id = *id;
-                if self.pick(&Token::Dot) {
-                    vec![ImportElement::Access(id, self.import_elem())]
-                } else {
-                    vec![ImportElement::Item(id)]
-                }
-            },
-            Token::LParen => {
-                let mut elems = vec![];
-                loop {
-                    if matches!(*self.next, Token::RParen) { break }
-                    let mut elem = self.import_elem();
-                    elems.append(&mut elem);
-                    if !self.pick(&Token::Comma) { break }
-                }
-                if !self.pick(&Token::RParen) {
-                    self.error("expected a closing `)` for import statement group");
-                    return vec![]
-                }
-                elems
-            },
-            _ => {
-                self.error("expected an identifier or `(` for import statement");
-                vec![]
+        if self.pick(&Token::Identifier("")) {
+            let id = self.current.map(|_| self.unwrap_current_id_unchecked());
+            if self.pick(&Token::Dot) {
+                vec![ImportElement::Access(id, self.import_elem())]
+            } else {
+                vec![ImportElement::Item(id)]
             }
+        } else if self.pick(&Token::LParen) {
+            let mut elems = vec![];
+            loop {
+                if matches!(*self.next, Token::RParen) { break }
+                let mut elem = self.import_elem();
+                elems.append(&mut elem);
+                if !self.pick(&Token::Comma) { break }
+            }
+            if !self.pick(&Token::RParen) {
+                self.error(codes::UNBALANCED_DELIMITER, "expected a closing `)` for import statement group");
+                return vec![]
+            }
+            elems
+        } else {
+            self.error(codes::BAD_IMPORT, "expected an identifier or `(` for import statement");
+            vec![]
         }
     }
 
@@ -189,21 +456,34 @@ impl<'a> Parser<'a> {
 
     fn parse_with_prec(&mut self, prec: u8) -> AstNode<'a> {
         use Token::*;
-        let mut node = match self.advance() {
+        let starts_expression = self.next.is_value()
+            || matches!(*self.next, Identifier(..) | Label(..) | If | Match | Loop | While | For | LParen | Sub | Not | LBrace);
+        if !starts_expression {
+            // leave it be, it might be where the statement ends
+            let found = self.found();
+            return self.error(codes::EXPECTED_EXPRESSION, format!("expected an expression, found {}", found))
+        }
+        let node = match self.advance() {
             n if n.is_value() => self.value(),
-            Identifier(..) => self.ident(),
+            Identifier(..) => self.ident_or_struct(),
             If => self.if_expr(),
-            Loop => self.loop_expr(),
+            Match => self.match_expr(),
+            Loop => self.loop_expr(None),
+            While => self.while_expr(None),
+            For => self.for_expr(None),
+            &Label(name) => self.labeled(name),
             LParen => self.group(),
-            Sub | Not => self.unary(prec),
+            Sub | Not => self.unary(),
             LBrace => self.block_expr(),
-            _ => {
-                dbg!(&self.current, &self.next, &self.errors);
-                panic!("expected expression");
-                // return self.error("expected expression")
-            }
+            _ => self.misread("an expression")
         };
+        self.infix(node, prec)
+    }
 
+    /// Parses the operators that follow `node`, as long as they bind at
+    /// least as tightly as `prec`.
+    fn infix(&mut self, mut node: AstNode<'a>, prec: u8) -> AstNode<'a> {
+        use Token::*;
         while {
             let nprec = self.next.get_precedence();
             prec <= nprec
@@ -211,8 +491,11 @@ impl<'a> Parser<'a> {
             node = match self.advance() {
                 Add | AddEq | Sub | SubEq | Mul | MulEq | Div | DivEq
                     | Pow | PowEq | Mod | ModEq | Assign
-                    | Eq | NotEq | Gt | GtEq | Lt | LtEq => self.binary(node, self.current.get_precedence() + 1),
-                x => panic!("{:#?} has precedence but no associated infix operation", x)
+                    | Eq | NotEq | Gt | GtEq | Lt | LtEq
+                    | And | Or => self.binary(node, self.current.get_precedence() + 1),
+                LParen => self.call(node),
+                Dot => self.field(node),
+                _ => self.misread("an operator")
             };
         }
 
@@ -220,26 +503,173 @@ impl<'a> Parser<'a> {
     }
 
     fn block_expr(&mut self) -> AstNode<'a> {
+        let start = self.current.ditch();
         let b = AstNodeKind::Block {
-            stmts: self.parse_block(&Token::RBrace)
+            stmts: self.allowing_structs(|p| p.parse_block(&Token::RBrace))
         };
-        self.sp(b)
+        self.sp_from(start, b)
+    }
+
+    /// Parses what comes before a block, like the condition of an `if`.
+    fn head(&mut self, prec: u8) -> AstNode<'a> {
+        let outer = mem::replace(&mut self.no_struct_literals, true);
+        let node = self.parse_with_prec(prec);
+        self.no_struct_literals = outer;
+        node
+    }
+
+    /// Runs `f` where struct literals can be used again, like inside the
+    /// parentheses of a condition.
+    fn allowing_structs<T>(&mut self, f: impl FnOnce(&mut Self) -> T) -> T {
+        let outer = mem::replace(&mut self.no_struct_literals, false);
+        let t = f(self);
+        self.no_struct_literals = outer;
+        t
     }
 
-    fn loop_expr(&mut self) -> AstNode<'a> {
+    fn loop_expr(&mut self, label: Option<Sp<'a, Reference<'a>>>) -> AstNode<'a> {
+        let start = match &label {
+            Some(l) => l.ditch(),
+            None => self.current.ditch()
+        };
         if !self.pick(&Token::LBrace) {
-            return self.error("expected `{` to open loop block");
+            return self.error(codes::EXPECTED_BLOCK, "expected `{` to open loop block");
         }
         let b = self.block_expr();
 
-        self.sp(AstNodeKind::Loop { body: b })
+        self.sp_from(start, AstNodeKind::Loop { label, body: b })
+    }
+
+    /// `'name: loop { .. }`. The lexer only makes a label here when a loop
+    /// seems to come after it.
+    fn labeled(&mut self, name: &'a str) -> AstNode<'a> {
+        let label = self.current.map(|_| Reference::Unresolved(name));
+        self.pick(&Token::Colon);
+        if self.pick(&Token::Loop) {
+            self.loop_expr(Some(label))
+        } else if self.pick(&Token::While) {
+            self.while_expr(Some(label))
+        } else if self.pick(&Token::For) {
+            self.for_expr(Some(label))
+        } else {
+            let found = self.found();
+            self.error(codes::EXPECTED_EXPRESSION, format!("expected a loop after the label, found {}", found))
+        }
+    }
+
+    fn while_expr(&mut self, label: Option<Sp<'a, Reference<'a>>>) -> AstNode<'a> {
+        let start = match &label {
+            Some(l) => l.ditch(),
+            None => self.current.ditch()
+        };
+        let condition = self.head(prec::ASSIGN);
+        if !self.pick(&Token::LBrace) {
+            return self.error(codes::EXPECTED_BLOCK, "expected `{` to open block after condition");
+        }
+        let body = self.block_expr();
+
+        self.sp_from(start, AstNodeKind::While { label, condition, body })
+    }
+
+    /// `for i in 0..10 { .. }`. Ranges can't be used anywhere else yet, so
+    /// they're part of the loop.
+    fn for_expr(&mut self, label: Option<Sp<'a, Reference<'a>>>) -> AstNode<'a> {
+        let start = match &label {
+            Some(l) => l.ditch(),
+            None => self.current.ditch()
+        };
+        if !self.pick(&Token::Identifier("")) {
+            let found = self.found();
+            return self.error(codes::BAD_FOR, format!("expected a name for the loop variable, found {}", found))
+        }
+        let var = self.current.map(|_| Reference::Unresolved(self.unwrap_current_id_unchecked()));
+        if !self.pick(&Token::In) {
+            let found = self.found();
+            return self.error(codes::BAD_FOR, format!("expected `in` after the loop variable, found {}", found))
+        }
+        let from = self.head(prec::OR);
+        let inclusive = match *self.next {
+            Token::DotDot => false,
+            Token::DotDotEq => true,
+            _ => {
+                let found = self.found();
+                return self.error(codes::BAD_FOR, format!("expected `..` or `..=` for a range, found {}", found))
+            }
+        };
+        self.advance();
+        let to = self.head(prec::OR);
+        if !self.pick(&Token::LBrace) {
+            return self.error(codes::EXPECTED_BLOCK, "expected `{` to open loop block");
+        }
+        let body = self.block_expr();
+
+        self.sp_from(start, AstNodeKind::For { label, var, from, to, inclusive, body })
+    }
+
+    fn match_expr(&mut self) -> AstNode<'a> {
+        let start = self.current.ditch();
+        let value = self.head(prec::ASSIGN);
+        if !self.pick(&Token::LBrace) {
+            return self.error(codes::EXPECTED_BLOCK, "expected `{` to open the arms of the `match`");
+        }
+        let mut arms = vec![];
+        while !self.at_block_end() {
+            let Some(pattern) = self.pattern() else {
+                self.skip_past_brace();
+                return self.sp_from(start, AstNodeKind::Error)
+            };
+            if !self.pick(&Token::Arrow) {
+                let found = self.found();
+                let e = self.error(codes::BAD_MATCH, format!("expected `->` after the pattern, found {}", found));
+                self.skip_past_brace();
+                return e
+            }
+            let body = self.top_parse();
+            arms.push(MatchArm { pattern, body });
+            // arms on their own lines don't need a comma
+            if !(self.pick(&Token::Comma) || self.pick(&Token::Semicolon)) {
+                break
+            }
+        }
+        if !self.pick(&Token::RBrace) {
+            let found = self.found();
+            let e = self.error(codes::BAD_MATCH, format!("expected `,` or `}}` after the arm, found {}", found));
+            self.skip_past_brace();
+            return e
+        }
+        self.sp_from(start, AstNodeKind::Match { value, arms })
+    }
+
+    fn pattern(&mut self) -> Option<Pattern<'a>> {
+        if self.pick(&Token::Identifier("")) {
+            return Some(match self.unwrap_current_id_unchecked() {
+                "_" => Pattern::Wildcard(self.current.ditch()),
+                name => Pattern::Binding(self.current.map(|_| Reference::Unresolved(name)))
+            })
+        }
+        if !(self.next.is_value() || matches!(*self.next, Token::Sub)) {
+            let found = self.found();
+            self.error(codes::BAD_MATCH, format!("expected a pattern, found {}", found));
+            return None
+        }
+        let value = self.parse_with_prec(prec::UNARY);
+        match &*value.kind {
+            AstNodeKind::Value(..) => Some(Pattern::Value(value)),
+            // already reported
+            AstNodeKind::Error => None,
+            _ => {
+                self.report(&value, codes::BAD_MATCH, "patterns can only be literals, like `1` or `\"a\"`, names or `_`");
+                None
+            }
+        }
     }
 
     fn if_expr(&mut self) -> AstNode<'a> {
-        let condition = self.top_parse();
+        let start = self.current.ditch();
+        let condition = self.head(prec::ASSIGN);
 
         if !self.pick(&Token::LBrace) {
-            return self.error("expected `{` to open block after condition");
+            return self.error(codes::EXPECTED_BLOCK, "expected `{` to open block after condition");
         }
         let body = self.block_expr();
 
@@ -248,13 +678,13 @@ impl<'a> Parser<'a> {
                 Some(self.if_expr())
             } else {
                 if !self.pick(&Token::LBrace) {
-                    return self.error("expected `{` to open block after else");
+                    return self.error(codes::EXPECTED_BLOCK, "expected `{` to open block after else");
                 }
                 Some(self.block_expr())
             }
         } else { None };
 
-        self.sp(AstNodeKind::If {
+        self.sp_from(start, AstNodeKind::If {
             condition,
             body,
             else_body
@@ -267,13 +697,28 @@ impl<'a> Parser<'a> {
         ))
     }
 
-    fn unary(&mut self, prec: u8) -> AstNode<'a> {
-        let op = self.current.map(|d| match d {
+    /// A name, or the start of a struct literal if a `{` comes after it.
+    fn ident_or_struct(&mut self) -> AstNode<'a> {
+        if !self.no_struct_literals && matches!(*self.next, Token::LBrace) {
+            return self.struct_literal()
+        }
+        self.ident()
+    }
+
+    fn unary(&mut self) -> AstNode<'a> {
+        let op = match *self.current {
             Token::Not => UnOp::Not,
             Token::Sub => UnOp::Negate,
-            _ => todo!("thought it was a unop but it wasn't ({:#?})", d)
-        });
-        let target = self.parse_with_prec(prec);
+            _ => return self.misread("an operator like `-` or `!`")
+        };
+        let op = self.current.map(|_| op);
+        let literal = matches!(*op, UnOp::Negate)
+            && matches!(*self.next, Token::CompInt(_, suffix) if !suffix.is_some_and(|s| s.starts_with('u')));
+        self.negated = literal;
+        let target = self.parse_with_prec(prec::UNARY);
+        if literal && matches!(*target.kind, AstNodeKind::Value(..)) {
+            return Sp { line: op.line, col: op.col, span: op.span.start..target.span.end, ..target }
+        }
         self.sp(AstNodeKind::UnOp {
             op ,
             target 
@@ -281,39 +726,180 @@ impl<'a> Parser<'a> {
     }
 
     fn value(&mut self) -> AstNode<'a> {
-        self.sp(AstNodeKind::Value(match *self.current {
-            // TODO: don't assume that everything is an i64
-            Token::CompInt(ci) => ast::ConstantValue::CompInt(ast::CompInt::I64(ci)),
-            Token::CompFloat(cf) => ast::ConstantValue::CompFloat(ast::CompFloat::F64(cf)),
-            Token::String(s) => ast::ConstantValue::String(s),
-            Token::True => ast::ConstantValue::Bool(true),
-            Token::False => ast::ConstantValue::Bool(false),
-            Token::Nil => ast::ConstantValue::Nil,
-            Token::Struct => todo!("structs not implemented"), // TODO: implement structs
-            _ => panic!("thought it was a value but it wasn't")
-        }))
+        let negated = mem::take(&mut self.negated);
+        let value = match *self.current {
+            Token::CompInt(n, suffix) => int_constant(n, negated, suffix).map(ast::ConstantValue::CompInt),
+            Token::CompFloat(f, suffix) => float_constant(f, suffix).map(ast::ConstantValue::CompFloat),
+            Token::String(s) => return self.string(s),
+            Token::True => Ok(ast::ConstantValue::Bool(true)),
+            Token::False => Ok(ast::ConstantValue::Bool(false)),
+            Token::Nil => Ok(ast::ConstantValue::Nil),
+            _ => return self.misread("a value")
+        };
+        match value {
+            Ok(v) => self.sp(AstNodeKind::Value(v)),
+            Err(ty) => {
+                let sign = if negated { "-" } else { "" };
+                let mut message = format!("`{}{}` doesn't fit in {}", sign, self.current.text(), ty);
+                if matches!(*self.current, Token::CompInt(_, None)) {
+                    message += "; numbers without a suffix are `i64`s";
+                }
+                self.errors.push(CompileError::error(&self.current, message).with_code(codes::BAD_NUMBER));
+                self.sp(AstNodeKind::Error)
+            }
+        }
     }
-    
+
+    /// Works out the escapes in a string, and parses the code in any `{..}`.
+    /// `raw` is the string as it's written, without its quotes.
+    fn string(&mut self, raw: &'a str) -> AstNode<'a> {
+        let start = self.current.span.start + 1;
+        let mut parts = vec![];
+        let mut interpolated = false;
+        // the text since the last `{..}`, and whether it's any different from how it's written
+        let mut text = String::new();
+        let mut escaped = false;
+        let mut text_start = 0;
+
+        let mut i = 0;
+        while let Some(ch) = raw[i..].chars().next() {
+            match ch {
+                '\\' => {
+                    match lexer::unescape(&raw[i..]) {
+                        Ok((ch, len)) => {
+                            text.push(ch);
+                            i += len;
+                        },
+                        Err((e, len)) => {
+                            let at = self.span_of(start + i..start + i + len);
+                            self.errors.push(CompileError::error(&at, &e).with_code(e.code()));
+                            i += len;
+                        }
+                    }
+                    escaped = true;
+                },
+                '{' => {
+                    interpolated = true;
+                    if i > text_start {
+                        let piece = if escaped { Cow::Owned(mem::take(&mut text)) } else { Cow::Borrowed(&raw[text_start..i]) };
+                        parts.push(self.span_of(start + text_start..start + i)
+                            .map(|_| RawAstNode::new(AstNodeKind::Value(ast::ConstantValue::String(piece.clone())))));
+                    }
+                    text.clear();
+                    escaped = false;
+
+                    let Some(len) = lexer::interpolation_len(&raw[i + 1..]) else {
+                        let at = self.span_of(start + i..start + i + 1);
+                        self.errors.push(CompileError::error(&at, "expected `}` to end the interpolation")
+                            .with_code(codes::UNBALANCED_DELIMITER));
+                        break
+                    };
+                    let mut inner = Parser::with_lexer(Lexer::within(self.lexer.prog, start + i + 1..start + i + 1 + len));
+                    parts.push(inner.top_parse());
+                    if !inner.pick(&Token::RBrace) {
+                        inner.error(codes::UNBALANCED_DELIMITER, "expected `}` to end the interpolation");
+                    }
+                    self.errors.append(&mut inner.errors);
+                    i += 1 + len;
+                    text_start = i;
+                },
+                _ => {
+                    text.push(ch);
+                    i += ch.len_utf8();
+                }
+            }
+        }
+
+        let piece = if escaped { Cow::Owned(text) } else { Cow::Borrowed(&raw[text_start..]) };
+        if !interpolated {
+            return self.sp(AstNodeKind::Value(ast::ConstantValue::String(piece)))
+        }
+        if !piece.is_empty() {
+            parts.push(self.span_of(start + text_start..start + raw.len())
+                .map(|_| RawAstNode::new(AstNodeKind::Value(ast::ConstantValue::String(piece.clone())))));
+        }
+        self.sp(AstNodeKind::Interpolation { parts })
+    }
+
     fn group(&mut self) -> AstNode<'a> {
-        let n = self.parse_with_prec(prec::ASSIGN);
+        let n = self.allowing_structs(|p| p.parse_with_prec(prec::ASSIGN));
         if !self.pick(&Token::RParen) {
-            return self.error("expected `)` to end group")
+            return self.error(codes::UNBALANCED_DELIMITER, "expected `)` to end group")
         };
         n
     }
 
+    fn call(&mut self, callee: AstNode<'a>) -> AstNode<'a> {
+        let mut args = vec![];
+        if !self.pick(&Token::RParen) {
+            loop {
+                args.push(self.allowing_structs(|p| p.top_parse()));
+                if !self.pick(&Token::Comma) { break }
+            }
+            if !self.pick(&Token::RParen) {
+                return self.error(codes::UNBALANCED_DELIMITER, "expected `)` to end the arguments of a call")
+            }
+        }
+        let start = callee.ditch();
+        self.sp_from(start, AstNodeKind::Call { callee, args })
+    }
+
+    /// `Name { field: value, .. }`, with the name just read.
+    fn struct_literal(&mut self) -> AstNode<'a> {
+        let name = self.current.map(|_| Reference::Unresolved(self.unwrap_current_id_unchecked()));
+        let start = self.current.ditch();
+        self.advance();
+        let mut fields: Vec<FieldInit> = vec![];
+        while !self.pick(&Token::RBrace) {
+            if !self.pick(&Token::Identifier("")) {
+                let found = self.found();
+                let e = self.error(codes::BAD_STRUCT, format!("expected the name of a field, found {}", found));
+                self.skip_past_brace();
+                return e
+            }
+            let field = self.current.map(|_| self.unwrap_current_id_unchecked());
+            if !self.pick(&Token::Colon) {
+                let e = self.error(codes::BAD_STRUCT, "expected `:` and a value to follow the name of a field");
+                self.skip_past_brace();
+                return e
+            }
+            let value = self.allowing_structs(|p| p.top_parse());
+            fields.push(FieldInit { name: field, value });
+            if !(self.pick(&Token::Comma) || self.pick(&Token::Semicolon)) {
+                if !self.pick(&Token::RBrace) {
+                    let found = self.found();
+                    let e = self.error(codes::BAD_STRUCT, format!("expected `,` or `}}` after the field, found {}", found));
+                    self.skip_past_brace();
+                    return e
+                }
+                break
+            }
+        }
+        self.sp_from(start, AstNodeKind::StructLiteral { name, fields })
+    }
+
+    /// `.name` after `target`
+    fn field(&mut self, target: AstNode<'a>) -> AstNode<'a> {
+        if !self.pick(&Token::Identifier("")) {
+            let found = self.found();
+            return self.error(codes::BAD_STRUCT, format!("expected the name of a field after `.`, found {}", found))
+        }
+        let name = self.current.map(|_| self.unwrap_current_id_unchecked());
+        self.sp(AstNodeKind::Field { target, name })
+    }
+
     fn binary(&mut self, lhs: AstNode<'a>, prec: u8) -> AstNode<'a> {
         macro_rules! binop_equiv {
             ($d:expr, $($id:ident),*) => {
                 match $d {
                     $(
-                        Token::$id => ast::BinOp::$id,
+                        Token::$id => Some(ast::BinOp::$id),
                     )*
-                    _ => panic!("thought it was a binop but it wasn't")
+                    _ => None
                 }
             };
         }
-        let op = self.current.map(|d| binop_equiv!(d,
+        let op = binop_equiv!(*self.current,
             Assign,
 
             Add, AddEq,
@@ -328,7 +914,9 @@ impl<'a> Parser<'a> {
             Lt, LtEq,
 
             And, Or
-        ));
+        );
+        let Some(op) = op else { return self.misread("an operator like `+` or `==`") };
+        let op = self.current.map(|_| op);
 
         let rhs = self.parse_with_prec(prec);
 
@@ -357,31 +945,118 @@ impl<'a> Parser<'a> {
         }
     }
 
-    fn error<T: ToString>(&mut self, err: T) -> AstNode<'a> {
-        self.advance();
-        let node = self.sp(AstNodeKind::Error);
-        self.errors.push(CompileError {
-            span: &node.of[node.span.clone()],
-            message: err.to_string()
-        });
+    /// An empty span just after the current token, for things that are missing
+    fn after_current(&self) -> Sp<'a, ()> {
+        let end = self.current.span.end;
+        self.span_of(end..end)
+    }
+
+    fn span_of(&self, span: Range<usize>) -> Sp<'a, ()> {
+        Sp::new(self.lexer.prog, span, ())
+    }
+
+    /// Spans from the start of `start` to the end of the current token.
+    fn sp_from(&self, start: Sp<'a, ()>, node: AstNodeKind<'a>) -> AstNode<'a> {
+        Sp {
+            line: start.line,
+            col: start.col,
+            span: start.span.start..self.current.span.end,
+            of: self.lexer.prog,
+            data: RawAstNode::new(node)
+        }
+    }
+
+    /// Complains about the token we were about to read, and gives back a
+    /// placeholder for whatever should have been there.
+    fn error<T: ToString>(&mut self, code: &'static str, err: T) -> AstNode<'a> {
+        let node = self.next.map(|_| RawAstNode::new(AstNodeKind::Error));
+        self.report(&node, code, err);
         node
     }
 
-    fn advance(&mut self) -> &Token<'a> {
-        let mut next;
-        loop {
-            next = self.lexer.next().unwrap_or_else(|| Token::nothing_span());
-            if let Token::Comment(..) = *next {
-                continue
-            }
+    /// Complains that the token we just read was taken for `expected`, which
+    /// it isn't, and gives back a placeholder for it.
+    fn misread(&mut self, expected: &str) -> AstNode<'a> {
+        let node = self.current.map(|_| RawAstNode::new(AstNodeKind::Error));
+        let message = format!("expected {}, found `{}`", expected, self.current.text());
+        self.report(&node, codes::EXPECTED_EXPRESSION, message);
+        node
+    }
+
+    /// Records a syntax error, unless we're still recovering from the last one.
+    fn report<T: Debug>(&mut self, at: &Sp<'a, T>, code: &'static str, err: impl ToString) {
+        if !self.panicking {
+            self.errors.push(CompileError::error(at, err).with_code(code));
+            self.panicking = true;
+        }
+    }
 
-            break
+    /// Describes the token we were about to read, for error messages
+    fn found(&self) -> String {
+        match *self.next {
+            Token::Nothing => "the end of the file".to_string(),
+            _ => format!("`{}`", self.next.text())
         }
+    }
+
+    fn advance(&mut self) -> &Token<'a> {
+        let next = match self.pending.take() {
+            Some(next) => next,
+            None => self.lex()
+        };
+        let line_break = || self.lexer.prog[self.next.span.end..next.span.start].contains('\n');
+        let next = if self.next.semicolon_inbetween(&next) && line_break() {
+            self.pending = Some(next);
+            self.next_docs.clear();
+            let end = self.next.span.end;
+            self.span_of(end..end).map(|_| Token::Semicolon)
+        } else {
+            self.next_docs = mem::take(&mut self.docs);
+            next
+        };
         mem::swap(&mut self.next, &mut self.current);
         self.next = next;
         &self.current
     }
 
+    /// The next token from the lexer that the parser cares about.
+    fn lex(&mut self) -> Sp<'a, Token<'a>> {
+        let mut next;
+        loop {
+            next = self.lexer.next().unwrap_or_else(|| self.eof());
+            match &*next {
+                Token::Comment(CommentType::Doc, text) => {
+                    self.docs.push(text);
+                    continue
+                },
+                Token::Comment(..) => continue,
+                Token::Error(e) => {
+                    let err = CompileError::error(&next, e).with_code(e.code());
+                    self.errors.push(err);
+                    // whatever was there is missing now, which isn't worth another error
+                    self.panicking = true;
+                    continue
+                },
+                _ => break
+            }
+        }
+        next
+    }
+
+    /// An empty token at the very end of the program
+    fn eof(&self) -> Sp<'a, Token<'a>> {
+        let prog = self.lexer.prog;
+        let mut eof = Sp {
+            line: 0,
+            col: 0,
+            span: prog.len()..prog.len(),
+            of: prog,
+            data: Token::Nothing
+        };
+        (eof.line, eof.col) = eof.line_col(prog.len());
+        eof
+    }
+
     fn pick(&mut self, tt: &Token) -> bool {
         if mem::discriminant(tt) == mem::discriminant(&self.next) {
             self.advance();
@@ -389,3 +1064,45 @@ impl<'a> Parser<'a> {
         } else { false }
     }
 }
+
+/// Makes the value of an integer literal, or describes the type it's too big
+/// for. `negated` numbers had a `-` in front.
+fn int_constant(n: u64, negated: bool, suffix: Option<&str>) -> Result<ast::CompInt, &'static str> {
+    macro_rules! fit {
+        ($variant:ident, $t:ty, $name:expr) => {{
+            let signed = <$t>::MIN != 0;
+            let value = if negated && signed { -(n as i128) } else { n as i128 };
+            if value < <$t>::MIN as i128 || value > <$t>::MAX as i128 {
+                return Err($name)
+            }
+            ast::CompInt::$variant(value as $t)
+        }};
+    }
+    Ok(match suffix {
+        Some("i8") => fit!(I8, i8, "an `i8`, which goes from -128 to 127"),
+        Some("i16") => fit!(I16, i16, "an `i16`, which goes from -32768 to 32767"),
+        Some("i32") => fit!(I32, i32, "an `i32`, which goes from -2147483648 to 2147483647"),
+        Some("isize") => fit!(ISize, isize, "an `isize`, which goes from -9223372036854775808 to 9223372036854775807"),
+        Some("u8") => fit!(U8, u8, "a `u8`, which goes up to 255"),
+        Some("u16") => fit!(U16, u16, "a `u16`, which goes up to 65535"),
+        Some("u32") => fit!(U32, u32, "a `u32`, which goes up to 4294967295"),
+        Some("u64") => fit!(U64, u64, "a `u64`, which goes up to 18446744073709551615"),
+        Some("usize") => fit!(USize, usize, "a `usize`, which goes up to 18446744073709551615"),
+        _ => fit!(I64, i64, "an `i64`, which goes from -9223372036854775808 to 9223372036854775807")
+    })
+}
+
+/// Makes the value of a float literal, or describes the type it's too big for.
+fn float_constant(f: f64, suffix: Option<&str>) -> Result<ast::CompFloat, &'static str> {
+    match suffix {
+        Some("f32") if (f as f32).is_infinite() => Err("an `f32`"),
+        Some("f32") => Ok(ast::CompFloat::F32(f as f32)),
+        _ if f.is_infinite() => Err("an `f64`"),
+        _ => Ok(ast::CompFloat::F64(f))
+    }
+}
+
+/// Whether an attribute sets how loudly lints are reported
+fn is_lint_level(name: &str) -> bool {
+    matches!(name, "allow" | "warn" | "deny")
+}
@@ -0,0 +1,157 @@
+//! Rendering Diagnostics
+//!
+//! Shows a [`CompileError`] the way rustc would: what went wrong, where, and
+//! the lines of code involved with the interesting parts underlined.
+
+use std::fmt::Write;
+
+use super::{CompileError, Severity, Sp};
+
+const TAB: &str = "    ";
+
+struct Style {
+    color: bool
+}
+
+impl Style {
+    const BOLD: &'static str = "1";
+    const RED: &'static str = "1;31";
+    const YELLOW: &'static str = "1;33";
+    const BLUE: &'static str = "1;34";
+
+    fn paint(&self, code: &str, text: &str) -> String {
+        if self.color {
+            format!("\x1b[{}m{}\x1b[0m", code, text)
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+/// An underline beneath one line of code
+struct Mark<'e> {
+    of: &'e str,
+    line: usize,
+    /// Where the underline starts and how long it is, in columns
+    start: usize,
+    width: usize,
+    primary: bool,
+    message: &'e str
+}
+
+impl CompileError<'_> {
+    /// Renders this for a terminal, calling the code it's about `file`.
+    pub fn render(&self, file: &str, color: bool) -> String {
+        let style = Style { color };
+        let severity_color = match self.severity {
+            Severity::Error => Style::RED,
+            Severity::Warning => Style::YELLOW
+        };
+        let mut out = String::new();
+        let header = match self.code {
+            Some(code) => format!("{}[{}]", self.severity.name(), code),
+            None => self.severity.name().to_string()
+        };
+        let _ = writeln!(out, "{}{}", style.paint(severity_color, &header), style.paint(Style::BOLD, &format!(": {}", self.message)));
+
+        if is_builtin(&self.at) {
+            return out
+        }
+        let (line, col) = self.at.line_col(self.at.span.start);
+
+        let mut marks = vec![mark(&self.at, true, "")];
+        marks.extend(self.labels.iter()
+            .filter(|l| !is_builtin(&l.at))
+            .map(|l| mark(&l.at, false, &l.message)));
+
+        let width = marks.iter().map(|m| m.line).max().unwrap_or(line).to_string().len();
+        let pad = " ".repeat(width);
+        let gutter = |text: &str| style.paint(Style::BLUE, text);
+
+        // labels can point into other code, like an earlier line in the REPL
+        let mut sources: Vec<&str> = vec![];
+        for m in &marks {
+            if !sources.iter().any(|s| std::ptr::eq(*s, m.of)) {
+                sources.push(m.of);
+            }
+        }
+        for (i, source) in sources.into_iter().enumerate() {
+            let mut group: Vec<_> = marks.iter().filter(|m| std::ptr::eq(m.of, source)).collect();
+            // keep the primary mark first among the ones on its line
+            group.sort_by_key(|m| (m.line, !m.primary));
+
+            if i == 0 {
+                let _ = writeln!(out, "{}{} {}:{}:{}", pad, gutter("-->"), file, line, col);
+            } else {
+                let _ = writeln!(out, "{} {}", pad, gutter("|"));
+                let _ = writeln!(out, "{}{} {}:{}:{}", pad, gutter(":::"), file, group[0].line, group[0].start + 1);
+            }
+            let _ = writeln!(out, "{} {}", pad, gutter("|"));
+            let lines: Vec<_> = source.split('\n').collect();
+            let mut last_line = None;
+            for m in group {
+                if last_line != Some(m.line) {
+                    if last_line.is_some_and(|l| m.line > l + 1) {
+                        let _ = writeln!(out, "{}", gutter("..."));
+                    }
+                    let text = lines.get(m.line - 1).copied().unwrap_or_default();
+                    let _ = writeln!(
+                        out,
+                        "{} {}",
+                        gutter(&format!("{:>width$} |", m.line, width = width)),
+                        text.trim_end().replace('\t', TAB)
+                    );
+                    last_line = Some(m.line);
+                }
+
+                let (underline, color) = if m.primary {
+                    ("^".repeat(m.width), severity_color)
+                } else {
+                    ("-".repeat(m.width), Style::BLUE)
+                };
+                let label = if m.message.is_empty() {
+                    underline
+                } else {
+                    format!("{} {}", underline, m.message)
+                };
+                let _ = writeln!(out, "{} {} {}{}", pad, gutter("|"), " ".repeat(m.start), style.paint(color, &label));
+            }
+        }
+        out
+    }
+}
+
+fn is_builtin(at: &Sp<()>) -> bool {
+    at.line == 0
+}
+
+fn mark<'e>(at: &Sp<'e, ()>, primary: bool, message: &'e str) -> Mark<'e> {
+    let src = at.of;
+    let start = at.span.start.min(src.len());
+    let line_start = src[..start].rfind('\n').map(|i| i + 1).unwrap_or(0);
+    let line_end = src[start..].find('\n').map(|i| start + i).unwrap_or(src.len());
+    // anything past the end of the first line just gets cut off
+    let end = at.span.end.clamp(start, line_end);
+
+    let columns = |s: &str| s.chars().map(width).sum::<usize>();
+    Mark {
+        of: src,
+        line: src[..start].matches('\n').count() + 1,
+        start: columns(&src[line_start..start]),
+        width: columns(&src[start..end]).max(1),
+        primary,
+        message
+    }
+}
+
+/// How many columns a terminal gives `ch`. Tabs are shown as spaces, and most
+/// CJK characters and emoji take up two.
+fn width(ch: char) -> usize {
+    match ch {
+        '\t' => TAB.len(),
+        '\u{1100}'..='\u{115F}' | '\u{2E80}'..='\u{303E}' | '\u{3041}'..='\u{A4CF}' | '\u{AC00}'..='\u{D7A3}'
+            | '\u{F900}'..='\u{FAFF}' | '\u{FE30}'..='\u{FE4F}' | '\u{FF00}'..='\u{FF60}' | '\u{FFE0}'..='\u{FFE6}'
+            | '\u{1F300}'..='\u{1F64F}' | '\u{1F900}'..='\u{1F9FF}' | '\u{20000}'..='\u{3FFFD}' => 2,
+        _ => 1
+    }
+}
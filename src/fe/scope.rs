@@ -1,24 +1,34 @@
-use super::{ast::{AstNode, Statement, AstNodeKind, Reference}, symbols::{SymbolTable, Symbol}, Sp};
+use super::{ast::{AstNode, Statement, AstNodeKind, Reference, Pattern, MaybeTyped}, symbols::{SymbolTable, Symbol, Builtin}, codes, CompileError, Sp};
 
 pub struct ScopeItem<'a> {
     name: &'a str,
     depth: usize,
-    item: usize
+    item: usize,
+    /// Functions and structs can be seen from inside functions, variables can't
+    shared: bool
 }
 
+#[derive(Default)]
 pub struct Scope<'a> {
     /// A stack of ScopeItems.
     /// start = least nested item in scope
     /// end = most nested item in scope
     pub items: Vec<ScopeItem<'a>>,
-    pub depth: usize
+    pub depth: usize,
+    /// Variables before this belong to an enclosing function, which can't be seen from here
+    barrier: usize,
+    /// The loops we're in, innermost last, with their labels. Labels don't
+    /// share names with anything else.
+    loops: Vec<Option<Sp<'a, (&'a str, usize)>>>
 }
 
 impl<'a> Scope<'a> {
     pub fn new() -> Self {
         Self {
             items: vec![],
-            depth: 0
+            depth: 0,
+            barrier: 0,
+            loops: vec![]
         }
     }
 
@@ -37,61 +47,276 @@ impl<'a> Scope<'a> {
         self.items.push(ScopeItem {
             name,
             depth: self.depth,
-            item
+            item,
+            shared: false
+        })
+    }
+
+    /// Adds something that can be seen from inside functions, like a
+    /// function or a struct.
+    pub fn add_shared(&mut self, item: usize, name: &'a str) {
+        self.items.push(ScopeItem {
+            name,
+            depth: self.depth,
+            item,
+            shared: true
         })
     }
 
     pub fn get(&self, name: &'a str) -> Option<usize> {
-        for item in self.items.iter().rev() {
-            if item.name == name {
-                return Some(item.item)
+        self.visible(name).next()
+    }
+
+    /// The struct called `name`, even if a variable of the same name hides it.
+    fn get_type(&self, syms: &SymbolTable<'a>, name: &'a str) -> Option<usize> {
+        self.visible(name).find(|id| matches!(syms.tbl.get(id).map(|s| &s.data), Some(Symbol::Type(..))))
+    }
+
+    /// Everything called `name` that can be seen from here, innermost first.
+    fn visible(&self, name: &'a str) -> impl Iterator<Item = usize> + '_ {
+        self.items.iter().enumerate().rev()
+            .filter(move |(i, item)| item.name == name && (*i >= self.barrier || item.shared))
+            .map(|(_, item)| item.item)
+    }
+
+    /// The variable called `name` that an enclosing function has, which this
+    /// one can't see.
+    fn hidden(&self, name: &'a str) -> Option<usize> {
+        self.items[..self.barrier].iter().rev()
+            .find(|item| item.name == name && !item.shared)
+            .map(|item| item.item)
+    }
+
+    /// Resolves a type that names a struct. Anything else is left for the
+    /// type checker, which knows the built in types.
+    fn resolve_type(&self, syms: &SymbolTable<'a>, ty: &mut MaybeTyped<'a>) {
+        if let MaybeTyped::TypeProvided(name) = ty {
+            if let Some(id) = self.get_type(syms, name.text()) {
+                **name = Reference::Resolved(id);
             }
         }
-        None
     }
 
-    pub fn resolve(&mut self, syms: &mut SymbolTable<'a>, ast: &mut AstNode<'a>) {
+    /// Where the loop we're in with this label is labeled, if there is one.
+    fn loop_labeled(&self, name: &str) -> Option<Sp<'a, ()>> {
+        self.loops.iter().flatten().find(|l| l.data.0 == name).map(|l| l.ditch())
+    }
+
+    /// Declares the label of a loop we're going into, if it has one.
+    fn enter_loop(&mut self, syms: &mut SymbolTable<'a>, label: &mut Option<Sp<'a, Reference<'a>>>) -> Option<CompileError<'a>> {
+        let mut error = None;
+        let declared = label.as_mut().map(|l| {
+            let name = l.data.clone().unwrap_str();
+            if let Some(outer) = self.loop_labeled(name) {
+                error = Some(
+                    CompileError::error(l, format!("the label `'{}` is already used by a loop around this one", name))
+                        .with_code(codes::SHADOWED_LABEL)
+                        .with_label(&outer, "the outer loop is labeled here")
+                );
+            }
+            let sym = syms.add(l.map(|_| Symbol::Label));
+            **l = Reference::Resolved(sym);
+            l.map(|_| (name, sym))
+        });
+        self.loops.push(declared);
+        error
+    }
+
+    /// Resolves the label of a `break` or `continue`, if it has one, and
+    /// makes sure there's a loop for it to go to.
+    fn jump(&self, keyword: &Sp<'a, ()>, label: &mut Option<Sp<'a, Reference<'a>>>) -> Option<CompileError<'a>> {
+        let Some(label) = label.as_mut() else {
+            return self.loops.is_empty().then(|| {
+                CompileError::error(keyword, format!("`{}` has to be inside a loop", keyword.text()))
+                    .with_code(codes::OUTSIDE_LOOP)
+            })
+        };
+        let name = label.data.clone().unwrap_str();
+        match self.loops.iter().flatten().rev().find(|l| l.data.0 == name) {
+            Some(l) => {
+                **label = Reference::Resolved(l.data.1);
+                None
+            },
+            None => Some(
+                CompileError::error(label, format!("there's no loop labeled `'{}` around this `{}`", name, keyword.text()))
+                    .with_code(codes::UNKNOWN_LABEL)
+            )
+        }
+    }
+
+    /// Resolves every name in `ast`. Names that don't refer to anything are
+    /// left unresolved and reported.
+    pub fn resolve(&mut self, syms: &mut SymbolTable<'a>, ast: &mut AstNode<'a>) -> Vec<CompileError<'a>> {
+        let mut errors = vec![];
         match &mut *ast.kind {
             AstNodeKind::Reference(ref mut r) => {
                 let ra = r.clone().unwrap_str();
-                let thing = self.get(ra)
-                    .expect(&format!("unresolved identifier `{}`", ra));
-                *r = Reference::Resolved(thing);
+                match self.get(ra) {
+                    Some(thing) => *r = Reference::Resolved(thing),
+                    None => match Builtin::from_name(ra) {
+                        Some(b) => *r = Reference::Builtin(b),
+                        None => {
+                            let mut e = CompileError::error(ast, format!("cannot find `{}` in this scope", ra))
+                                .with_code(codes::UNRESOLVED_NAME);
+                            if let Some(outside) = self.hidden(ra).and_then(|id| syms.tbl.get(&id)) {
+                                e = e.with_label(outside, "this is outside of the function, and functions can't see variables from outside");
+                            }
+                            errors.push(e)
+                        }
+                    }
+                };
             },
-            AstNodeKind::BinOp { a, b, .. } => { self.resolve(syms, a); self.resolve(syms, b); }
-            AstNodeKind::UnOp { target, .. } => { self.resolve(syms, target); }
-            AstNodeKind::Block { stmts } => { self.resolve_block(syms, stmts); }
+            AstNodeKind::BinOp { a, b, .. } => {
+                errors.append(&mut self.resolve(syms, a));
+                errors.append(&mut self.resolve(syms, b));
+            }
+            AstNodeKind::UnOp { target, .. } => { errors.append(&mut self.resolve(syms, target)); }
+            AstNodeKind::Block { stmts } => {
+                self.start();
+                errors.append(&mut self.resolve_block(syms, stmts));
+                self.end();
+            }
             AstNodeKind::If { condition, body, else_body } => {
-                self.resolve(syms, condition);
-                self.resolve(syms, body);
+                errors.append(&mut self.resolve(syms, condition));
+                errors.append(&mut self.resolve(syms, body));
                 if let Some(eb) = else_body {
-                    self.resolve(syms, eb);
+                    errors.append(&mut self.resolve(syms, eb));
+                }
+            },
+            AstNodeKind::Loop { label, body } => {
+                errors.extend(self.enter_loop(syms, label));
+                errors.append(&mut self.resolve(syms, body));
+                self.loops.pop();
+            },
+            AstNodeKind::While { label, condition, body } => {
+                errors.append(&mut self.resolve(syms, condition));
+                errors.extend(self.enter_loop(syms, label));
+                errors.append(&mut self.resolve(syms, body));
+                self.loops.pop();
+            },
+            AstNodeKind::For { label, var, from, to, body, .. } => {
+                errors.append(&mut self.resolve(syms, from));
+                errors.append(&mut self.resolve(syms, to));
+                self.start();
+                let sym = syms.add(var.map(|_| Symbol::Variable { ty: None }));
+                self.add(sym, var.data.clone().unwrap_str());
+                **var = Reference::Resolved(sym);
+                errors.extend(self.enter_loop(syms, label));
+                errors.append(&mut self.resolve(syms, body));
+                self.loops.pop();
+                self.end();
+            },
+            AstNodeKind::Call { callee, args } => {
+                errors.append(&mut self.resolve(syms, callee));
+                for a in args {
+                    errors.append(&mut self.resolve(syms, a));
+                }
+            },
+            AstNodeKind::Interpolation { parts } => {
+                for p in parts {
+                    errors.append(&mut self.resolve(syms, p));
+                }
+            },
+            AstNodeKind::StructLiteral { name, fields } => {
+                // anything else with the name is left for the type checker to complain about
+                let ra = name.data.clone().unwrap_str();
+                match self.get_type(syms, ra).or_else(|| self.get(ra)) {
+                    Some(thing) => **name = Reference::Resolved(thing),
+                    None => errors.push(
+                        CompileError::error(name, format!("cannot find `{}` in this scope", ra))
+                            .with_code(codes::UNRESOLVED_NAME)
+                    )
+                }
+                for f in fields {
+                    errors.append(&mut self.resolve(syms, &mut f.value));
+                }
+            },
+            AstNodeKind::Field { target, .. } => errors.append(&mut self.resolve(syms, target)),
+            AstNodeKind::Match { value, arms } => {
+                errors.append(&mut self.resolve(syms, value));
+                for arm in arms {
+                    // a name in the pattern is only around for its arm
+                    self.start();
+                    if let Pattern::Binding(id) = &mut arm.pattern {
+                        let sym = syms.add(id.map(|_| Symbol::Variable { ty: None }));
+                        self.add(sym, id.data.clone().unwrap_str());
+                        **id = Reference::Resolved(sym);
+                    }
+                    errors.append(&mut self.resolve(syms, &mut arm.body));
+                    self.end();
                 }
             },
-            AstNodeKind::Loop { body } => { self.resolve(syms, body) },
             _ => { /* irrelevant! */ }
         }
+        errors
     }
-    
-    pub fn resolve_block(&mut self, syms: &mut SymbolTable<'a>, stmts: &mut Vec<Statement<'a>>) {
+
+    pub fn resolve_block(&mut self, syms: &mut SymbolTable<'a>, stmts: &mut Vec<Statement<'a>>) -> Vec<CompileError<'a>> {
+        let mut errors = vec![];
+        // functions and structs can be used from anywhere in their block,
+        // even before they're declared, so they can refer to each other
+        for s in stmts.iter_mut() {
+            match s {
+                Statement::Function { name, id, .. } => {
+                    let sym = syms.add(name.map(|_| Symbol::Function { ty: None, inferring: false }));
+                    self.add_shared(sym, name.data);
+                    *id = Some(sym);
+                },
+                Statement::Struct { name, id, fields, .. } => {
+                    let sym = syms.add_struct(name, fields.is_none());
+                    self.add_shared(sym, name.data);
+                    *id = Some(sym);
+                },
+                _ => {}
+            }
+        }
         for s in stmts {
             match s {
-                Statement::Declare { value, ref mut id, .. } => {
-                    self.resolve(syms, value);
+                Statement::Declare { value, ref mut id, with_type, .. } => {
+                    self.resolve_type(syms, with_type);
+                    errors.append(&mut self.resolve(syms, value));
                     // add new thing to the thang
-                    // HACK: THIS IS BUILTIN ABUSE!!!
-                    let sym = syms.add(Sp::builtin(Symbol::Variable { ty: None }));
+                    let sym = syms.add(id.map(|_| Symbol::Variable { ty: None }));
 
-                    self.add(sym, id.clone().unwrap_str());
-                    *id = Reference::Resolved(sym);
+                    self.add(sym, id.data.clone().unwrap_str());
+                    **id = Reference::Resolved(sym);
                 },
                 Statement::Expression(e)
                     | Statement::Return(e)
-                    | Statement::Out(e)
-                    | Statement::Break { with: Some(e), .. } => self.resolve(syms, e),
-                Statement::Continue { .. } | Statement::Break { .. }=> {/* nothing to do */}
-                Statement::Error | Statement::Import { .. } => todo!(),
+                    | Statement::Out(e) => errors.append(&mut self.resolve(syms, e)),
+                Statement::Break { keyword, label, with } => {
+                    errors.extend(self.jump(keyword, label));
+                    if let Some(e) = with {
+                        errors.append(&mut self.resolve(syms, e));
+                    }
+                },
+                Statement::Continue { keyword, label } => errors.extend(self.jump(keyword, label)),
+                Statement::Function { params, returns, body, .. } => {
+                    for p in params.iter_mut() {
+                        self.resolve_type(syms, &mut p.with_type);
+                    }
+                    self.resolve_type(syms, returns);
+                    // loops around a function can't be broken out of from inside it
+                    let loops = std::mem::take(&mut self.loops);
+                    let outside = std::mem::replace(&mut self.barrier, self.items.len());
+                    self.start();
+                    for p in params {
+                        let sym = syms.add(p.id.map(|_| Symbol::Variable { ty: None }));
+                        self.add(sym, p.id.data.clone().unwrap_str());
+                        *p.id = Reference::Resolved(sym);
+                    }
+                    errors.append(&mut self.resolve(syms, body));
+                    self.end();
+                    self.barrier = outside;
+                    self.loops = loops;
+                },
+                Statement::Struct { fields, .. } => for f in fields.iter_mut().flatten() {
+                    self.resolve_type(syms, &mut f.with_type);
+                },
+                // the parser has already complained about these
+                Statement::Error | Statement::Import { .. } => {},
             }
         }
+        errors
     }
 }
@@ -1,6 +1,6 @@
-use std::collections::HashMap;
+use std::{borrow::Cow, collections::HashMap};
 
-use super::{Sp, ast::{AstNode, AstNodeKind}};
+use super::Sp;
 
 pub enum TypeProps {
     Integer {
@@ -93,7 +93,9 @@ primitives!(
 #[derive(Debug, PartialEq)]
 pub enum TypeKind<'a> {
     Primitive,
-    Struct { fields: HashMap<&'a str, usize> },
+    /// Fields are in the order they're declared, which is how they're laid
+    /// out. A field whose type couldn't be worked out doesn't have one.
+    Struct { fields: Vec<(&'a str, Option<usize>)> },
     UnitStruct,
     Tuple { fields: Vec<usize> },
     Function {
@@ -108,9 +110,40 @@ pub struct Type<'a> {
     kind: TypeKind<'a>
 }
 
+/// Functions that come with the language
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Builtin {
+    /// `assert(cond)` fails the running test if `cond` is false
+    Assert
+}
+
+impl Builtin {
+    pub fn from_name(name: &str) -> Option<Builtin> {
+        match name {
+            "assert" => Some(Builtin::Assert),
+            _ => None
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Builtin::Assert => "assert"
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum Symbol<'a> {
     Variable { ty: Option<usize> },
+    Function {
+        /// Its function type, once that's been worked out
+        ty: Option<usize>,
+        /// Set until what it gives back has been worked out from its body,
+        /// which has to happen before it can be called
+        inferring: bool
+    },
+    /// The label of a loop, which only `break` and `continue` can refer to
+    Label,
     Type(Type<'a>)
 }
 
@@ -120,6 +153,12 @@ pub struct SymbolTable<'a> {
     pub primitive_map: HashMap<PrimitiveType, usize>
 }
 
+impl Default for SymbolTable<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<'a> SymbolTable<'a> {
     pub fn new() -> Self {
         let mut s = Self {
@@ -151,6 +190,19 @@ impl<'a> SymbolTable<'a> {
         self.primitive_map.iter().find(|(_, a)| *a == &t).unwrap().0
     }
 
+    /// The ID the next symbol will get. Everything added from now on gets
+    /// this ID or a bigger one.
+    pub fn next_id(&self) -> usize {
+        self.next_id
+    }
+
+    /// Forgets every symbol added since `next_id` gave back `id`, like the
+    /// ones from code that turned out to be wrong. Their IDs aren't given
+    /// out again, in case something still holds on to one.
+    pub fn forget_from(&mut self, id: usize) {
+        self.tbl.retain(|&i, _| i < id);
+    }
+
     pub fn add(&mut self, s: Sp<'a, Symbol<'a>>) -> usize {
         let id = self.allocate_id();
         self.tbl.insert(id, s);
@@ -160,5 +212,87 @@ impl<'a> SymbolTable<'a> {
     pub fn unit(&self) -> usize {
         0
     }
+
+    /// What kind of primitive a type is. Anything that isn't a primitive is
+    /// [`TypeProps::Standalone`].
+    pub fn type_props(&self, t: usize) -> TypeProps {
+        self.primitive_map.iter()
+            .find(|(_, id)| **id == t)
+            .map(|(prim, _)| prim.get_props())
+            .unwrap_or(TypeProps::Standalone)
+    }
+
+    /// The built in type that's written as `name` in code, if there is one.
+    /// Structs can share names, so they're found through the scope instead.
+    pub fn type_named(&self, name: &str) -> Option<usize> {
+        self.tbl.iter().find_map(|(id, s)| match &s.data {
+            Symbol::Type(ty) if ty.name == name && !matches!(ty.kind, TypeKind::Struct { .. } | TypeKind::UnitStruct) => Some(*id),
+            _ => None
+        })
+    }
+
+    /// Declares a struct called `name`. Its fields are filled in once their
+    /// types have been worked out.
+    pub fn add_struct(&mut self, name: &Sp<'a, &'a str>, unit: bool) -> usize {
+        let kind = if unit { TypeKind::UnitStruct } else { TypeKind::Struct { fields: vec![] } };
+        self.add(name.map(|n| Symbol::Type(Type { name: n, kind })))
+    }
+
+    pub fn set_fields(&mut self, t: usize, to: Vec<(&'a str, Option<usize>)>) {
+        if let Some(Symbol::Type(Type { kind: TypeKind::Struct { fields }, .. })) = self.tbl.get_mut(&t).map(|s| &mut s.data) {
+            *fields = to;
+        }
+    }
+
+    /// The fields of a struct, or `None` if `t` isn't one.
+    pub fn fields(&self, t: usize) -> Option<&[(&'a str, Option<usize>)]> {
+        match self.tbl.get(&t).map(|s| &s.data) {
+            Some(Symbol::Type(Type { kind: TypeKind::Struct { fields }, .. })) => Some(fields),
+            Some(Symbol::Type(Type { kind: TypeKind::UnitStruct, .. })) => Some(&[]),
+            _ => None
+        }
+    }
+
+    /// Where the field called `name` is in a struct, and its type.
+    pub fn field(&self, t: usize, name: &str) -> Option<(usize, Option<usize>)> {
+        self.fields(t)?.iter().enumerate().find(|(_, (n, _))| *n == name).map(|(i, (_, ty))| (i, *ty))
+    }
+
+    /// Whether `t` is a struct that's written without any braces
+    pub fn is_unit_struct(&self, t: usize) -> bool {
+        matches!(self.tbl.get(&t).map(|s| &s.data), Some(Symbol::Type(Type { kind: TypeKind::UnitStruct, .. })))
+    }
+
+    /// The name of the type with the given id, as it's written in code.
+    pub fn type_name(&self, t: usize) -> Cow<'a, str> {
+        match self.tbl.get(&t).map(|s| &s.data) {
+            Some(Symbol::Type(Type { kind: TypeKind::Function { params, out }, .. })) => {
+                let params: Vec<_> = params.iter().map(|p| self.type_name(*p)).collect();
+                Cow::Owned(format!("fn({}) -> {}", params.join(", "), self.type_name(*out)))
+            },
+            Some(Symbol::Type(ty)) => Cow::Borrowed(ty.name),
+            _ => Cow::Borrowed("<unknown>")
+        }
+    }
+
+    /// The type of functions taking `params` and giving back `out`. There's
+    /// only ever one of each, so they can be compared by id like any other.
+    pub fn function_type(&mut self, params: Vec<usize>, out: usize) -> usize {
+        let kind = TypeKind::Function { params, out };
+        let existing = self.tbl.iter().find_map(|(id, s)| match &s.data {
+            Symbol::Type(ty) if ty.kind == kind => Some(*id),
+            _ => None
+        });
+        // `fn` is a keyword, so `type_named` never finds these
+        existing.unwrap_or_else(|| self.add(Sp::builtin(Symbol::Type(Type { name: "fn", kind }))))
+    }
+
+    /// What a function type takes and gives back, if `t` is one.
+    pub fn signature(&self, t: usize) -> Option<(&[usize], usize)> {
+        match self.tbl.get(&t).map(|s| &s.data) {
+            Some(Symbol::Type(Type { kind: TypeKind::Function { params, out }, .. })) => Some((params, *out)),
+            _ => None
+        }
+    }
 }
 
@@ -1,6 +1,6 @@
 use super::{lexer::LexError, Sp};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum CommentType {
     Regular,
     Doc,
@@ -10,13 +10,16 @@ pub enum CommentType {
 #[derive(Debug)]
 pub enum Token<'a> {
     Identifier(&'a str),
+    /// `'name`, which names a loop for `break` and `continue`
+    Label(&'a str),
 
     Comment(CommentType, &'a str), 
 
     // Literals
     String(&'a str),
-    CompInt(i64),
-    CompFloat(f64),
+    /// The value and the suffix, like `u8`, if it has one
+    CompInt(u64, Option<&'a str>),
+    CompFloat(f64, Option<&'a str>),
     
     // Operators and their respective = variants
     Add, AddEq, // +
@@ -26,6 +29,7 @@ pub enum Token<'a> {
     Pow, PowEq, // **
     Mod, ModEq, // %
     Dot, // .
+    DotDot, DotDotEq, // .. / ..=
     Try, // ?
     Not, // !
     Assign, // =
@@ -47,9 +51,7 @@ pub enum Token<'a> {
     LBracket, RBracket, // []
     Comma, // ,
     Colon, // :
-    // TODO: automatic semicolon insertion
-    Semicolon, // ;
-    Newline, // Parser::advance deals with this for automatic semicolon insertion
+    Semicolon, // ; (or a line break, see `semicolon_inbetween`)
 
     // Other
     At, // @
@@ -64,6 +66,7 @@ pub enum Token<'a> {
     Fn,
     For,
     While,
+    In,
     Loop,
     Break,
     Continue,
@@ -99,26 +102,30 @@ impl Token<'_> {
 
     pub fn is_value(&self) -> bool {
         use Token::*;
-        match self {
-            CompInt(..) | CompFloat(..) | String(..) | Nil | True | False => true,
-            _ => false
-        }
+        matches!(self, CompInt(..) | CompFloat(..) | String(..) | Nil | True | False)
     }
 
-    // TODO: Stuff for optional semicolons
-
     pub fn can_end_stmt(&self) -> bool {
         use Token::*;
         match self {
-            Break | Continue | Return => true,
+            Identifier(..) | Label(..) | RParen | RBracket | RBrace | Try | Break | Continue | Return => true,
             _ => self.is_value()
         }
     }
 
     pub fn can_start_stmt(&self) -> bool {
-        todo!()
+        use Token::*;
+        match self {
+            Identifier(..) | Label(..) | LParen | LBrace | LBracket | Sub | Not | At | LeftArrow
+                | Let | If | Fn | For | While | Loop | Break | Continue | Return
+                | Enum | Struct | Import | Pub | Match => true,
+            _ => self.is_value()
+        }
     }
 
+    /// Whether a line break between these two ends a statement, as if there
+    /// were a `;`. Lines that start with something like `.` or `+` carry on
+    /// the one before.
     pub fn semicolon_inbetween(&self, next: &Self) -> bool {
         self.can_end_stmt() && next.can_start_stmt()
     }
@@ -134,7 +141,6 @@ impl Token<'_> {
             Add | Sub => prec::TERM,
             Mul | Div | Mod => prec::FACTOR,
             Pow => prec::POW,
-            Not => prec::UNARY, // NOTE: `-` is handled by TERM above
             LParen | Dot => prec::CALL,
             _ => prec::NONE
         }
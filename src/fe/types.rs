@@ -1,28 +1,53 @@
 use crate::fe::ast::UnOp;
 use crate::fe::symbols::TypeProps;
-use super::{ast::{ConstantValue, AstNodeKind, AstNode, CompFloat, CompInt, BinOp, Statement, MaybeTyped}, CompileError, symbols::{SymbolTable, PrimitiveType, Symbol}};
+use super::{Sp, ast::{ConstantValue, AstNodeKind, AstNode, CompFloat, CompInt, BinOp, Statement, MaybeTyped, Reference, Pattern}, codes, CompileError, symbols::{SymbolTable, PrimitiveType, Symbol, Builtin}};
 
+/// Works out the type of `ast` and everything in it. Nodes whose type can't
+/// be worked out are left without one, and whatever went wrong is reported
+/// once, where it happened, so nothing around them complains about it again.
 pub fn type_check<'a>(
     symbols: &mut SymbolTable<'a>,
     ast: &mut AstNode<'a>
 ) -> Vec<CompileError<'a>> {
     let mut errors = vec![];
+    let mut uncalled = None;
 
-    ast.type_data = Some(match &mut *ast.kind {
-        AstNodeKind::Reference(r) => {
-            let t = symbols.tbl.get(
-                &r.clone().unwrap_resolved()
-            ).unwrap();
-            
-            match t.data {
-                Symbol::Variable { ty } => ty.unwrap(),
-                _ => unreachable!()
-            }
+    ast.type_data = match &mut *ast.kind {
+        AstNodeKind::Reference(Reference::Builtin(b)) => {
+            uncalled = Some(*b);
+            Some(symbols.unit())
+        },
+        AstNodeKind::Reference(Reference::Resolved(id)) => match symbols.tbl.get(id).map(|s| &s.data) {
+            Some(Symbol::Variable { ty }) => *ty,
+            // TODO: first class functions
+            Some(Symbol::Function { .. }) => {
+                errors.push(
+                    CompileError::error(ast, format!("`{}` can only be called, functions can't be passed around yet", ast.text()))
+                        .with_code(codes::UNSUPPORTED)
+                );
+                None
+            },
+            Some(Symbol::Type(..)) if symbols.is_unit_struct(*id) => Some(*id),
+            Some(Symbol::Type(..)) => {
+                let fields: Vec<_> = symbols.fields(*id).unwrap_or_default().iter().map(|(f, _)| format!("{}: ..", f)).collect();
+                let written = match &fields[..] {
+                    [] => format!("{} {{}}", ast.text()),
+                    _ => format!("{} {{ {} }}", ast.text(), fields.join(", "))
+                };
+                errors.push(
+                    CompileError::error(ast, format!("`{}` is a struct, so it's written `{}`", ast.text(), written))
+                        .with_code(codes::BAD_STRUCT_LITERAL)
+                );
+                None
+            },
+            _ => unreachable!()
         },
+        // the scope has already complained about this one
+        AstNodeKind::Reference(Reference::Unresolved(..)) => None,
         AstNodeKind::Value(v) => match v {
             // hideous code incoming!
-            ConstantValue::String(..) => symbols.get_primitive(PrimitiveType::String),
-            ConstantValue::CompInt(i) => match i {
+            ConstantValue::String(..) => Some(symbols.get_primitive(PrimitiveType::String)),
+            ConstantValue::CompInt(i) => Some(match i {
                 CompInt::I8(..) => symbols.get_primitive(PrimitiveType::I8),
                 CompInt::I16(..) => symbols.get_primitive(PrimitiveType::I16),
                 CompInt::I32(..) => symbols.get_primitive(PrimitiveType::I32),
@@ -33,32 +58,87 @@ pub fn type_check<'a>(
                 CompInt::U32(..) => symbols.get_primitive(PrimitiveType::U32),
                 CompInt::U64(..) => symbols.get_primitive(PrimitiveType::U64),
                 CompInt::USize(..) => symbols.get_primitive(PrimitiveType::USize),
-            },
-            ConstantValue::CompFloat(f) => match f {
+            }),
+            ConstantValue::CompFloat(f) => Some(match f {
                 CompFloat::F32(..) => symbols.get_primitive(PrimitiveType::F32),
                 CompFloat::F64(..) => symbols.get_primitive(PrimitiveType::F64)
+            }),
+            ConstantValue::Bool(..) => Some(symbols.get_primitive(PrimitiveType::Bool)),
+            ConstantValue::Nil => {
+                // TODO: sum types and lang items
+                errors.push(CompileError::error(ast, "`nil` isn't supported yet").with_code(codes::UNSUPPORTED));
+                None
             },
-            ConstantValue::Bool(..) => symbols.get_primitive(PrimitiveType::Bool),
-            ConstantValue::Nil => todo!("sum types and lang items"),
         },
         AstNodeKind::BinOp { a, b, op } => {
             errors.append(&mut type_check(symbols, a));
             errors.append(&mut type_check(symbols, b));
+            let bool_t = symbols.get_primitive(PrimitiveType::Bool);
+
+            // the type both sides share, if they do
+            let operands = match (a.type_data, b.type_data) {
+                (Some(ta), Some(tb)) if ta != tb => {
+                    errors.push(
+                        CompileError::error(op, format!(
+                            "`{}` needs both sides to have the same type, but they're `{}` and `{}`",
+                            op.text(), symbols.type_name(ta), symbols.type_name(tb)
+                        ))
+                        .with_code(codes::MISMATCHED_OPERANDS)
+                        .with_label(a, format!("this is `{}`", symbols.type_name(ta)))
+                        .with_label(b, format!("this is `{}`", symbols.type_name(tb)))
+                    );
+                    None
+                },
+                (Some(t), Some(_)) => Some(t),
+                _ => None
+            };
+            let numeric = |errors: &mut Vec<CompileError<'a>>, t: Option<usize>| match t {
+                Some(t) if matches!(symbols.type_props(t), TypeProps::Standalone) => {
+                    errors.push(
+                        CompileError::error(op, format!("`{}` only works on numbers, not `{}`", op.text(), symbols.type_name(t)))
+                            .with_code(codes::WRONG_OPERAND_TYPE)
+                            .with_label(a, format!("this is `{}`", symbols.type_name(t)))
+                    );
+                    None
+                },
+                t => t
+            };
+            if op.is_assignment() && !is_place(symbols, a) {
+                errors.push(
+                    CompileError::error(a, format!("`{}` can only change variables and fields", op.text()))
+                        .with_code(codes::NOT_ASSIGNABLE)
+                        .with_label(a, "this isn't a variable or a field")
+                );
+            }
+
             match **op {
-                BinOp::Add | BinOp::Sub | BinOp::Mul | BinOp::Div => {
-                    // HACK: this assumes that everything is a number
-                    if a.type_data != b.type_data {
-                        panic!("type mismatch!"); // TODO: error handling
-                    };
-                    a.type_data.unwrap()
+                BinOp::Add | BinOp::Sub | BinOp::Mul | BinOp::Div | BinOp::Pow | BinOp::Mod => {
+                    numeric(&mut errors, operands)
+                },
+                BinOp::AddEq | BinOp::SubEq | BinOp::MulEq | BinOp::DivEq | BinOp::PowEq | BinOp::ModEq => {
+                    numeric(&mut errors, operands);
+                    Some(symbols.unit())
                 },
-                BinOp::Eq | BinOp::NotEq | BinOp::Gt | BinOp::GtEq | BinOp::Lt | BinOp::LtEq => {
-                    symbols.get_primitive(PrimitiveType::Bool)
-                }
                 BinOp::Assign => {
-                    symbols.unit() // at least for now
+                    Some(symbols.unit()) // at least for now
+                },
+                BinOp::Eq | BinOp::NotEq => Some(bool_t),
+                BinOp::Gt | BinOp::GtEq | BinOp::Lt | BinOp::LtEq => {
+                    numeric(&mut errors, operands);
+                    Some(bool_t)
+                },
+                BinOp::And | BinOp::Or => {
+                    for side in [&*a, &*b] {
+                        if let Some(t) = side.type_data.filter(|t| *t != bool_t) {
+                            let e = CompileError::error(side, format!(
+                                "`{}` expects a `bool`, but this is `{}`",
+                                op.text(), symbols.type_name(t)
+                            )).with_code(codes::WRONG_OPERAND_TYPE);
+                            errors.push(declared_here(symbols, side, e));
+                        }
+                    }
+                    Some(bool_t)
                 }
-                _ => todo!("binop semantic analysis is not fully implemented")
             }
         },
         AstNodeKind::UnOp { op, target } => {
@@ -66,89 +146,892 @@ pub fn type_check<'a>(
             match op.data {
                 UnOp::Negate => {
                     // only signed ints and floats can be negated
-                    let t = target.type_data.unwrap();
-
-                    let prim = symbols.get_primitive_type_from_id(t).get_props();
-                    match prim {
-                        TypeProps::Integer { signed, .. } => {
-                            if !signed {
-                                panic!("cannot negate an unsigned integer")
-                            }
+                    if let Some(t) = target.type_data {
+                        let problem = match symbols.type_props(t) {
+                            TypeProps::Integer { signed: false, .. } => Some("cannot negate an unsigned integer".to_string()),
+                            TypeProps::Integer { .. } | TypeProps::Float { .. } => None,
+                            TypeProps::Standalone => Some(format!("cannot negate a `{}`", symbols.type_name(t)))
+                        };
+                        if let Some(message) = problem {
+                            errors.push(
+                                CompileError::error(op, message)
+                                    .with_code(codes::WRONG_OPERAND_TYPE)
+                                    .with_label(target, format!("this is `{}`", symbols.type_name(t)))
+                            );
                         }
-                        TypeProps::Float { .. } => { /* ok! */ }
-                        TypeProps::Standalone => panic!("cannot negate a non-number")
                     }
-                    t
+                    target.type_data
                 }
                 UnOp::Not => {
                     let b_type = symbols.get_primitive(PrimitiveType::Bool);
-                    if target.type_data != Some(b_type) {
-                        panic!("can only apply `!` to boolean"); // TODO: error handling
+                    if let Some(t) = target.type_data.filter(|t| *t != b_type) {
+                        errors.push(
+                            CompileError::error(op, format!("`!` can only be applied to a `bool`, not `{}`", symbols.type_name(t)))
+                                .with_code(codes::WRONG_OPERAND_TYPE)
+                                .with_label(target, format!("this is `{}`", symbols.type_name(t)))
+                        );
                     }
-                    b_type
+                    Some(b_type)
+                },
+                UnOp::Try => {
+                    errors.push(
+                        CompileError::error(op, "`?` isn't supported yet").with_code(codes::UNSUPPORTED)
+                    );
+                    None
                 }
-                _ => todo!("unop semantic analysis is not fully implemented")
             }
         },
         AstNodeKind::If { condition, body, else_body } => {
-            errors.append(&mut type_check(symbols, condition));
+            errors.append(&mut check_condition(symbols, condition));
             errors.append(&mut type_check(symbols, body));
-            if let Some(eb) = else_body {
-                errors.append(&mut type_check(symbols, eb));
+            match else_body {
+                // without an `else`, there's nothing to give back when it doesn't run
+                None => Some(symbols.unit()),
+                Some(eb) => {
+                    errors.append(&mut type_check(symbols, eb));
+                    match (body.type_data, eb.type_data) {
+                        (Some(t), Some(e)) if t != e => {
+                            errors.push(
+                                CompileError::error(eb, format!(
+                                    "expected `{}`, but the `else` gives back `{}`",
+                                    symbols.type_name(t), symbols.type_name(e)
+                                ))
+                                .with_code(codes::MISMATCHED_BRANCHES)
+                                .with_label(body, format!("the `if` gives back `{}` because of this", symbols.type_name(t)))
+                            );
+                            None
+                        },
+                        (t, e) => t.and(e)
+                    }
+                }
             }
-            body.type_data.unwrap()
         },
         AstNodeKind::Block { stmts } => {
-            let ret = type_check_block(symbols, stmts);
-            ret.0
+            let (ty, mut errs) = type_check_block(symbols, stmts);
+            errors.append(&mut errs);
+            ty
         },
-        AstNodeKind::Loop { body } => {
+        AstNodeKind::Loop { label, body } => {
             errors.append(&mut type_check(symbols, body));
-            body.type_data.unwrap()
-        }
-        AstNodeKind::Error => todo!("fix your parse error for now"),
-    });
+            let label = label.as_ref().map(|l| l.data.clone().unwrap_resolved());
+            let mut found = vec![];
+            breaks(body, label, false, &mut found);
+            loop_type(symbols, &found, None, &mut errors)
+        },
+        AstNodeKind::While { label, condition, body } => {
+            errors.append(&mut check_condition(symbols, condition));
+            errors.append(&mut type_check(symbols, body));
+            let label = label.as_ref().map(|l| l.data.clone().unwrap_resolved());
+            let mut found = vec![];
+            breaks(body, label, false, &mut found);
+            loop_type(symbols, &found, Some("while"), &mut errors)
+        },
+        AstNodeKind::For { label, var, from, to, body, .. } => {
+            errors.append(&mut type_check(symbols, from));
+            errors.append(&mut type_check(symbols, to));
+            let ty = match (from.type_data, to.type_data) {
+                (Some(a), Some(b)) if a != b => {
+                    errors.push(
+                        CompileError::error(to, format!(
+                            "both ends of a range have to be the same type, but this is `{}`",
+                            symbols.type_name(b)
+                        ))
+                        .with_code(codes::BAD_RANGE)
+                        .with_label(from, format!("this is `{}`", symbols.type_name(a)))
+                    );
+                    None
+                },
+                (Some(t), Some(_)) if !matches!(symbols.type_props(t), TypeProps::Integer { .. }) => {
+                    errors.push(
+                        CompileError::error(from, format!("ranges have to be integers, but this is `{}`", symbols.type_name(t)))
+                            .with_code(codes::BAD_RANGE)
+                    );
+                    None
+                },
+                (a, b) => a.and(b)
+            };
+            if let Some(Symbol::Variable { ty: var_ty }) = symbols.tbl.get_mut(&var.data.clone().unwrap_resolved()).map(|s| &mut s.data) {
+                *var_ty = ty;
+            }
+            errors.append(&mut type_check(symbols, body));
+            let label = label.as_ref().map(|l| l.data.clone().unwrap_resolved());
+            let mut found = vec![];
+            breaks(body, label, false, &mut found);
+            loop_type(symbols, &found, Some("for"), &mut errors)
+        },
+        AstNodeKind::Match { value, arms } => {
+            errors.append(&mut type_check(symbols, value));
+            // what the first arm that could be worked out gives back
+            let mut out: Option<(usize, Sp<'a, ()>)> = None;
+            let mut known = true;
+            let mut catch_all = false;
+            let mut bools = [false; 2];
+            for arm in arms.iter_mut() {
+                match &mut arm.pattern {
+                    Pattern::Value(v) => {
+                        errors.append(&mut type_check(symbols, v));
+                        if let (Some(expected), Some(t)) = (value.type_data, v.type_data) {
+                            if t != expected {
+                                errors.push(
+                                    CompileError::error(v, format!(
+                                        "expected `{}`, but this pattern is `{}`",
+                                        symbols.type_name(expected), symbols.type_name(t)
+                                    ))
+                                    .with_code(codes::MISMATCHED_PATTERN)
+                                    .with_label(value, format!("this is `{}`", symbols.type_name(expected)))
+                                );
+                            }
+                        }
+                        if let AstNodeKind::Value(ConstantValue::Bool(b)) = &*v.kind {
+                            bools[*b as usize] = true;
+                        }
+                    },
+                    Pattern::Binding(id) => {
+                        if let Some(Symbol::Variable { ty }) = symbols.tbl.get_mut(&id.data.clone().unwrap_resolved()).map(|s| &mut s.data) {
+                            *ty = value.type_data;
+                        }
+                        catch_all = true;
+                    },
+                    Pattern::Wildcard(..) => catch_all = true
+                }
+                errors.append(&mut type_check(symbols, &mut arm.body));
+                match (arm.body.type_data, &out) {
+                    (None, _) => known = false,
+                    (Some(t), None) => out = Some((t, arm.body.ditch())),
+                    (Some(t), Some((o, first))) => if t != *o {
+                        errors.push(
+                            CompileError::error(&arm.body, format!(
+                                "expected `{}`, but this arm gives back `{}`",
+                                symbols.type_name(*o), symbols.type_name(t)
+                            ))
+                            .with_code(codes::MISMATCHED_ARMS)
+                            .with_label(first, format!("the `match` gives back `{}` because of this", symbols.type_name(*o)))
+                        );
+                    }
+                }
+            }
+
+            let bool_t = symbols.get_primitive(PrimitiveType::Bool);
+            let covered = catch_all || (value.type_data == Some(bool_t) && bools == [true, true]);
+            if let Some(t) = value.type_data.filter(|_| !covered) {
+                errors.push(
+                    CompileError::error(value, format!(
+                        "this `match` doesn't cover every `{}`, it needs a `_ -> ..` arm for the rest",
+                        symbols.type_name(t)
+                    ))
+                    .with_code(codes::NON_EXHAUSTIVE)
+                );
+            }
+            match out {
+                Some((t, _)) if known => Some(t),
+                None if arms.is_empty() => Some(symbols.unit()),
+                _ => None
+            }
+        },
+        // anything can be put in a string
+        AstNodeKind::Interpolation { parts } => {
+            for p in parts.iter_mut() {
+                errors.append(&mut type_check(symbols, p));
+            }
+            Some(symbols.get_primitive(PrimitiveType::String))
+        },
+        AstNodeKind::Call { callee, args } => {
+            for a in args.iter_mut() {
+                errors.append(&mut type_check(symbols, a));
+            }
+            match &*callee.kind {
+                AstNodeKind::Reference(Reference::Builtin(Builtin::Assert)) => {
+                    let bool_t = symbols.get_primitive(PrimitiveType::Bool);
+                    match &args[..] {
+                        [cond] => if let Some(t) = cond.type_data.filter(|t| *t != bool_t) {
+                            let e = CompileError::error(cond, format!(
+                                "`assert` expects a `bool`, but this is `{}`",
+                                symbols.type_name(t)
+                            )).with_code(codes::BAD_BUILTIN_CALL);
+                            errors.push(declared_here(symbols, cond, e));
+                        },
+                        _ => errors.push(
+                            CompileError::error(callee, "`assert` takes exactly one argument").with_code(codes::BAD_BUILTIN_CALL)
+                        )
+                    }
+                    Some(symbols.unit())
+                },
+                AstNodeKind::Reference(Reference::Resolved(id))
+                    if matches!(symbols.tbl.get(id).map(|s| &s.data), Some(Symbol::Function { .. })) => {
+                    let id = *id;
+                    check_call(symbols, id, callee, args, &mut errors)
+                },
+                _ => {
+                    errors.append(&mut type_check(symbols, callee));
+                    if let Some(t) = callee.type_data {
+                        let e = CompileError::error(callee, format!(
+                            "`{}` is `{}`, which isn't a function",
+                            callee.text(), symbols.type_name(t)
+                        )).with_code(codes::NOT_CALLABLE);
+                        errors.push(declared_here(symbols, callee, e));
+                    }
+                    None
+                }
+            }
+        },
+        AstNodeKind::StructLiteral { name, fields } => {
+            for f in fields.iter_mut() {
+                errors.append(&mut type_check(symbols, &mut f.value));
+            }
+            match name.data {
+                Reference::Resolved(id) => match symbols.fields(id).map(|f| f.to_vec()) {
+                    Some(declared) => {
+                        for (i, f) in fields.iter().enumerate() {
+                            if let Some(earlier) = fields[..i].iter().find(|e| e.name.data == f.name.data) {
+                                errors.push(
+                                    CompileError::error(&f.name, format!("`{}` is given a value twice", f.name.data))
+                                        .with_code(codes::BAD_STRUCT_LITERAL)
+                                        .with_label(&earlier.name, "it's first given one here")
+                                );
+                                continue
+                            }
+                            match declared.iter().find(|(n, _)| *n == f.name.data) {
+                                Some((_, Some(t))) => if let Some(v) = f.value.type_data.filter(|v| v != t) {
+                                    let e = CompileError::error(&f.value, format!(
+                                        "`{}` is `{}`, but this is `{}`",
+                                        f.name.data, symbols.type_name(*t), symbols.type_name(v)
+                                    )).with_code(codes::BAD_STRUCT_LITERAL);
+                                    errors.push(declared_here(symbols, &f.value, e));
+                                },
+                                // the field's own type is already known to be wrong
+                                Some((_, None)) => {},
+                                None => errors.push(
+                                    CompileError::error(&f.name, format!("`{}` doesn't have a field called `{}`", name.text(), f.name.data))
+                                        .with_code(codes::UNKNOWN_FIELD)
+                                )
+                            }
+                        }
+                        let missing: Vec<_> = declared.iter()
+                            .filter(|(n, _)| !fields.iter().any(|f| f.name.data == *n))
+                            .map(|(n, _)| format!("`{}`", n))
+                            .collect();
+                        if !missing.is_empty() {
+                            errors.push(
+                                CompileError::error(name, format!("`{}` is missing a value for {}", name.text(), missing.join(", ")))
+                                    .with_code(codes::BAD_STRUCT_LITERAL)
+                            );
+                        }
+                        Some(id)
+                    },
+                    None => {
+                        let e = CompileError::error(name, format!("`{}` isn't a struct", name.text()))
+                            .with_code(codes::BAD_STRUCT_LITERAL);
+                        errors.push(match symbols.tbl.get(&id) {
+                            Some(sym) if sym.line != 0 => e.with_label(sym, format!("`{}` is declared here", name.text())),
+                            _ => e
+                        });
+                        None
+                    }
+                },
+                // the scope has already complained about this one
+                _ => None
+            }
+        },
+        AstNodeKind::Field { target, name } => {
+            errors.append(&mut type_check(symbols, target));
+            match target.type_data {
+                Some(t) => match symbols.field(t, name.data) {
+                    Some((_, ty)) => ty,
+                    None => {
+                        let message = match symbols.fields(t) {
+                            Some(_) => format!("`{}` doesn't have a field called `{}`", symbols.type_name(t), name.data),
+                            None => format!("`{}` doesn't have any fields", symbols.type_name(t))
+                        };
+                        errors.push(
+                            CompileError::error(name, message)
+                                .with_code(codes::UNKNOWN_FIELD)
+                                .with_label(target, format!("this is `{}`", symbols.type_name(t)))
+                        );
+                        None
+                    }
+                },
+                None => None
+            }
+        },
+        // the parser has already said what's wrong here
+        AstNodeKind::Error => None,
+    };
 
+    if let Some(b) = uncalled {
+        errors.push(
+            CompileError::error(ast, format!("`{}` can only be called", b.name())).with_code(codes::BAD_BUILTIN_CALL)
+        );
+    }
     errors
 }
 
+/// Checks a call to the function with the symbol `id`, giving back the type
+/// of what it returns.
+fn check_call<'a>(
+    symbols: &mut SymbolTable<'a>,
+    id: usize,
+    callee: &mut AstNode<'a>,
+    args: &[AstNode<'a>],
+    errors: &mut Vec<CompileError<'a>>
+) -> Option<usize> {
+    let Some(Symbol::Function { ty, inferring }) = symbols.tbl.get(&id).map(|s| &s.data) else { unreachable!() };
+    if *inferring {
+        let e = CompileError::error(callee, format!(
+            "what `{}` gives back has to be written out to call it here",
+            callee.text()
+        )).with_code(codes::NEEDS_RETURN_TYPE);
+        errors.push(declared_here(symbols, callee, e));
+        return None
+    }
+    // without a signature, whatever was wrong with it has been reported
+    let ty = (*ty)?;
+    callee.type_data = Some(ty);
+    let (params, out) = symbols.signature(ty)?;
+
+    if params.len() != args.len() {
+        let plural = |n: usize| if n == 1 { "" } else { "s" };
+        let e = CompileError::error(callee, format!(
+            "`{}` takes {} argument{}, but {} {} given",
+            callee.text(), params.len(), plural(params.len()), args.len(),
+            if args.len() == 1 { "was" } else { "were" }
+        )).with_code(codes::BAD_ARGUMENTS);
+        errors.push(declared_here(symbols, callee, e));
+        return Some(out)
+    }
+    for (arg, param) in args.iter().zip(params) {
+        if let Some(t) = arg.type_data.filter(|t| t != param) {
+            let e = CompileError::error(arg, format!(
+                "expected `{}`, but this is `{}`",
+                symbols.type_name(*param), symbols.type_name(t)
+            )).with_code(codes::BAD_ARGUMENTS);
+            errors.push(declared_here(symbols, callee, e));
+        }
+    }
+    Some(out)
+}
+
+/// Points out where a variable was declared, if `node` refers to one.
+fn declared_here<'a>(symbols: &SymbolTable<'a>, node: &AstNode<'a>, e: CompileError<'a>) -> CompileError<'a> {
+    let AstNodeKind::Reference(Reference::Resolved(id)) = &*node.kind else { return e };
+    match symbols.tbl.get(id) {
+        // builtins don't have anywhere to point at
+        Some(sym) if sym.line != 0 => e.with_label(sym, format!("`{}` is declared here", node.text())),
+        _ => e
+    }
+}
+
+/// Type checks a block, giving back the type it evaluates to, if that could
+/// be worked out, and everything that's wrong inside it.
 pub fn type_check_block<'a>(
     symbols: &mut SymbolTable<'a>,
     block: &mut Vec<Statement<'a>>,
-) -> (usize, Vec<CompileError<'a>>) {
-    let errs = vec![];
-    let mut return_type = symbols.unit();
+) -> (Option<usize>, Vec<CompileError<'a>>) {
+    let mut errs = vec![];
+    let mut return_type = Some(symbols.unit());
+    // code after a `return` or the like never runs, but it still has to make sense
+    let mut diverged = false;
+    // functions don't run where they're written, so whatever the code
+    // around them does, they still need checking. They can be called from
+    // anywhere in the block, so their signatures come first. Then come the
+    // ones that give back whatever their body does, since they can't be
+    // called until that's been worked out. Before any of that come the
+    // structs, which the functions' signatures can use.
+    for stmt in block.iter_mut() {
+        errs.append(&mut declare_struct(symbols, stmt));
+    }
+    for stmt in block.iter() {
+        if let Statement::Struct { id: Some(id), name, .. } = stmt {
+            if contains(symbols, *id, *id, &mut vec![]) {
+                errs.push(
+                    CompileError::error(name, format!("`{}` contains itself, so it would never end", name.data))
+                        .with_code(codes::RECURSIVE_STRUCT)
+                );
+            }
+        }
+    }
+    for stmt in block.iter_mut() {
+        errs.append(&mut declare_function(symbols, stmt));
+    }
+    for inferred in [true, false] {
+        for stmt in block.iter_mut() {
+            if matches!(stmt, Statement::Function { id: Some(id), .. } if inferring(symbols, *id) == inferred) {
+                errs.append(&mut check_function(symbols, stmt));
+            }
+        }
+    }
     for stmt in block {
         match stmt {
-            Statement::Declare { with_type, value, id } => {
-                type_check(symbols, value);
+            Statement::Declare { with_type, value, id, .. } => {
+                errs.append(&mut type_check(symbols, value));
+                let ty = match with_type {
+                    MaybeTyped::TypeProvided(name) => match provided_type(symbols, name) {
+                        Some(t) => {
+                            if let Some(v) = value.type_data.filter(|v| *v != t) {
+                                errs.push(
+                                    CompileError::error(value, format!(
+                                        "expected `{}`, but this is `{}`",
+                                        symbols.type_name(t), symbols.type_name(v)
+                                    ))
+                                    .with_code(codes::MISMATCHED_DECLARATION)
+                                    .with_label(name, "expected because of this")
+                                );
+                            }
+                            *with_type = MaybeTyped::TypeResolved(t);
+                            Some(t)
+                        },
+                        None => {
+                            errs.push(
+                                CompileError::error(name, format!("there's no type called `{}`", name.text()))
+                                    .with_code(codes::UNKNOWN_TYPE)
+                            );
+                            value.type_data
+                        }
+                    },
+                    MaybeTyped::NotTyped => {
+                        if let Some(t) = value.type_data {
+                            *with_type = MaybeTyped::TypeResolved(t);
+                        }
+                        value.type_data
+                    },
+                    MaybeTyped::TypeResolved(t) => Some(*t)
+                };
                 let var = symbols.tbl.get_mut(&id.clone().unwrap_resolved()).unwrap();
                 match var.data {
-                    Symbol::Variable { ref mut ty } => { *ty = value.type_data },
+                    Symbol::Variable { ty: ref mut var_ty } => { *var_ty = ty },
                     _ => unreachable!()
                 }
-                // TODO: error handling
-                if let MaybeTyped::NotTyped = with_type {
-                    *with_type = MaybeTyped::TypeResolved(value.type_data.unwrap());
-                    continue;
-                }
-                assert_eq!(with_type.unwrap_type(), value.type_data.unwrap(), "type mismatch!")
             },
             Statement::Expression(e) => {
-                type_check(symbols, e);
+                errs.append(&mut type_check(symbols, e));
             },
             Statement::Return(e) | Statement::Out(e) => {
-                type_check(symbols, e);
-                return_type = e.type_data.unwrap();
-                break;
+                errs.append(&mut type_check(symbols, e));
+                if !diverged {
+                    return_type = e.type_data;
+                }
+                diverged = true;
             },
             Statement::Break { with: Some(e), .. } => {
-                type_check(symbols, e);
-                break;
+                errs.append(&mut type_check(symbols, e));
+                diverged = true;
             }
-            Statement::Continue { .. } | Statement::Break { .. } => { break },
-            Statement::Import { .. } | Statement::Error => { },
+            Statement::Continue { .. } | Statement::Break { .. } => { diverged = true },
+            Statement::Import { .. } | Statement::Function { .. } | Statement::Struct { .. } | Statement::Error => { },
         };
     }
     (return_type, errs)
 }
+
+/// Makes sure the condition of an `if` or `while` is a `bool`.
+fn check_condition<'a>(symbols: &mut SymbolTable<'a>, condition: &mut AstNode<'a>) -> Vec<CompileError<'a>> {
+    let mut errors = type_check(symbols, condition);
+    let bool_t = symbols.get_primitive(PrimitiveType::Bool);
+    if let Some(t) = condition.type_data.filter(|t| *t != bool_t) {
+        let e = CompileError::error(condition, format!(
+            "conditions have to be a `bool`, but this is `{}`",
+            symbols.type_name(t)
+        )).with_code(codes::NON_BOOL_CONDITION);
+        errors.push(declared_here(symbols, condition, e));
+    }
+    errors
+}
+
+/// The type written as `name`, if there is one.
+fn named_type<'a>(symbols: &SymbolTable<'a>, name: &Sp<'a, Reference<'a>>, errs: &mut Vec<CompileError<'a>>) -> Option<usize> {
+    let t = provided_type(symbols, name);
+    if t.is_none() {
+        errs.push(
+            CompileError::error(name, format!("there's no type called `{}`", name.text()))
+                .with_code(codes::UNKNOWN_TYPE)
+        );
+    }
+    t
+}
+
+/// The type written as `name`: a struct the scope found, or a built in one.
+fn provided_type(symbols: &SymbolTable, name: &Sp<Reference>) -> Option<usize> {
+    match name.data {
+        Reference::Resolved(id) => Some(id),
+        _ => symbols.type_named(name.text())
+    }
+}
+
+/// Works out the types of a struct's fields.
+fn declare_struct<'a>(symbols: &mut SymbolTable<'a>, stmt: &mut Statement<'a>) -> Vec<CompileError<'a>> {
+    let mut errs = vec![];
+    let Statement::Struct { id: Some(id), fields: Some(fields), .. } = stmt else { return errs };
+    let mut types = vec![];
+    for f in fields.iter_mut() {
+        let t = match &f.with_type {
+            MaybeTyped::TypeProvided(name) => named_type(symbols, name, &mut errs),
+            MaybeTyped::TypeResolved(t) => Some(*t),
+            MaybeTyped::NotTyped => None
+        };
+        if let Some(t) = t {
+            f.with_type = MaybeTyped::TypeResolved(t);
+        }
+        types.push((f.name.data, t));
+    }
+    symbols.set_fields(*id, types);
+    errs
+}
+
+/// Whether the struct `t` has a `target` somewhere inside it. `seen` are the
+/// structs that have already been looked through.
+fn contains(symbols: &SymbolTable, t: usize, target: usize, seen: &mut Vec<usize>) -> bool {
+    let fields: Vec<_> = symbols.fields(t).unwrap_or_default().iter().filter_map(|(_, f)| *f).collect();
+    fields.into_iter().any(|f| {
+        if f == target {
+            return true
+        }
+        if seen.contains(&f) {
+            return false
+        }
+        seen.push(f);
+        contains(symbols, f, target, seen)
+    })
+}
+
+/// Whether `node` is somewhere a value can be kept: a variable, or a field of
+/// one.
+fn is_place(symbols: &SymbolTable, node: &AstNode) -> bool {
+    match &*node.kind {
+        AstNodeKind::Reference(Reference::Resolved(id)) => {
+            matches!(symbols.tbl.get(id).map(|s| &s.data), Some(Symbol::Variable { .. }))
+        },
+        AstNodeKind::Field { target, .. } => is_place(symbols, target),
+        _ => false
+    }
+}
+
+fn inferring(symbols: &SymbolTable, id: usize) -> bool {
+    matches!(symbols.tbl.get(&id).map(|s| &s.data), Some(Symbol::Function { inferring: true, .. }))
+}
+
+/// Works out the types of a function's parameters and what it gives back,
+/// unless that has to be worked out from its body.
+fn declare_function<'a>(symbols: &mut SymbolTable<'a>, stmt: &mut Statement<'a>) -> Vec<CompileError<'a>> {
+    let mut errs = vec![];
+    let Statement::Function { id: Some(id), params, returns, expression_body, .. } = stmt else { return errs };
+
+    let mut param_types = vec![];
+    for p in params.iter_mut() {
+        let t = match &p.with_type {
+            MaybeTyped::TypeProvided(name) => named_type(symbols, name, &mut errs),
+            MaybeTyped::TypeResolved(t) => Some(*t),
+            MaybeTyped::NotTyped => None
+        };
+        if let Some(t) = t {
+            p.with_type = MaybeTyped::TypeResolved(t);
+        }
+        if let Some(Symbol::Variable { ty }) = symbols.tbl.get_mut(&p.id.clone().unwrap_resolved()).map(|s| &mut s.data) {
+            *ty = t;
+        }
+        param_types.push(t);
+    }
+
+    let out = match returns {
+        MaybeTyped::TypeProvided(name) => named_type(symbols, name, &mut errs),
+        MaybeTyped::TypeResolved(t) => Some(*t),
+        MaybeTyped::NotTyped if *expression_body => None,
+        MaybeTyped::NotTyped => Some(symbols.unit())
+    };
+    let inferring = out.is_none() && matches!(returns, MaybeTyped::NotTyped);
+    let ty = match (param_types.into_iter().collect::<Option<Vec<_>>>(), out) {
+        (Some(params), Some(out)) => Some(symbols.function_type(params, out)),
+        _ => None
+    };
+    if let Some(Symbol::Function { ty: sym_ty, inferring: sym_inferring }) = symbols.tbl.get_mut(id).map(|s| &mut s.data) {
+        *sym_ty = ty;
+        *sym_inferring = inferring;
+    }
+    errs
+}
+
+/// Checks the body of a function against what it says it gives back, or
+/// works that out from the body if it doesn't say.
+fn check_function<'a>(symbols: &mut SymbolTable<'a>, stmt: &mut Statement<'a>) -> Vec<CompileError<'a>> {
+    let Statement::Function { id: Some(id), name, params, returns, body, .. } = stmt else { return vec![] };
+    let id = *id;
+    let mut errs = type_check(symbols, body);
+
+    if inferring(symbols, id) {
+        let params: Option<Vec<_>> = params.iter().map(|p| match p.with_type {
+            MaybeTyped::TypeResolved(t) => Some(t),
+            _ => None
+        }).collect();
+        let ty = match (params, body.type_data) {
+            (Some(params), Some(out)) => Some(symbols.function_type(params, out)),
+            _ => None
+        };
+        if let Some(out) = body.type_data {
+            *returns = MaybeTyped::TypeResolved(out);
+        }
+        if let Some(Symbol::Function { ty: sym_ty, inferring }) = symbols.tbl.get_mut(&id).map(|s| &mut s.data) {
+            *sym_ty = ty;
+            *inferring = false;
+        }
+    }
+
+    let (out, because) = match returns {
+        MaybeTyped::TypeProvided(t) => match provided_type(symbols, t) {
+            Some(out) => (out, (t.ditch(), "expected because of this".to_string())),
+            // already reported
+            None => return errs
+        },
+        MaybeTyped::TypeResolved(out) => (*out, (body.ditch(), format!("`{}` gives back what this does", name.data))),
+        MaybeTyped::NotTyped => (symbols.unit(), (name.ditch(), format!("`{}` has no `-> type`, so it gives back `unit`", name.data)))
+    };
+    if let MaybeTyped::TypeProvided(_) = returns {
+        *returns = MaybeTyped::TypeResolved(out);
+    }
+    check_returns(symbols, body, out, &because, &mut errs);
+
+    // a body that finishes without a `return` gives back its own value
+    if let Some(t) = body.type_data.filter(|t| *t != out && !always_returns(body)) {
+        errs.push(
+            CompileError::error(name, format!(
+                "`{}` should give back `{}`, but its body gives `{}`",
+                name.data, symbols.type_name(out), symbols.type_name(t)
+            ))
+            .with_code(codes::MISMATCHED_RETURN)
+            .with_label(&because.0, because.1.clone())
+        );
+    }
+    errs
+}
+
+/// What a loop gives back, from the `break`s that leave it, which all have to
+/// agree. A loop that's never broken out of doesn't give back anything, and
+/// neither do the ones that can finish on their own, named by `finishes`.
+fn loop_type<'a>(
+    symbols: &SymbolTable<'a>,
+    found: &[Option<&AstNode<'a>>],
+    finishes: Option<&str>,
+    errs: &mut Vec<CompileError<'a>>
+) -> Option<usize> {
+    if let Some(keyword) = finishes {
+        for e in found.iter().flatten() {
+            if let Some(t) = e.type_data.filter(|t| *t != symbols.unit()) {
+                errs.push(
+                    CompileError::error(e, format!("`{}` loops always give back `unit`, but this is `{}`", keyword, symbols.type_name(t)))
+                        .with_code(codes::MISMATCHED_BREAK)
+                );
+            }
+        }
+        return Some(symbols.unit())
+    }
+    let Some(first) = found.first() else { return Some(symbols.unit()) };
+    let out = match first {
+        Some(e) => e.type_data?,
+        None => symbols.unit()
+    };
+    for e in found[1..].iter().flatten() {
+        if let Some(t) = e.type_data.filter(|t| *t != out) {
+            let mut e = CompileError::error(e, format!(
+                "expected `{}`, but this is `{}`",
+                symbols.type_name(out), symbols.type_name(t)
+            )).with_code(codes::MISMATCHED_BREAK);
+            if let Some(first) = first {
+                e = e.with_label(first, format!("the loop gives back `{}` because of this", symbols.type_name(out)));
+            }
+            errs.push(e);
+        }
+    }
+    Some(out)
+}
+
+/// Finds the `break`s in `node` that leave the loop labeled `label`, or the
+/// innermost loop if `nested` is false, and what they break out with.
+fn breaks<'n, 'a>(node: &'n AstNode<'a>, label: Option<usize>, nested: bool, found: &mut Vec<Option<&'n AstNode<'a>>>) {
+    macro_rules! find {
+        ($n:expr) => { breaks($n, label, nested, found) };
+    }
+    match &*node.kind {
+        AstNodeKind::BinOp { a, b, .. } => {
+            find!(a);
+            find!(b);
+        },
+        AstNodeKind::UnOp { target, .. } => find!(target),
+        AstNodeKind::If { condition, body, else_body } => {
+            find!(condition);
+            find!(body);
+            if let Some(eb) = else_body {
+                find!(eb);
+            }
+        },
+        // a `break` without a label in here leaves the inner loop
+        AstNodeKind::Loop { body, .. } => breaks(body, label, true, found),
+        AstNodeKind::While { condition, body, .. } => {
+            find!(condition);
+            breaks(body, label, true, found);
+        },
+        AstNodeKind::For { from, to, body, .. } => {
+            find!(from);
+            find!(to);
+            breaks(body, label, true, found);
+        },
+        AstNodeKind::Call { callee, args } => {
+            find!(callee);
+            for a in args {
+                find!(a);
+            }
+        },
+        AstNodeKind::Interpolation { parts } => for p in parts {
+            find!(p);
+        },
+        AstNodeKind::Match { value, arms } => {
+            find!(value);
+            for arm in arms {
+                find!(&arm.body);
+            }
+        },
+        AstNodeKind::StructLiteral { fields, .. } => for f in fields {
+            find!(&f.value);
+        },
+        AstNodeKind::Field { target, .. } => find!(target),
+        AstNodeKind::Block { stmts } => for stmt in stmts {
+            match stmt {
+                Statement::Break { label: to, with, .. } => {
+                    let ours = match to {
+                        Some(to) => matches!(to.data, Reference::Resolved(id) if Some(id) == label),
+                        None => !nested
+                    };
+                    if ours {
+                        found.push(with.as_ref());
+                    }
+                    if let Some(e) = with {
+                        find!(e);
+                    }
+                },
+                Statement::Declare { value: e, .. }
+                    | Statement::Expression(e)
+                    | Statement::Return(e)
+                    | Statement::Out(e) => find!(e),
+                _ => {}
+            }
+        },
+        AstNodeKind::Value(..) | AstNodeKind::Reference(..) | AstNodeKind::Error => {}
+    }
+}
+
+/// Whether every way through `node` ends in a `return`, or never ends, so it
+/// can't finish and give back its own value.
+fn always_returns(node: &AstNode) -> bool {
+    match &*node.kind {
+        AstNodeKind::BinOp { a, b, op } => match **op {
+            // the right side might not run
+            BinOp::And | BinOp::Or => always_returns(a),
+            _ => always_returns(a) || always_returns(b)
+        },
+        AstNodeKind::UnOp { target, .. } | AstNodeKind::Field { target, .. } => always_returns(target),
+        AstNodeKind::If { condition, body, else_body } => always_returns(condition)
+            || else_body.as_ref().is_some_and(|eb| always_returns(body) && always_returns(eb)),
+        // a `loop` only finishes when it's broken out of
+        AstNodeKind::Loop { label, body } => {
+            let mut found = vec![];
+            breaks(body, label.as_ref().map(|l| l.data.clone().unwrap_resolved()), false, &mut found);
+            found.is_empty()
+        },
+        AstNodeKind::While { condition, .. } => always_returns(condition),
+        AstNodeKind::For { from, to, .. } => always_returns(from) || always_returns(to),
+        AstNodeKind::Call { callee, args } => always_returns(callee) || args.iter().any(always_returns),
+        AstNodeKind::Interpolation { parts } => parts.iter().any(always_returns),
+        AstNodeKind::Match { value, arms } => always_returns(value)
+            || (!arms.is_empty() && arms.iter().all(|arm| always_returns(&arm.body))),
+        AstNodeKind::StructLiteral { fields, .. } => fields.iter().any(|f| always_returns(&f.value)),
+        AstNodeKind::Block { stmts } => {
+            for stmt in stmts {
+                match stmt {
+                    Statement::Return(..) => return true,
+                    // these leave the block some other way
+                    Statement::Out(..) | Statement::Break { .. } | Statement::Continue { .. } => return false,
+                    Statement::Declare { value: e, .. } | Statement::Expression(e) if always_returns(e) => return true,
+                    _ => {}
+                }
+            }
+            false
+        },
+        AstNodeKind::Value(..) | AstNodeKind::Reference(..) | AstNodeKind::Error => false
+    }
+}
+
+/// Checks that every `return` in the body of a function gives back `out`.
+/// Functions inside it are checked on their own.
+fn check_returns<'a>(
+    symbols: &SymbolTable<'a>,
+    node: &AstNode<'a>,
+    out: usize,
+    because: &(Sp<'a, ()>, String),
+    errs: &mut Vec<CompileError<'a>>
+) {
+    macro_rules! check {
+        ($n:expr) => { check_returns(symbols, $n, out, because, errs) };
+    }
+    match &*node.kind {
+        AstNodeKind::BinOp { a, b, .. } => {
+            check!(a);
+            check!(b);
+        },
+        AstNodeKind::UnOp { target, .. } => check!(target),
+        AstNodeKind::If { condition, body, else_body } => {
+            check!(condition);
+            check!(body);
+            if let Some(eb) = else_body {
+                check!(eb);
+            }
+        },
+        AstNodeKind::Loop { body, .. } => check!(body),
+        AstNodeKind::While { condition, body, .. } => {
+            check!(condition);
+            check!(body);
+        },
+        AstNodeKind::For { from, to, body, .. } => {
+            check!(from);
+            check!(to);
+            check!(body);
+        },
+        AstNodeKind::Call { callee, args } => {
+            check!(callee);
+            for a in args {
+                check!(a);
+            }
+        },
+        AstNodeKind::Interpolation { parts } => for p in parts {
+            check!(p);
+        },
+        AstNodeKind::Match { value, arms } => {
+            check!(value);
+            for arm in arms {
+                check!(&arm.body);
+            }
+        },
+        AstNodeKind::StructLiteral { fields, .. } => for f in fields {
+            check!(&f.value);
+        },
+        AstNodeKind::Field { target, .. } => check!(target),
+        AstNodeKind::Block { stmts } => for stmt in stmts {
+            match stmt {
+                Statement::Return(e) => {
+                    check!(e);
+                    if let Some(t) = e.type_data.filter(|t| *t != out) {
+                        errs.push(
+                            CompileError::error(e, format!(
+                                "expected `{}`, but this is `{}`",
+                                symbols.type_name(out), symbols.type_name(t)
+                            ))
+                            .with_code(codes::MISMATCHED_RETURN)
+                            .with_label(&because.0, because.1.clone())
+                        );
+                    }
+                },
+                Statement::Declare { value: e, .. }
+                    | Statement::Expression(e)
+                    | Statement::Out(e)
+                    | Statement::Break { with: Some(e), .. } => check!(e),
+                _ => {}
+            }
+        },
+        AstNodeKind::Value(..) | AstNodeKind::Reference(..) | AstNodeKind::Error => {}
+    }
+}
@@ -0,0 +1,247 @@
+//! The Formatter
+//!
+//! Works on tokens rather than the AST so that comments survive, and keeps the
+//! line breaks it finds between statements. The parser only gets a say in
+//! whether the code is worth formatting at all.
+
+use crate::{
+    fe::{lexer::{Lexer, LexError}, parser::Parser, token::{Token, CommentType}, Sp},
+    Diagnostics
+};
+
+const INDENT: &str = "    ";
+
+/// Names that can be written as `\name` to get the character instead
+const ESCAPES: &[(&str, char)] = &[
+    ("alpha", 'α'), ("beta", 'β'), ("gamma", 'γ'), ("delta", 'δ'), ("epsilon", 'ε'),
+    ("zeta", 'ζ'), ("eta", 'η'), ("theta", 'θ'), ("iota", 'ι'), ("kappa", 'κ'),
+    ("lambda", 'λ'), ("mu", 'μ'), ("nu", 'ν'), ("xi", 'ξ'), ("omicron", 'ο'),
+    ("pi", 'π'), ("rho", 'ρ'), ("sigma", 'σ'), ("tau", 'τ'), ("upsilon", 'υ'),
+    ("phi", 'φ'), ("chi", 'χ'), ("psi", 'ψ'), ("omega", 'ω'),
+    ("Gamma", 'Γ'), ("Delta", 'Δ'), ("Theta", 'Θ'), ("Lambda", 'Λ'), ("Xi", 'Ξ'),
+    ("Pi", 'Π'), ("Sigma", 'Σ'), ("Phi", 'Φ'), ("Psi", 'Ψ'), ("Omega", 'Ω')
+];
+
+/// Formats a whole file. Code that doesn't parse is left alone and its
+/// errors are handed back instead.
+pub fn format(source: &str) -> Result<String, Diagnostics<'_>> {
+    let tokens: Vec<_> = Lexer::new(source).collect();
+
+    // `\pi` isn't valid code until we've done our job, so don't hold it against anyone
+    let escapes: Vec<_> = (0..tokens.len())
+        .filter(|i| escape(&tokens, *i).is_some())
+        .map(|i| tokens[i].span.start)
+        .collect();
+    let (_, errors) = Parser::new(source).parse();
+    let errors: Vec<_> = errors.into_iter()
+        .filter(|e| !escapes.contains(&e.at.span.start))
+        .collect();
+    if !errors.is_empty() {
+        return Err(Diagnostics { errors })
+    }
+
+    Ok(Formatter {
+        source,
+        tokens: &tokens,
+        out: String::new(),
+        open: vec![]
+    }.run())
+}
+
+/// The character a `\name` escape starting at token `i` stands for.
+fn escape(tokens: &[Sp<Token>], i: usize) -> Option<char> {
+    let (Token::Error(LexError::UnknownCharacter('\\')), Some(next)) = (&tokens[i].data, tokens.get(i + 1)) else {
+        return None
+    };
+    match next.data {
+        Token::Identifier(name) if next.span.start == tokens[i].span.end => {
+            ESCAPES.iter().find(|(n, _)| *n == name).map(|(_, ch)| *ch)
+        },
+        _ => None
+    }
+}
+
+struct Formatter<'t, 'a> {
+    source: &'a str,
+    tokens: &'t [Sp<'a, Token<'a>>],
+    out: String,
+    /// The brackets we're inside of, and whether each one spans several lines
+    open: Vec<bool>
+}
+
+impl<'a> Formatter<'_, 'a> {
+    fn run(mut self) -> String {
+        // the previous token, and the previous one that wasn't a comment
+        let mut prev: Option<usize> = None;
+        let mut code: Option<usize> = None;
+        let mut unary = false;
+
+        let mut i = 0;
+        while i < self.tokens.len() {
+            let t = &self.tokens[i];
+            let (text, last) = match escape(self.tokens, i) {
+                Some(ch) => (ch.to_string(), i + 1),
+                None => (t.text().trim_end().to_string(), i)
+            };
+
+            if let Some(p) = prev {
+                self.separate(p, code, i, unary);
+            }
+            self.out.push_str(&text);
+            if is_closer(t) {
+                self.open.pop();
+            } else if is_opener(t) {
+                let multiline = self.multiline(i);
+                self.open.push(multiline);
+            }
+
+            if !matches!(t.data, Token::Comment(..)) {
+                unary = matches!(t.data, Token::Sub | Token::Not)
+                    && !code.is_some_and(|c| ends_value(&self.tokens[c]) && !self.line_ends_statement(c, i));
+                code = Some(last);
+            }
+            prev = Some(last);
+            i = last + 1;
+        }
+
+        let mut out = self.out.trim_end().to_string();
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        out
+    }
+
+    /// Writes whatever goes between token `p` and token `t`. `code` is the
+    /// last token before `t` that wasn't a comment.
+    fn separate(&mut self, p: usize, code: Option<usize>, t: usize, unary: bool) {
+        let (prev, next) = (&self.tokens[p], &self.tokens[t]);
+        let gap = &self.source[prev.span.end..next.span.start];
+        // line comments own the newline that ends them
+        let newlines = gap.matches('\n').count() + is_line_comment(prev) as usize;
+        let trailing_comment = matches!(next.data, Token::Comment(..)) && newlines == 0;
+
+        // the innermost bracket, which `next` might be about to close
+        let multiline = self.open.last().copied();
+        let forced = is_line_comment(prev)
+            || (!trailing_comment && match (&prev.data, &next.data) {
+                (Token::LBrace, Token::RBrace) => false,
+                (Token::Semicolon, _) => multiline.unwrap_or(true),
+                (Token::LBrace, _) | (_, Token::RBrace) => multiline.unwrap_or(false),
+                _ => false
+            });
+        // braces go at the end of the line, and so does anything chained onto a block
+        let joined = !is_line_comment(prev) && match &next.data {
+            // unless the line break ends a statement, and it's a block of its own
+            Token::LBrace => !self.line_ends_statement(p, t),
+            Token::RBrace => matches!(prev.data, Token::LBrace),
+            Token::Else => matches!(prev.data, Token::RBrace),
+            _ => false
+        };
+
+        if forced || (newlines > 0 && !joined) {
+            let blank = newlines > 1
+                && !matches!(prev.data, Token::LBrace)
+                && !matches!(next.data, Token::RBrace);
+            self.newline(blank, self.indent(code, t));
+        } else if space_between(prev, next, unary) {
+            self.out.push(' ');
+        }
+    }
+
+    fn newline(&mut self, blank: bool, indent: usize) {
+        let trimmed = self.out.trim_end_matches(' ').len();
+        self.out.truncate(trimmed);
+        self.out.push('\n');
+        if blank {
+            self.out.push('\n');
+        }
+        for _ in 0..indent {
+            self.out.push_str(INDENT);
+        }
+    }
+
+    fn indent(&self, code: Option<usize>, t: usize) -> usize {
+        let next = &self.tokens[t];
+        let mut nesting = self.open.iter().filter(|m| **m).count();
+        if is_closer(next) && self.open.last() == Some(&true) {
+            // closing brackets line up with whatever opened them
+            nesting -= 1;
+        }
+        // a line that carries on the previous one's expression goes in a little
+        // further, and so do comments in the middle of it
+        let after = (t..self.tokens.len()).find(|i| !matches!(self.tokens[*i].data, Token::Comment(..)));
+        let continues = code.zip(after).is_some_and(|(c, n)| !matches!(
+            self.tokens[c].data,
+            Token::Semicolon | Token::LBrace | Token::LParen | Token::LBracket | Token::Comma | Token::RBrace
+        ) && !self.line_ends_statement(c, n) && !is_closer(&self.tokens[n]) && !starts_statement(&self.tokens[n]));
+        nesting + continues as usize
+    }
+
+    /// Whether there's a line break between tokens `a` and `b` that the parser
+    /// takes as a `;`.
+    fn line_ends_statement(&self, a: usize, b: usize) -> bool {
+        let (a, b) = (&self.tokens[a], &self.tokens[b]);
+        a.semicolon_inbetween(b) && self.source[a.span.end..b.span.start].contains('\n')
+    }
+
+    /// Whether the bracket opened by token `i` is closed on a later line.
+    fn multiline(&self, i: usize) -> bool {
+        let mut depth = 0;
+        for t in &self.tokens[i..] {
+            if is_opener(t) {
+                depth += 1;
+            } else if is_closer(t) {
+                depth -= 1;
+                if depth == 0 {
+                    return self.source[self.tokens[i].span.start..t.span.end].contains('\n')
+                }
+            }
+        }
+        false
+    }
+}
+
+fn space_between(prev: &Sp<Token>, next: &Sp<Token>, unary: bool) -> bool {
+    use Token::*;
+    if matches!(prev.data, Comment(..)) || matches!(next.data, Comment(..)) {
+        return true
+    }
+    match (&prev.data, &next.data) {
+        (_, RParen | RBracket | Comma | Semicolon | Colon | Dot | DotDot | DotDotEq | Try) => false,
+        (LParen | LBracket | Dot | DotDot | DotDotEq | At, _) => false,
+        _ if unary => false,
+        // calls and indexing
+        (_, LParen | LBracket) => !ends_value(prev),
+        // `{}`
+        (LBrace, RBrace) => false,
+        _ => true
+    }
+}
+
+/// Whether a `-` or `!` after this would be a binary operator.
+fn ends_value(t: &Sp<Token>) -> bool {
+    use Token::*;
+    t.is_value() || matches!(t.data, Identifier(..) | RParen | RBracket | RBrace | Try)
+}
+
+/// Tokens that can only be the first thing in a statement
+fn starts_statement(t: &Sp<Token>) -> bool {
+    use Token::*;
+    matches!(t.data, Let | Fn | At | Import | Return | Break | Continue | Struct | Enum | Pub)
+}
+
+fn is_opener(t: &Sp<Token>) -> bool {
+    matches!(t.data, Token::LBrace | Token::LParen | Token::LBracket)
+}
+
+fn is_closer(t: &Sp<Token>) -> bool {
+    matches!(t.data, Token::RBrace | Token::RParen | Token::RBracket)
+}
+
+fn is_line_comment(t: &Sp<Token>) -> bool {
+    match t.data {
+        Token::Comment(CommentType::Regular, _) => !t.text().starts_with("/*"),
+        Token::Comment(..) => true,
+        _ => false
+    }
+}
@@ -0,0 +1,167 @@
+//! The Fig Compiler
+//!
+//! [`compile`] runs the whole pipeline over a source string. The stages are
+//! also public, so tools can stop wherever they like and poke at the results.
+
+use std::fmt::Display;
+
+use crate::{
+    fe::{
+        ast::Statement,
+        symbols::SymbolTable,
+        parser::Parser,
+        types,
+        item::Item,
+        scope::Scope,
+        lint,
+        codes,
+        CompileError,
+        Severity,
+        Sp
+    },
+    be::{
+        check,
+        irgen::IrGen,
+        ir::IrBlock,
+        consts::ConstTable,
+        platform::arm64::{self, Arm64Generator},
+        CompUnit
+    }
+};
+
+pub mod fe;
+pub mod be;
+pub mod doc;
+pub mod eval;
+pub mod fmt;
+pub mod json;
+
+#[derive(Debug, Default, Clone)]
+pub struct Options {
+    /// Stop after type checking instead of generating code
+    pub check_only: bool
+}
+
+/// Everything that went wrong while compiling
+#[derive(Debug)]
+pub struct Diagnostics<'a> {
+    pub errors: Vec<CompileError<'a>>
+}
+
+impl Display for Diagnostics<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for e in &self.errors {
+            writeln!(f, "{}", e)?;
+        }
+        Ok(())
+    }
+}
+
+/// A successfully compiled program, along with everything we made on the way
+pub struct Artifact<'a> {
+    /// The checked program. Its statements are the code of the main function.
+    pub unit: CompUnit<'a>,
+    pub symbols: SymbolTable<'a>,
+    /// Lints that went off, none of which were denied
+    pub warnings: Vec<CompileError<'a>>,
+    /// The program in three address code, before register allocation
+    pub ir: Option<IrBlock>,
+    /// The program in three address code, after register allocation
+    pub colored_ir: Option<IrBlock>,
+    /// arm64 assembly for the program
+    pub asm: Option<String>
+}
+
+impl<'a> Artifact<'a> {
+    pub fn statements(&self) -> &[Statement<'a>] {
+        match self.unit.items.first() {
+            Some(Item::Function { code, .. }) => code,
+            _ => &[]
+        }
+    }
+}
+
+/// Compiles `source` as far as `options` asks for.
+pub fn compile<'a>(source: &'a str, options: &Options) -> Result<Artifact<'a>, Diagnostics<'a>> {
+    let mut stmts = parse(source)?;
+    let mut symbols = SymbolTable::new();
+    let analyzed = analyze(&mut symbols, &mut Scope::new(), &mut stmts);
+    let mut warnings = lint::lint(&stmts);
+    if let Err(mut d) = analyzed {
+        d.errors.append(&mut warnings);
+        d.errors.sort_by_key(|e| e.at.span.start);
+        return Err(d)
+    }
+    if warnings.iter().any(|w| w.severity == Severity::Error) {
+        return Err(Diagnostics { errors: warnings })
+    }
+
+    let mut artifact = Artifact {
+        unit: CompUnit {
+            prog: source,
+            consts: ConstTable::new(),
+            items: vec![Item::Function {
+                code: stmts,
+                return_type: symbols.unit()
+            }]
+        },
+        symbols,
+        warnings,
+        ir: None,
+        colored_ir: None,
+        asm: None
+    };
+    if options.check_only {
+        return Ok(artifact)
+    }
+    let errors = check::check(&artifact.symbols, artifact.statements());
+    if !errors.is_empty() {
+        return Err(Diagnostics { errors })
+    }
+
+    let mut block = IrBlock::new();
+    IrGen::new().gen(&artifact.symbols, &mut artifact.unit, &mut block);
+    artifact.ir = Some(block.clone());
+
+    if let Err(out) = arm64::allocate_registers(&mut block) {
+        let at = match out.function {
+            Some(id) => artifact.symbols.tbl[&id].ditch(),
+            None => Sp::new(source, 0..0, ())
+        };
+        let e = CompileError::error(&at, "this can't be compiled to native code yet, since it needs more registers than there are")
+            .with_code(codes::NOT_NATIVE);
+        return Err(Diagnostics { errors: vec![e] })
+    }
+    let mut arm_gen = Arm64Generator::new(Vec::new(), &mut artifact.unit);
+    arm_gen.gen(&block);
+    let asm = arm_gen.finish().expect("writing to memory can't fail");
+    artifact.asm = Some(String::from_utf8(asm).expect("generated assembly should be UTF-8"));
+    artifact.colored_ir = Some(block);
+
+    Ok(artifact)
+}
+
+/// Parses `source` into the statements of its main function.
+pub fn parse(source: &str) -> Result<Vec<Statement<'_>>, Diagnostics<'_>> {
+    let (stmts, errors) = Parser::new(source).parse();
+    if !errors.is_empty() {
+        return Err(Diagnostics { errors })
+    }
+    Ok(stmts)
+}
+
+/// Resolves names in and type checks freshly parsed statements. `symbols`
+/// and `scope` may be shared across calls to check code piece by piece.
+pub fn analyze<'a>(
+    symbols: &mut SymbolTable<'a>,
+    scope: &mut Scope<'a>,
+    stmts: &mut Vec<Statement<'a>>
+) -> Result<(), Diagnostics<'a>> {
+    let mut errors = scope.resolve_block(symbols, stmts);
+    errors.append(&mut types::type_check_block(symbols, stmts).1);
+    errors.sort_by_key(|e| e.at.span.start);
+    if !errors.is_empty() {
+        return Err(Diagnostics { errors })
+    }
+    Ok(())
+}
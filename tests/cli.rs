@@ -0,0 +1,98 @@
+//! The command line: its arguments, `--emit`, and finding a C compiler.
+
+mod common;
+
+use std::{fs, path::PathBuf};
+
+use common::{fig, fig_args, fig_with_env};
+
+const PROGRAM: &str = "fn main() -> i64 { return 2 + 3 }\nlet a = main()\n";
+
+fn stderr(out: &std::process::Output) -> String {
+    String::from_utf8_lossy(&out.stderr).into_owned()
+}
+
+/// A fresh, empty directory for a test to point `TMPDIR` at.
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("fig-cli-{}-{}", std::process::id(), name));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn bad_arguments_print_the_usage() {
+    for (args, message) in [
+        (&["frobnicate"][..], "unknown command `frobnicate`"),
+        (&["build"], "expected a source file"),
+        (&["build", "a.fig", "--frob"], "unknown option `--frob`"),
+        (&["build", "a.fig", "-o"], "expected a value after `-o`"),
+        (&["build", "a.fig", "b.fig"], "unexpected argument `b.fig`")
+    ] {
+        let out = fig_args(args);
+        assert_eq!(out.status.code(), Some(2), "{:?}", args);
+        let err = stderr(&out);
+        assert!(err.contains(message) && err.contains("usage: fig"), "{:?}: {}", args, err);
+    }
+    let out = fig_args(&["--help"]);
+    assert!(out.status.success());
+    assert!(String::from_utf8_lossy(&out.stdout).contains("usage: fig"));
+}
+
+#[test]
+fn stages_are_emitted_to_stdout() {
+    // linking fails, but everything before it has already been written
+    let out = fig("build", PROGRAM, &["--emit=asm=-", "--cc", "/nonexistent/cc"]);
+    let asm = String::from_utf8_lossy(&out.stdout);
+    assert!(asm.contains("fig_entrypoint:") && asm.contains("add x0"), "{}", asm);
+
+    let out = fig("build", PROGRAM, &["--emit", "ir=-", "--cc", "/nonexistent/cc"]);
+    let ir = String::from_utf8_lossy(&out.stdout);
+    assert!(ir.contains("= Function(") && !ir.contains("fig_entrypoint"), "{}", ir);
+
+    let out = fig("check", PROGRAM, &["--emit=ast=-"]);
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert!(String::from_utf8_lossy(&out.stdout).contains("main"));
+}
+
+#[test]
+fn bad_stages_are_rejected() {
+    for (args, message) in [
+        (&["build", "a.fig", "--emit=bytecode"][..], "unknown stage `bytecode` for `--emit`"),
+        (&["check", "a.fig", "--emit", "asm"], "`fig check` stops before `asm` can be emitted")
+    ] {
+        let out = fig_args(args);
+        assert_eq!(out.status.code(), Some(2), "{:?}", args);
+        assert!(stderr(&out).contains(message), "{:?}: {}", args, stderr(&out));
+    }
+}
+
+#[test]
+fn cc_beats_fig_cc_beats_cc() {
+    let vars = [("FIG_CC", "/nonexistent/fig-cc"), ("CC", "/nonexistent/cc")];
+    let out = fig_with_env("build", PROGRAM, &["--cc", "/nonexistent/flag-cc"], &vars);
+    assert!(!out.status.success());
+    assert!(stderr(&out).contains("couldn't invoke `/nonexistent/flag-cc`"), "{}", stderr(&out));
+
+    let out = fig_with_env("build", PROGRAM, &[], &vars);
+    assert!(stderr(&out).contains("couldn't invoke `/nonexistent/fig-cc`"), "{}", stderr(&out));
+
+    let out = fig_with_env("build", PROGRAM, &[], &[("FIG_CC", ""), ("CC", "/nonexistent/cc")]);
+    assert!(stderr(&out).contains("couldn't invoke `/nonexistent/cc`"), "{}", stderr(&out));
+}
+
+#[test]
+fn intermediate_files_are_cleaned_up_unless_kept() {
+    let tmp = temp_dir("scratch");
+    let vars = [("TMPDIR", tmp.to_str().unwrap())];
+    let out = fig_with_env("build", PROGRAM, &["--cc", "/nonexistent/cc"], &vars);
+    assert!(!out.status.success());
+    assert_eq!(fs::read_dir(&tmp).unwrap().count(), 0, "{}", stderr(&out));
+
+    let out = fig_with_env("build", PROGRAM, &["--cc", "/nonexistent/cc", "--save-temps"], &vars);
+    assert!(stderr(&out).contains("note: intermediate files were kept in"), "{}", stderr(&out));
+    let kept: Vec<_> = fs::read_dir(&tmp).unwrap().map(|e| e.unwrap().path()).collect();
+    assert_eq!(kept.len(), 1);
+    assert!(kept[0].join("prog.s").is_file());
+    fs::remove_dir_all(&tmp).unwrap();
+}
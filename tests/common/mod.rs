@@ -0,0 +1,81 @@
+//! Helpers shared by the tests
+
+#![allow(dead_code)]
+
+use std::{
+    io::Write,
+    process::{Command, Output, Stdio},
+    sync::atomic::{AtomicUsize, Ordering}
+};
+
+use fig::{eval::Evaluator, fe::Severity, Options};
+
+/// Checks and runs `source` with the evaluator, giving back the value it
+/// returned, or the message it failed with.
+pub fn eval(source: &str) -> String {
+    let artifact = match fig::compile(source, &Options { check_only: true }) {
+        Ok(a) => a,
+        Err(d) => panic!("`{}` didn't compile:\n{}", source, d)
+    };
+    match Evaluator::new().run(artifact.statements()) {
+        Ok(v) => v.to_string(),
+        Err(e) => e.message
+    }
+}
+
+/// The code and message of every error in `source`, in order. Warnings are
+/// left out.
+pub fn errors(source: &str) -> Vec<(&'static str, String)> {
+    match fig::compile(source, &Options { check_only: true }) {
+        Ok(_) => vec![],
+        Err(d) => d.errors.into_iter()
+            .filter(|e| e.severity == Severity::Error)
+            .map(|e| (e.code.unwrap_or("none"), e.message))
+            .collect()
+    }
+}
+
+/// The codes of every error in `source`, in order.
+pub fn codes(source: &str) -> Vec<&'static str> {
+    errors(source).into_iter().map(|(c, _)| c).collect()
+}
+
+/// Runs `fig <command>` on a file holding `source`, with `args` after it.
+pub fn fig(command: &str, source: &str, args: &[&str]) -> Output {
+    fig_with_env(command, source, args, &[])
+}
+
+/// Runs `fig <command>` like [`fig`], with `vars` set in its environment.
+pub fn fig_with_env(command: &str, source: &str, args: &[&str], vars: &[(&str, &str)]) -> Output {
+    static FILES: AtomicUsize = AtomicUsize::new(0);
+    let n = FILES.fetch_add(1, Ordering::Relaxed);
+    let path = std::env::temp_dir().join(format!("fig-tests-{}-{}.fig", std::process::id(), n));
+    std::fs::write(&path, source).unwrap();
+    let out = Command::new(env!("CARGO_BIN_EXE_fig"))
+        .arg(command)
+        .arg(&path)
+        .args(args)
+        .envs(vars.iter().copied())
+        .output()
+        .unwrap();
+    std::fs::remove_file(&path).unwrap();
+    out
+}
+
+/// Runs `fig` with nothing but `args`.
+pub fn fig_args(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_fig")).args(args).output().unwrap()
+}
+
+/// Runs `fig <command>`, which takes no file, feeding it `stdin`.
+pub fn fig_with_input(command: &str, stdin: &[u8]) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_fig"))
+        .arg(command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(stdin).unwrap();
+    child.wait_with_output().unwrap()
+}
@@ -0,0 +1,98 @@
+//! `fig doc`, and the Markdown and HTML it writes.
+
+mod common;
+
+use common::fig;
+use fig::{doc::Page, Options};
+
+const SOURCE: &str = "\
+//! Sums of things.
+//!
+//! Use `add` for two of them.
+
+/// Adds `a` and `b`.
+///
+/// ```
+/// add(1, 2)
+/// ```
+fn add(a: i64, b: i64) -> i64 { return a + b }
+
+/// How many there are, `a < b`
+let count = add(1, 2)
+
+@test fn adds() { assert(add(1, 2) == 3) }
+";
+
+fn page(source: &str) -> Page {
+    let artifact = fig::compile(source, &Options { check_only: true }).unwrap();
+    fig::doc::document("sums", source, &artifact.symbols, artifact.statements())
+}
+
+#[test]
+fn markdown_lists_every_item_with_its_docs() {
+    assert_eq!(page(SOURCE).markdown(), "\
+# sums
+
+Sums of things.
+
+Use `add` for two of them.
+
+## Functions
+
+### `add`
+
+```fig
+fn add(a: i64, b: i64) -> i64
+```
+
+Adds `a` and `b`.
+
+```
+add(1, 2)
+```
+
+## Variables
+
+### `count`
+
+```fig
+let count: i64
+```
+
+How many there are, `a < b`
+");
+}
+
+#[test]
+fn html_escapes_text_and_marks_up_code() {
+    let html = page(SOURCE).html();
+    assert!(html.starts_with("<!DOCTYPE html>") && html.ends_with("</html>\n"));
+    assert!(html.contains("<p>Use <code>add</code> for two of them.</p>"), "{}", html);
+    assert!(html.contains("<section id=\"fn.add\">\n<h3><code>fn add(a: i64, b: i64) -&gt; i64</code></h3>"), "{}", html);
+    assert!(html.contains("<pre><code>add(1, 2)</code></pre>"), "{}", html);
+    assert!(html.contains("<p>How many there are, <code>a &lt; b</code></p>"), "{}", html);
+    // tests aren't part of the documentation
+    assert!(!html.contains("adds"), "{}", html);
+}
+
+#[test]
+fn unmatched_backticks_are_left_alone() {
+    let html = page("/// Costs 5` or more\nlet price = 5\n").html();
+    assert!(html.contains("<p>Costs 5` or more</p>"), "{}", html);
+    let html = page("/// Uses `a` and `b\nlet price = 5\n").html();
+    assert!(html.contains("<p>Uses <code>a</code> and `b</p>"), "{}", html);
+}
+
+#[test]
+fn fig_doc_writes_both() {
+    let dir = std::env::temp_dir().join(format!("fig-doc-{}", std::process::id()));
+    let out = fig("doc", SOURCE, &["-o", dir.to_str().unwrap()]);
+    assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr));
+    let written: Vec<_> = std::fs::read_dir(&dir).unwrap().map(|e| e.unwrap().path()).collect();
+    assert_eq!(written.len(), 2);
+    for path in written {
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert!(text.contains("Sums of things."), "{}", path.display());
+    }
+    std::fs::remove_dir_all(&dir).unwrap();
+}
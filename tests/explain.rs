@@ -0,0 +1,77 @@
+//! `fig explain`, and the explanations it prints.
+
+mod common;
+
+use common::fig_args;
+use fig::{fe::codes, Options};
+
+/// The indented code after `heading` in an explanation.
+fn example(explanation: &str, heading: &str) -> String {
+    let after = &explanation[explanation.find(heading).unwrap() + heading.len()..];
+    after.lines()
+        .skip_while(|l| l.trim().is_empty())
+        .take_while(|l| l.is_empty() || l.starts_with("    "))
+        .map(|l| l.strip_prefix("    ").unwrap_or(l))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[test]
+fn every_code_is_explained() {
+    let mut last = "";
+    for &code in codes::ALL {
+        assert!(code > last, "`{}` is out of order or used twice", code);
+        last = code;
+        let Some(explanation) = codes::explain(code) else { panic!("`{}` has no explanation", code) };
+        assert!(explanation.contains("Erroneous code example:"), "`{}` has no example", code);
+    }
+}
+
+#[test]
+fn examples_show_the_mistake() {
+    for &code in codes::ALL {
+        let source = example(codes::explain(code).unwrap(), "Erroneous code example:");
+        let options = Options { check_only: !code.starts_with("E04") };
+        let found = match fig::compile(&source, &options) {
+            Ok(_) => vec![],
+            Err(d) => d.errors.into_iter().filter_map(|e| e.code).collect()
+        };
+        assert!(found.contains(&code), "the example for `{}` gives {:?}:\n{}", code, found, source);
+    }
+}
+
+#[test]
+fn fixes_get_rid_of_the_mistake() {
+    for &code in codes::ALL {
+        let explanation = codes::explain(code).unwrap();
+        let heading = "Erroneous code example:";
+        let after = &explanation[explanation.find(heading).unwrap() + heading.len()..];
+        // some things can't be fixed yet, and only have the one example
+        let Some(fix) = after.find(":\n\n") else { continue };
+        let source = example(&after[fix..], ":");
+        let found = match fig::compile(&source, &Options { check_only: true }) {
+            Ok(_) => vec![],
+            Err(d) => d.errors.into_iter().filter_map(|e| e.code).collect()
+        };
+        assert!(!found.contains(&code), "the fix for `{}` still gives it:\n{}", code, source);
+    }
+}
+
+#[test]
+fn explain_prints_the_explanation() {
+    let out = fig_args(&["explain", "e302"]);
+    assert!(out.status.success());
+    assert_eq!(String::from_utf8(out.stdout).unwrap(), codes::explain(codes::MISMATCHED_DECLARATION).unwrap());
+}
+
+#[test]
+fn unknown_codes_fail_cleanly() {
+    for code in ["E9999", "nonsense"] {
+        let out = fig_args(&["explain", code]);
+        assert_eq!(out.status.code(), Some(1));
+        assert!(out.stdout.is_empty());
+        let err = String::from_utf8(out.stderr).unwrap();
+        assert!(err.starts_with("error: `") && err.contains("isn't a Fig error code"), "{}", err);
+    }
+    assert_eq!(fig_args(&["explain"]).status.code(), Some(2));
+}
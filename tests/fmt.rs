@@ -0,0 +1,57 @@
+//! The formatter, which has to leave formatted code the way it is.
+
+/// Formats `source`, which has to parse, and checks formatting it again
+/// doesn't change anything.
+fn fmt(source: &str) -> String {
+    let out = match fig::fmt::format(source) {
+        Ok(out) => out,
+        Err(d) => panic!("`{}` didn't parse:\n{}", source, d)
+    };
+    assert_eq!(fig::fmt::format(&out).ok().as_deref(), Some(&*out), "formatting twice changed it");
+    out
+}
+
+#[test]
+fn comments_between_statements_stay_put() {
+    let source = "let a = 1\n// c\nlet b = 2\n/* d */\nlet c = a + b\n";
+    assert_eq!(fmt(source), source);
+    let source = "fn f() -> i64 {\n    let x = 1\n    // y\n    return x\n    // z\n}\n// end\n";
+    assert_eq!(fmt(source), source);
+}
+
+#[test]
+fn comments_inside_expressions_are_indented_with_them() {
+    assert_eq!(fmt("let c = 1 +\n// why\n2\n"), "let c = 1 +\n    // why\n    2\n");
+}
+
+#[test]
+fn spacing_is_made_consistent() {
+    assert_eq!(fmt("let   a=1+2*3"), "let a = 1 + 2 * 3\n");
+    assert_eq!(fmt("fn f(a:i64,b:i64)->i64{return a+b}"), "fn f(a: i64, b: i64) -> i64 { return a + b }\n");
+    assert_eq!(fmt("let p = P{x:1,y:-2}"), "let p = P { x: 1, y: -2 }\n");
+}
+
+#[test]
+fn blocks_open_on_the_line_they_belong_to() {
+    assert_eq!(fmt("let x = if true {\n1\n}\nelse {\n2\n}"), "let x = if true {\n    1\n} else {\n    2\n}\n");
+    assert_eq!(fmt("if a > 1 { 1 } else { 2 }"), "if a > 1 { 1 } else { 2 }\n");
+}
+
+#[test]
+fn blank_lines_are_kept_but_not_piled_up() {
+    assert_eq!(fmt("let a = 1\n\n\n\nlet b = -a"), "let a = 1\n\nlet b = -a\n");
+}
+
+#[test]
+fn escapes_become_the_characters_they_name() {
+    assert_eq!(fmt("let r = 2.0 * \\pi"), "let r = 2.0 * π\n");
+}
+
+#[test]
+fn example_programs_are_already_formatted_after_one_pass() {
+    for file in ["fibonacci.fig", "expr.fig", "current.fig"] {
+        let path = format!("{}/{}", env!("CARGO_MANIFEST_DIR"), file);
+        let source = std::fs::read_to_string(path).unwrap();
+        fmt(&source);
+    }
+}
@@ -0,0 +1,106 @@
+//! Functions: parameters, what they give back, and calling them
+
+mod common;
+
+use common::{codes, eval};
+use fig::fe::codes::*;
+
+#[test]
+fn calls_with_arguments() {
+    assert_eq!(eval("fn add(a: i64, b: i64) -> i64 { return a + b }\nreturn add(2, 3)"), "5");
+    assert_eq!(eval("fn double(a: i64) -> a * 2\nreturn double(21)"), "42");
+}
+
+#[test]
+fn called_before_they_are_declared() {
+    assert_eq!(eval("return twice(4)\nfn twice(a: i64) -> i64 { return once(a) + once(a) }\nfn once(a: i64) -> i64 { return a }"), "8");
+}
+
+#[test]
+fn recursion() {
+    let source = "
+        fn fib(n: i64) -> i64 {
+            if n < 2 { return n }
+            return fib(n - 1) + fib(n - 2)
+        }
+        return fib(10)
+    ";
+    assert_eq!(eval(source), "55");
+}
+
+#[test]
+fn functions_cant_see_the_callers_variables() {
+    assert_eq!(codes("let a = 1\nfn f() -> i64 { return a }"), [UNRESOLVED_NAME]);
+}
+
+#[test]
+fn returns_in_every_branch() {
+    let source = "
+        fn pick(c: bool) -> i64 { if c { return 1 } else { return 2 } }
+        return pick(false)
+    ";
+    assert_eq!(eval(source), "2");
+    let source = "
+        fn sign(n: i64) -> i64 {
+            match n {
+                0 -> { return 0 },
+                n -> if n < 0 { return -1 } else { return 1 }
+            }
+        }
+        return sign(-5)
+    ";
+    assert_eq!(eval(source), "-1");
+}
+
+#[test]
+fn returns_from_a_loop() {
+    assert_eq!(eval("fn f() -> i64 { loop { return 1 } }\nreturn f()"), "1");
+    let source = "
+        fn first_over(n: i64) -> i64 {
+            let i = 0
+            loop {
+                if i * i > n { return i }
+                i += 1
+            }
+        }
+        return first_over(50)
+    ";
+    assert_eq!(eval(source), "8");
+}
+
+#[test]
+fn every_way_out_needs_a_return() {
+    assert_eq!(codes("fn f(c: bool) -> i64 { if c { return 1 } }"), [MISMATCHED_RETURN]);
+    assert_eq!(codes("fn f(c: bool) -> i64 { loop { if c { break }; return 1 } }"), [MISMATCHED_RETURN]);
+    assert_eq!(codes("fn f(c: bool) -> i64 { while c { return 1 } }"), [MISMATCHED_RETURN]);
+}
+
+#[test]
+fn return_types_are_checked() {
+    assert_eq!(codes("fn f() -> bool { return 1 }"), [MISMATCHED_RETURN]);
+    assert_eq!(codes("fn f() { return 1 }"), [MISMATCHED_RETURN]);
+}
+
+#[test]
+fn arguments_are_checked() {
+    assert_eq!(codes("fn f(a: i64) -> a\nf(true)"), [BAD_ARGUMENTS]);
+    assert_eq!(codes("fn f(a: i64) -> a\nf(1, 2)"), [BAD_ARGUMENTS]);
+    assert_eq!(codes("let a = 1\na()"), [NOT_CALLABLE]);
+}
+
+#[test]
+fn bare_returns_give_back_unit() {
+    let source = "
+        fn check(c: bool) {
+            if c { return }
+            return;
+        }
+        check(true)
+        fn early() { return }
+        early()
+        return
+    ";
+    assert_eq!(eval(source), "()");
+    assert_eq!(eval("fn f(n: i64) -> i64 { if n > 0 { return n }; return 0 }\nreturn f(3)"), "3");
+    assert_eq!(codes("fn f() -> i64 { return }"), [MISMATCHED_RETURN]);
+}
@@ -0,0 +1,44 @@
+//! `--message-format json`, which prints a JSON object per diagnostic.
+
+mod common;
+
+use common::fig;
+use fig::{fe::codes::*, json::Json};
+
+/// The diagnostics `fig check` prints for `source` as JSON.
+fn check(source: &str) -> Vec<Json> {
+    let out = fig("check", source, &["--message-format", "json"]);
+    String::from_utf8(out.stdout).unwrap()
+        .lines()
+        .map(|l| Json::parse(l).unwrap_or_else(|e| panic!("`{}` isn't JSON: {}", l, e)))
+        .collect()
+}
+
+#[test]
+fn errors_say_where_they_are() {
+    let d = check("let a = 1\nreturn a + true\n");
+    assert_eq!(d.len(), 1);
+    let e = &d[0];
+    assert_eq!(e.get("severity").as_str(), Some("error"));
+    assert_eq!(e.get("code").as_str(), Some(MISMATCHED_OPERANDS));
+    assert_eq!(e.get("line").as_usize(), Some(2));
+    assert_eq!(e.get("column").as_usize(), Some(10));
+    assert_eq!(e.get("byte_start").as_usize(), Some(19));
+    assert_eq!(e.get("byte_end").as_usize(), Some(20));
+    let labels = e.get("labels").as_array().unwrap();
+    assert_eq!(labels.len(), 2);
+    assert_eq!(labels[1].get("message").as_str(), Some("this is `bool`"));
+    assert_eq!(labels[1].get("column").as_usize(), Some(12));
+}
+
+#[test]
+fn warnings_and_errors_come_in_order() {
+    let d = check("let unused = 1\nreturn y\n");
+    let found: Vec<_> = d.iter().map(|e| (e.get("severity").as_str(), e.get("code").as_str())).collect();
+    assert_eq!(found, [(Some("warning"), Some("unused_variables")), (Some("error"), Some(UNRESOLVED_NAME))]);
+}
+
+#[test]
+fn nothing_is_printed_for_good_code() {
+    assert!(check("let a = 1\nreturn a\n").is_empty());
+}
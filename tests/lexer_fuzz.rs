@@ -0,0 +1,171 @@
+//! Throws random input at the lexer, and everything built on it, to make sure
+//! none of it panics or loops forever. The inputs come from a fixed seed, so a
+//! failure always comes back.
+
+use fig::fe::{cst, lexer::{LexError, Lexer}, parser::Parser, token::Token};
+
+/// xorshift, which is plenty random for this
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    fn pick<'t, T>(&mut self, from: &'t [T]) -> &'t T {
+        &from[self.below(from.len())]
+    }
+}
+
+/// Bytes that mean something to the lexer, and some that can't be UTF-8
+const BYTES: &[u8] = b"\"'{}()[]/*\\\r\n\t 0123456789xobeEuif_.-+=<>!&|@:;,?aZ\0\xff\xc3\xe2";
+
+/// Pieces of code that are more likely to get somewhere than random bytes
+const SNIPPETS: &[&str] = &[
+    "let", "fn", "if", "else", "loop", "break", "continue", "return", "import", "<-", "@test", "@allow(unused)",
+    "x", "π", "_y", "=", "+", "-", "**", "==", "&&", "!", ".", ",", ";", ":", "i64",
+    "(", ")", "{", "}", "[", "]", " ", "\n", "\r\n", "\t",
+    "1", "0x1F", "0b2", "0o", "1_000", "1.5e-3", "1e", "255u8", "-128i8", "2.0f32", "1.5u8", "9999999999999999999999",
+    "\"", "'", "\"a{x}b\"", "\"{", "\\", "\\u{", "\\u{1F600}", "\\q", "\"\\n\"",
+    "//", "///", "//!", "/*", "*/", "/* /* */ */", "\0",
+    "'a: loop", "'a:", "'a", "break 'a", "continue 'a", "while", "for", "in", "..", "..=", "0..10",
+    "match", "->", "_ ->", "match x { 1 -> 2, _ -> 3 }",
+    "struct", "struct P { x: i64 }", "P { x: 1 }", "P", "x.y", ".x",
+];
+
+fn random_bytes(rng: &mut Rng) -> String {
+    let len = rng.below(48);
+    let bytes: Vec<u8> = (0..len).map(|_| *rng.pick(BYTES)).collect();
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+fn random_snippets(rng: &mut Rng) -> String {
+    let len = rng.below(32);
+    (0..len).map(|_| *rng.pick(SNIPPETS)).collect()
+}
+
+/// Runs `source` through everything that takes source code.
+fn check(source: &str) {
+    let mut last_end = 0;
+    let mut count = 0;
+    for t in Lexer::new(source) {
+        assert!(t.span.start >= last_end && t.span.start < t.span.end, "bad span {:?} in {:?}", t.span, source);
+        assert!(source.is_char_boundary(t.span.start) && source.is_char_boundary(t.span.end));
+        assert!(!matches!(t.data, Token::Nothing));
+        last_end = t.span.end;
+        count += 1;
+        assert!(count <= source.len(), "the lexer never finished {:?}", source);
+    }
+    assert!(source[last_end..].chars().all(char::is_whitespace), "the lexer stopped early in {:?}", source);
+
+    assert_eq!(cst::parse(source).to_string(), source);
+
+    let (_, errors) = Parser::new(source).parse();
+    for e in &errors {
+        e.to_string();
+    }
+    match fig::compile(source, &fig::Options { check_only: true }) {
+        // whatever checks has to either compile or say why it can't
+        Ok(_) => if let Err(d) = fig::compile(source, &fig::Options::default()) {
+            d.to_string();
+        },
+        Err(d) => { d.to_string(); }
+    }
+    let _ = fig::fmt::format(source);
+}
+
+#[test]
+fn random_bytes_never_panic() {
+    let mut rng = Rng(0x5EED);
+    for _ in 0..4000 {
+        check(&random_bytes(&mut rng));
+    }
+}
+
+#[test]
+fn random_code_never_panics() {
+    let mut rng = Rng(0xF16);
+    for _ in 0..4000 {
+        check(&random_snippets(&mut rng));
+    }
+}
+
+#[test]
+fn unfinished_things_at_the_end() {
+    for source in ["\"", "'", "/*", "/*/", "//", "// no newline", "///", "0x", "1e", "1.", "\\", "\"\\", "\"{", "@", "&", "\0"] {
+        check(source);
+        check(&format!("let a = 1\n{}", source));
+    }
+}
+
+#[test]
+fn crlf_is_whitespace() {
+    let source = "let a = 1\r\nlet b = a + 1 // two\r\nassert(b == 2)\r\n";
+    check(source);
+    assert!(fig::compile(source, &fig::Options { check_only: true }).is_ok());
+}
+
+#[test]
+fn block_comments_nest() {
+    let source = "/* outer /* inner */ still outer */ let a = 1";
+    let tokens: Vec<_> = Lexer::new(source).map(|t| t.data).collect();
+    assert!(matches!(tokens[..], [Token::Comment(..), Token::Let, Token::Identifier("a"), Token::Assign, Token::CompInt(1, None)]));
+}
+
+#[test]
+fn lexing_carries_on_after_errors() {
+    let tokens: Vec<_> = Lexer::new("let a = 5$ + 0b12 + 3").map(|t| t.data).collect();
+    assert_eq!(tokens.iter().filter(|t| matches!(t, Token::Error(..))).count(), 2);
+    assert!(matches!(tokens.last(), Some(Token::CompInt(3, None))));
+}
+
+#[test]
+fn labels_and_single_quoted_strings() {
+    let tokens: Vec<_> = Lexer::new("'a: loop { break 'a 'a' }").map(|t| t.data).collect();
+    assert!(matches!(tokens[..], [
+        Token::Label("a"), Token::Colon, Token::Loop, Token::LBrace,
+        Token::Break, Token::Label("a"), Token::String("a"), Token::RBrace
+    ]));
+    let tokens: Vec<_> = Lexer::new("'a: b' 'loop'").map(|t| t.data).collect();
+    assert!(matches!(tokens[..], [Token::String("a: b"), Token::String("loop")]));
+    // strings can start out looking like a label
+    let tokens: Vec<_> = Lexer::new("let s = 'x: loop'").map(|t| t.data).collect();
+    assert!(matches!(tokens[..], [Token::Let, Token::Identifier("s"), Token::Assign, Token::String("x: loop")]));
+    let tokens: Vec<_> = Lexer::new("'x: loop forever' 'y: while'").map(|t| t.data).collect();
+    assert!(matches!(tokens[..], [Token::String("x: loop forever"), Token::String("y: while")]));
+    let tokens: Vec<_> = Lexer::new("'a: loop\n{ }").map(|t| t.data).collect();
+    assert!(matches!(tokens[0], Token::Label("a")));
+}
+
+#[test]
+fn labels_need_a_jump_on_the_same_line() {
+    let tokens: Vec<_> = Lexer::new("break 'a; s = 'b'").map(|t| t.data).collect();
+    assert!(matches!(tokens[..], [
+        Token::Break, Token::Label("a"), Token::Semicolon, Token::Identifier("s"), Token::Assign, Token::String("b")
+    ]));
+    let tokens: Vec<_> = Lexer::new("break\n'a'").map(|t| t.data).collect();
+    assert!(matches!(tokens[..], [Token::Break, Token::String("a")]));
+    let tokens: Vec<_> = Lexer::new("continue // why\n'a'").map(|t| t.data).collect();
+    assert!(matches!(tokens[..], [Token::Continue, Token::Comment(..), Token::String("a")]));
+    let tokens: Vec<_> = Lexer::new("break\n'a").map(|t| t.data).collect();
+    assert!(matches!(tokens[..], [Token::Break, Token::Error(LexError::UnterminatedString(..))]));
+}
+
+#[test]
+fn strings_closed_on_the_same_line_arent_labels() {
+    let tokens: Vec<_> = Lexer::new("break 'two words'").map(|t| t.data).collect();
+    assert!(matches!(tokens[..], [Token::Break, Token::String("two words")]));
+    let tokens: Vec<_> = Lexer::new("let s = 'a: loop {'").map(|t| t.data).collect();
+    assert!(matches!(tokens[..], [Token::Let, Token::Identifier("s"), Token::Assign, Token::String("a: loop {")]));
+    // unless a name comes right after the quote, which makes it another label
+    let tokens: Vec<_> = Lexer::new("'a: loop { s = 'x' }").map(|t| t.data).collect();
+    assert!(matches!(tokens[..2], [Token::Label("a"), Token::Colon]));
+    assert!(matches!(tokens[tokens.len() - 2], Token::String("x")));
+}
@@ -0,0 +1,55 @@
+//! Lints, which warn about code that's allowed but probably a mistake.
+
+mod common;
+
+use common::codes;
+use fig::{fe::Severity, Options};
+
+/// The lints that go off for `source`, as `(name, denied)`.
+fn lints(source: &str) -> Vec<(&'static str, bool)> {
+    let found = match fig::compile(source, &Options { check_only: true }) {
+        Ok(a) => a.warnings,
+        Err(d) => d.errors
+    };
+    found.into_iter()
+        .map(|e| (e.code.unwrap_or("none"), e.severity == Severity::Error))
+        .collect()
+}
+
+#[test]
+fn unused_variables() {
+    assert_eq!(lints("let a = 1\nreturn 2"), [("unused_variables", false)]);
+    assert_eq!(lints("let a = 1\nreturn a"), []);
+    assert_eq!(lints("fn f(n: i64) -> i64 { return 1 }\nreturn f(1)"), [("unused_variables", false)]);
+}
+
+#[test]
+fn unreachable_code() {
+    let source = "fn f() -> i64 {\nreturn 1\nlet x = 2\n}\nreturn f()";
+    assert_eq!(lints(source), [("unreachable_code", false), ("unused_variables", false)]);
+    assert_eq!(lints("return match 1 { _ -> 1, 2 -> 3 }"), [("unreachable_code", false)]);
+}
+
+#[test]
+fn shadowing() {
+    assert_eq!(lints("let a = 1\nlet a = a + 1\nreturn a"), [("shadowing", false)]);
+}
+
+#[test]
+fn levels_can_be_changed() {
+    assert_eq!(lints("@allow(unused) let a = 1\nreturn 2"), []);
+    let source = "@deny(shadowing) fn f() -> i64 {\nlet a = 1\nlet a = a + 2\nreturn a\n}\nreturn f()";
+    assert_eq!(lints(source), [("shadowing", true)]);
+    // a denied lint stops the program like any error
+    assert_eq!(codes(source), ["shadowing"]);
+    // and only counts inside what it's on
+    assert_eq!(lints("@allow(shadowing) fn f() { }\nlet a = 1\nlet a = a\nreturn a"), [("shadowing", false)]);
+}
+
+#[test]
+fn unknown_lints_are_pointed_out() {
+    assert_eq!(lints("@allow(nonsense) let a = 1\nreturn a"), [("none", false)]);
+    let found = fig::compile("@allow(unused_imports) let a = 1\nreturn a", &Options { check_only: true }).unwrap();
+    let messages: Vec<_> = found.warnings.iter().map(|w| w.message.as_str()).collect();
+    assert_eq!(messages, ["`unused_imports` isn't a lint yet, since `import` isn't supported"]);
+}
@@ -0,0 +1,55 @@
+//! Loops, and getting out of them.
+
+mod common;
+
+use common::{codes, eval};
+use fig::fe::codes::*;
+
+#[test]
+fn labels_reach_outer_loops() {
+    assert_eq!(eval("let n = 'outer: loop { loop { break 'outer 5 } }\nreturn n"), "5");
+    let source = "let c = 0\n'rows: for i in 0..3 { for j in 0..3 { if j > i { continue 'rows }\nc += 1 } }\nreturn c";
+    assert_eq!(eval(source), "6");
+}
+
+#[test]
+fn labels_have_to_be_on_a_loop_around() {
+    assert_eq!(codes("loop { break 'nope }"), [UNKNOWN_LABEL]);
+    assert_eq!(codes("'a: loop { fn f() { break 'a }\nbreak }"), [UNKNOWN_LABEL]);
+    assert_eq!(codes("'a: loop { 'a: loop { break 'a } }"), [SHADOWED_LABEL]);
+}
+
+#[test]
+fn jumps_need_a_loop() {
+    assert_eq!(codes("break"), [OUTSIDE_LOOP]);
+    assert_eq!(codes("fn f() { continue }\nf()"), [OUTSIDE_LOOP]);
+    // a function in a loop starts afresh
+    assert_eq!(codes("loop { fn f() { break }\nbreak }"), [OUTSIDE_LOOP]);
+}
+
+#[test]
+fn what_a_loop_breaks_with_has_one_type() {
+    assert_eq!(codes("let n = loop { if true { break 1 }\nbreak true }"), [MISMATCHED_BREAK]);
+}
+
+#[test]
+fn while_loops() {
+    assert_eq!(eval("let i = 0\nwhile i < 10 { i += 1 }\nreturn i"), "10");
+    assert_eq!(eval("let i = 0\nwhile false { i += 1 }\nreturn i"), "0");
+    assert_eq!(codes("while 1 { }"), [NON_BOOL_CONDITION]);
+}
+
+#[test]
+fn for_loops_over_ranges() {
+    assert_eq!(eval("let s = 0\nfor i in 1..4 { s += i }\nreturn s"), "6");
+    assert_eq!(eval("let s = 0\nfor i in 1..=4 { s += i }\nreturn s"), "10");
+    // a range that ends before it starts is empty
+    assert_eq!(eval("let s = 0\nfor i in 5..2 { s += 1 }\nreturn s"), "0");
+    assert_eq!(eval("let s = 0\nfor i in 0..10 { if i % 2 == 0 { continue }\ns += i }\nreturn s"), "25");
+    assert_eq!(codes("for i in 0..true { }"), [BAD_RANGE]);
+}
+
+#[test]
+fn the_loop_variable_stays_in_the_loop() {
+    assert_eq!(codes("for i in 0..3 { }\nreturn i"), [UNRESOLVED_NAME]);
+}
@@ -0,0 +1,101 @@
+//! `fig lsp`, talked to the way an editor would.
+
+mod common;
+
+use common::fig_with_input;
+use fig::{fe::codes::*, json::Json};
+
+/// Frames `messages` the way the protocol wants them.
+fn frame(messages: &[Json]) -> Vec<u8> {
+    let mut out = vec![];
+    for m in messages {
+        let body = m.to_string();
+        out.extend(format!("Content-Length: {}\r\n\r\n{}", body.len(), body).into_bytes());
+    }
+    out
+}
+
+/// Pulls the messages back out of what the server wrote.
+fn unframe(mut out: &str) -> Vec<Json> {
+    let mut messages = vec![];
+    while let Some((header, rest)) = out.split_once("\r\n\r\n") {
+        let length: usize = header.trim_start_matches("Content-Length: ").parse().unwrap();
+        messages.push(Json::parse(&rest[..length]).unwrap());
+        out = &rest[length..];
+    }
+    messages
+}
+
+fn request(id: usize, method: &str, params: Json) -> Json {
+    Json::object([("jsonrpc", "2.0".into()), ("id", id.into()), ("method", method.into()), ("params", params)])
+}
+
+fn notification(method: &str, params: Json) -> Json {
+    Json::object([("jsonrpc", "2.0".into()), ("method", method.into()), ("params", params)])
+}
+
+fn position(uri: &str, line: usize, character: usize) -> Json {
+    Json::object([
+        ("textDocument", Json::object([("uri", uri.into())])),
+        ("position", Json::object([("line", line.into()), ("character", character.into())]))
+    ])
+}
+
+/// Opens a document holding `text`, sends `then`, and shuts down, giving
+/// back everything the server said.
+fn session(text: &str, then: Vec<Json>) -> Vec<Json> {
+    let mut messages = vec![
+        request(1, "initialize", Json::object([])),
+        notification("initialized", Json::object([])),
+        notification("textDocument/didOpen", Json::object([
+            ("textDocument", Json::object([
+                ("uri", "file:///a.fig".into()),
+                ("languageId", "fig".into()),
+                ("version", 1.into()),
+                ("text", text.into())
+            ]))
+        ]))
+    ];
+    messages.extend(then);
+    messages.push(request(99, "shutdown", Json::Null));
+    messages.push(notification("exit", Json::Null));
+    let out = fig_with_input("lsp", &frame(&messages));
+    assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr));
+    unframe(&String::from_utf8(out.stdout).unwrap())
+}
+
+/// The result of the request with `id`.
+fn result(replies: &[Json], id: usize) -> &Json {
+    replies.iter().find(|r| r.get("id").as_usize() == Some(id)).unwrap().get("result")
+}
+
+#[test]
+fn diagnostics_are_published_when_a_document_opens() {
+    let replies = session("let a = 1\nreturn a + true\n", vec![]);
+    let published = replies.iter()
+        .find(|r| r.get("method").as_str() == Some("textDocument/publishDiagnostics"))
+        .unwrap();
+    let diagnostics = published.get("params").get("diagnostics").as_array().unwrap();
+    assert_eq!(diagnostics.len(), 1);
+    let d = &diagnostics[0];
+    assert_eq!(d.get("code").as_str(), Some(MISMATCHED_OPERANDS));
+    assert_eq!(d.get("severity").as_usize(), Some(1));
+    // positions start from zero
+    assert_eq!(d.get("range").get("start").get("line").as_usize(), Some(1));
+    assert_eq!(d.get("range").get("start").get("character").as_usize(), Some(9));
+}
+
+#[test]
+fn hovering_shows_types_and_definitions_are_found() {
+    let text = "fn twice(n: i64) -> i64 { return n * 2 }\nlet x = twice(4)\n";
+    let replies = session(text, vec![
+        request(2, "textDocument/hover", position("file:///a.fig", 1, 4)),
+        request(3, "textDocument/definition", position("file:///a.fig", 1, 9))
+    ]);
+    let hover = result(&replies, 2).get("contents").get("value").as_str().unwrap();
+    assert_eq!(hover, "```fig\nx: i64\n```");
+    let definition = result(&replies, 3);
+    assert_eq!(definition.get("uri").as_str(), Some("file:///a.fig"));
+    assert_eq!(definition.get("range").get("start").get("line").as_usize(), Some(0));
+    assert_eq!(definition.get("range").get("start").get("character").as_usize(), Some(3));
+}
@@ -0,0 +1,34 @@
+//! `match`, which picks the first arm whose pattern fits.
+
+mod common;
+
+use common::{codes, eval};
+use fig::fe::codes::*;
+
+#[test]
+fn the_first_fitting_arm_is_picked() {
+    let source = "let n = 2\nreturn match n { 1 -> \"one\", 2 -> \"two\", _ -> \"many\" }";
+    assert_eq!(eval(source), "\"two\"");
+    assert_eq!(eval("return match 5 { 1 -> 1, _ -> 2, 5 -> 3 }"), "2");
+    assert_eq!(eval("return match \"b\" { \"a\" -> 1, \"b\" -> 2, _ -> 3 }"), "2");
+}
+
+#[test]
+fn names_bind_the_value() {
+    assert_eq!(eval("return match 7 { 1 -> 0, other -> other * 2 }"), "14");
+    assert_eq!(codes("let a = match 7 { other -> other }\nreturn other"), [UNRESOLVED_NAME]);
+}
+
+#[test]
+fn every_value_has_to_be_covered() {
+    assert_eq!(eval("return match true { true -> 1, false -> 0 }"), "1");
+    assert_eq!(codes("return match true { true -> 1 }"), [NON_EXHAUSTIVE]);
+    assert_eq!(codes("return match 1 { 1 -> 1 }"), [NON_EXHAUSTIVE]);
+}
+
+#[test]
+fn patterns_and_arms_have_to_agree() {
+    assert_eq!(codes("return match 1 { \"a\" -> 1, _ -> 2 }"), [MISMATCHED_PATTERN]);
+    assert_eq!(codes("return match 1 { 1 -> 1, _ -> \"b\" }"), [MISMATCHED_ARMS]);
+    assert_eq!(codes("return match 1 { 1 => 1 }"), [BAD_MATCH]);
+}
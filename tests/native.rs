@@ -0,0 +1,76 @@
+//! Compiling to native code. Nothing here runs the result, since that needs
+//! an arm64 machine, but everything that type checks has to either compile or
+//! be reported.
+
+use fig::{fe::codes::NOT_NATIVE, Options};
+
+/// The assembly for `source`, which has to compile.
+fn asm(source: &str) -> String {
+    match fig::compile(source, &Options::default()) {
+        Ok(a) => a.asm.expect("compiling all the way gives assembly"),
+        Err(d) => panic!("`{}` didn't compile:\n{}", source, d)
+    }
+}
+
+/// The codes of the errors `source` gives when it's compiled all the way.
+fn codes(source: &str) -> Vec<&'static str> {
+    match fig::compile(source, &Options::default()) {
+        Ok(_) => vec![],
+        Err(d) => d.errors.into_iter().map(|e| e.code.unwrap_or("none")).collect()
+    }
+}
+
+#[test]
+fn logical_operators_short_circuit() {
+    let out = asm("let a = 1\nlet b = a > 0 && a < 10\nlet c = a == 0 || a / 0 == 1\nreturn a");
+    // each side on the right gets a branch around it
+    assert!(out.matches("b.eq").count() >= 2);
+}
+
+#[test]
+fn remainders_and_powers() {
+    asm("let a = 17 % 5\nlet b = 2 ** 10\na %= 3\nb **= 2\nreturn a + b");
+    asm("fn f(a: i64, b: i64) -> i64 { return a ** b % 7 }\nreturn f(3, 4)");
+}
+
+#[test]
+fn sized_integers() {
+    let out = asm("let a = 250u8 + 10u8\nlet b = 127i8 + 1i8\nlet c = 3u64 / 2u64\nreturn 0");
+    // small integers are cut back down to size after arithmetic
+    assert!(out.contains("and ") && out.contains("sxtb"));
+    // and unsigned ones are divided as such
+    assert!(out.contains("udiv"));
+    assert!(asm("return 18446744073709551615u64 > 1u64").contains("LO"));
+}
+
+#[test]
+fn whats_missing_is_reported() {
+    assert_eq!(codes("let half = 0.5\nreturn 0"), [NOT_NATIVE]);
+    assert_eq!(codes("struct P { x: f64 }\nlet p = P { x: 1.0 }\nreturn 0"), [NOT_NATIVE]);
+    assert_eq!(codes("fn f() -> i64 { assert(true); return 1 }\nreturn f()"), [NOT_NATIVE]);
+    assert_eq!(codes("let n = 2\nlet s = \"n is {n}\"\nreturn n"), [NOT_NATIVE]);
+    assert_eq!(codes("let s = \"a\"\nreturn match s { \"a\" -> 1, \"b\" -> 2, _ -> 3 }"), [NOT_NATIVE, NOT_NATIVE]);
+    assert_eq!(codes("let same = \"a\" != \"b\"\nreturn 0"), [NOT_NATIVE]);
+    // ints and bools are matched by value
+    asm("let n = 2\nreturn match n == 2 { true -> match n { 1 -> 10, 2 -> 20, _ -> 0 }, false -> 0 }");
+    // tests aren't compiled, so they can use anything
+    asm("@test fn t() { assert(1.5 > 1.0) }\nreturn 0");
+}
+
+#[test]
+fn running_out_of_registers_is_reported() {
+    let names: Vec<_> = (0..30).map(|i| format!("a{}", i)).collect();
+    let lets: String = names.iter().map(|n| format!("let {} = 1\n", n)).collect();
+    let source = format!("{}return {}", lets, names.join(" + "));
+    assert_eq!(codes(&source), [NOT_NATIVE]);
+    assert_eq!(codes(&format!("fn f() -> i64 {{ {} }}\nreturn f()", source)), [NOT_NATIVE]);
+}
+
+#[test]
+fn structs_live_in_the_frame() {
+    let source = "struct P { x: i64, y: i64 }\nfn swap(p: P) -> P { return P { x: p.y, y: p.x } }\n\
+        let a = P { x: 1, y: 2 }\nlet b = swap(a)\nb.x += 10\nreturn b.x + a.y";
+    let out = asm(source);
+    // fields are stored to and loaded from memory, instead of living in registers
+    assert!(out.contains(", #8]"));
+}
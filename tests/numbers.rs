@@ -0,0 +1,55 @@
+//! Number literals and arithmetic on them
+
+mod common;
+
+use common::{codes, errors, eval};
+use fig::fe::codes::*;
+
+#[test]
+fn sized_integers_wrap_around() {
+    assert_eq!(eval("return 255u8 + 1u8"), "0");
+    assert_eq!(eval("return 0u8 - 1u8"), "255");
+    assert_eq!(eval("return 200u8 * 2u8"), "144");
+    assert_eq!(eval("return 127i8 + 1i8"), "-128");
+    assert_eq!(eval("return -128i8 / -1i8"), "-128");
+    assert_eq!(eval("return 65535u16 + 1u16"), "0");
+    assert_eq!(eval("return 2i32 ** 31i32"), "-2147483648");
+    assert_eq!(eval("return 4294967295u32 + 1u32"), "0");
+    assert_eq!(eval("return 9223372036854775807 + 1"), "-9223372036854775808");
+}
+
+#[test]
+fn wrapping_keeps_going_through_variables() {
+    assert_eq!(eval("let a = 250u8\na += 10u8\nreturn a"), "4");
+    assert_eq!(eval("let total = 0u8\nfor i in 0u8..20u8 { total += 20u8 }\nreturn total"), "144");
+}
+
+#[test]
+fn literals_in_other_bases() {
+    assert_eq!(eval("return 0xff + 0b101 + 0o17"), "275");
+    assert_eq!(eval("return 0xFFu8"), "255");
+    assert_eq!(eval("return 1_000_000 + 1__0"), "1000010");
+}
+
+#[test]
+fn floats_with_exponents() {
+    assert_eq!(eval("return 1.5e3"), "1500.0");
+    assert_eq!(eval("return 2.5e-1f32"), "0.25");
+}
+
+#[test]
+fn suffixes_pick_the_type() {
+    assert_eq!(eval("return -128i8"), "-128");
+    assert_eq!(codes("return 1u8 + 1i8"), [MISMATCHED_OPERANDS]);
+    assert!(codes("let a: i16 = 5i16\nreturn a").is_empty());
+}
+
+#[test]
+fn bad_literals_are_reported() {
+    assert_eq!(errors("return 300u8"), [(BAD_NUMBER, "`300u8` doesn't fit in a `u8`, which goes up to 255".to_string())]);
+    assert_eq!(errors("return 128i8"), [(BAD_NUMBER, "`128i8` doesn't fit in an `i8`, which goes from -128 to 127".to_string())]);
+    assert_eq!(codes("return 0x"), [BAD_NUMBER]);
+    assert_eq!(codes("return 0b102"), [BAD_NUMBER]);
+    assert_eq!(codes("return 1e400"), [BAD_NUMBER]);
+    assert_eq!(codes("return 12abc"), [BAD_NUMBER]);
+}
@@ -0,0 +1,58 @@
+//! The parser, which carries on after a mistake so it can report the rest.
+
+mod common;
+
+use common::{codes, errors, eval};
+use fig::fe::codes::*;
+
+#[test]
+fn every_broken_statement_is_reported() {
+    let found = codes("let = 1\nlet b = 2 +\nlet c = )\nreturn b");
+    assert_eq!(found, [BAD_DECLARATION, EXPECTED_EXPRESSION, EXPECTED_EXPRESSION]);
+    let found = codes("fn (a) { }\nlet x = 1 1\nreturn x");
+    assert_eq!(found, [BAD_FUNCTION, EXPECTED_SEMICOLON]);
+}
+
+#[test]
+fn unclosed_brackets_are_reported_once() {
+    assert_eq!(codes("let a = (1 + 2\nlet b = 3"), [UNBALANCED_DELIMITER]);
+}
+
+#[test]
+fn messages_say_what_was_found() {
+    let found = errors("let a = 1 +\nreturn a");
+    assert_eq!(found, [(EXPECTED_EXPRESSION, "expected an expression, found `return`".to_string())]);
+}
+
+#[test]
+fn types_are_only_checked_once_it_all_parses() {
+    assert_eq!(codes("let a = )\nreturn 1 + true"), [EXPECTED_EXPRESSION]);
+    assert_eq!(codes("let a = 1\nreturn a + true"), [MISMATCHED_OPERANDS]);
+}
+
+#[test]
+fn line_breaks_end_statements() {
+    assert_eq!(eval("let a = 1; let b = 2; return a + b"), "3");
+    assert_eq!(eval("let a = 1\nlet b = 2\nreturn a + b"), "3");
+    // `-1` makes a statement of its own, since it could start one
+    assert_eq!(eval("let x = 5\n-1\nreturn x"), "5");
+    assert_eq!(codes("let a = 1 let b = 2"), [EXPECTED_SEMICOLON]);
+}
+
+#[test]
+fn unfinished_lines_carry_on() {
+    assert_eq!(eval("let a = 1 +\n2\nreturn a"), "3");
+    assert_eq!(eval("let a = 1\n+ 2\nreturn a"), "3");
+    assert_eq!(eval("let a = (1\n+ 2)\nreturn a"), "3");
+    assert_eq!(eval("fn f(a: i64,\nb: i64) -> i64 {\nreturn a - b\n}\nreturn f(\n5,\n2\n)"), "3");
+    // but a call has to start on the line its function is named on
+    assert_eq!(codes("fn f() -> i64 { return 1 }\nreturn f\n()"), [EXPECTED_EXPRESSION]);
+}
+
+#[test]
+fn labels_only_follow_a_jump_on_its_own_line() {
+    // `'a` starts a string that's never closed, not a label for the `break`
+    assert_eq!(codes("loop {\n  break\n  'a\n}"), [UNTERMINATED_STRING, UNBALANCED_DELIMITER]);
+    assert!(codes("loop {\n break\n 'x: 1'\n}").is_empty());
+    assert_eq!(eval("return loop { break 'two words' }"), "\"two words\"");
+}
@@ -0,0 +1,83 @@
+//! How diagnostics look in a terminal.
+
+use fig::fe::{CompileError, Sp};
+
+/// Renders an error at `at` in `source`, with a label for each of `labels`.
+fn render(source: &str, at: &str, labels: &[(&str, &str)]) -> String {
+    let span = |text: &str| {
+        let start = source.find(text).unwrap();
+        Sp::new(source, start..start + text.len(), ())
+    };
+    let mut e = CompileError::error(&span(at), "something's wrong");
+    for (text, message) in labels {
+        e = e.with_label(&span(text), message);
+    }
+    e.render("a.fig", false)
+}
+
+#[test]
+fn labels_on_other_lines() {
+    assert_eq!(render("let a = 1\nlet b = a + true\n", "true", &[("a = 1", "because of this")]), "\
+error: something's wrong
+ --> a.fig:2:13
+  |
+1 | let a = 1
+  |     ----- because of this
+2 | let b = a + true
+  |             ^^^^
+");
+}
+
+#[test]
+fn spans_over_several_lines_are_cut_off_at_the_first() {
+    let source = "let a = 1 +\n    2 +\n    3\nlet b = a";
+    assert_eq!(render(source, "b", &[("1 +\n    2 +\n    3", "this is `i64`")]), "\
+error: something's wrong
+ --> a.fig:4:5
+  |
+1 | let a = 1 +
+  |         --- this is `i64`
+...
+4 | let b = a
+  |     ^
+");
+}
+
+#[test]
+fn labels_in_other_code() {
+    let earlier = "let a = 1";
+    let source = "a = true";
+    let e = CompileError::error(&Sp::new(source, 4..8, ()), "something's wrong")
+        .with_label(&Sp::new(earlier, 4..5, ()), "declared here");
+    assert_eq!(e.render("<repl>", false), "\
+error: something's wrong
+ --> <repl>:1:5
+  |
+1 | a = true
+  |     ^^^^
+  |
+ ::: <repl>:1:5
+  |
+1 | let a = 1
+  |     - declared here
+");
+}
+
+#[test]
+fn carets_line_up_after_tabs_and_wide_characters() {
+    assert_eq!(render("\tlet a = true + 1", "true", &[]), "\
+error: something's wrong
+ --> a.fig:1:10
+  |
+1 |     let a = true + 1
+  |             ^^^^
+");
+    assert_eq!(render("let 名前 = true + 1", "true", &[("名前", "named here")]), "\
+error: something's wrong
+ --> a.fig:1:10
+  |
+1 | let 名前 = true + 1
+  |            ^^^^
+  |     ---- named here
+");
+}
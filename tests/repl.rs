@@ -0,0 +1,51 @@
+//! `fig repl`, fed one line at a time.
+
+mod common;
+
+use common::fig_with_input;
+
+/// Types `lines` into the prompt, giving back what it printed and what it
+/// complained about.
+fn repl(lines: &str) -> (String, String) {
+    let out = fig_with_input("repl", lines.as_bytes());
+    assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr));
+    (String::from_utf8(out.stdout).unwrap(), String::from_utf8(out.stderr).unwrap())
+}
+
+#[test]
+fn values_are_shown_with_their_types() {
+    let (out, err) = repl("let x = 2\nx * 3\nfn f(a: i64) -> i64 {\nreturn a + 1\n}\nf(x)\n\"a\"\n");
+    assert!(err.is_empty(), "{}", err);
+    assert!(out.contains("6 : i64"), "{}", out);
+    assert!(out.contains("3 : i64"), "{}", out);
+    assert!(out.contains("\"a\" : string"), "{}", out);
+}
+
+#[test]
+fn lines_that_dont_parse_are_forgotten() {
+    let (_, err) = repl("let x = 1 +\nx\n");
+    assert!(err.contains("cannot find `x` in this scope"), "{}", err);
+}
+
+#[test]
+fn lines_that_dont_check_are_forgotten() {
+    let (_, err) = repl("let x: i64 = true\nx\n");
+    assert!(err.contains("error[E0302]"), "{}", err);
+    assert!(err.contains("cannot find `x` in this scope"), "{}", err);
+    assert!(!err.contains("before it was given a value"), "{}", err);
+}
+
+#[test]
+fn lines_that_fail_are_forgotten() {
+    let (out, err) = repl("let x = 1\nlet x = 1 / 0\nx\n");
+    assert!(err.contains("attempt to divide by zero"), "{}", err);
+    // the `x` from before is back
+    assert!(out.contains("1 : i64"), "{}", out);
+}
+
+#[test]
+fn errors_show_the_line_as_it_was_typed() {
+    let (_, err) = repl("let y = true + 1\n");
+    assert!(err.contains("1 | let y = true + 1\n"), "{}", err);
+    assert!(!err.contains(';'), "{}", err);
+}
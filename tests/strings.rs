@@ -0,0 +1,32 @@
+//! Strings, their escapes, and the code that can go in them.
+
+mod common;
+
+use common::{codes, eval};
+use fig::fe::codes::*;
+
+/// How the evaluator shows a string holding `s`.
+fn string(s: &str) -> String {
+    format!("{:?}", s)
+}
+
+#[test]
+fn escapes() {
+    assert_eq!(eval(r#"return "a\tb\n\"q\" \\ \u{3c0}""#), string("a\tb\n\"q\" \\ π"));
+    assert_eq!(eval(r#"return "\{not} code\}""#), string("{not} code}"));
+    assert_eq!(codes(r#"return "bad \q""#), [BAD_ESCAPE]);
+}
+
+#[test]
+fn interpolation() {
+    assert_eq!(eval("let n = 2\nreturn \"n is {n}, twice is {n * 2}\""), string("n is 2, twice is 4"));
+    assert_eq!(eval(r#"return "{1 + 1} {true} {"inner {3}"}""#), string("2 true inner 3"));
+    assert_eq!(eval("return 'single {1}'"), string("single 1"));
+}
+
+#[test]
+fn mistakes_inside_interpolation() {
+    assert_eq!(codes(r#"return "open {1""#), [UNBALANCED_DELIMITER]);
+    assert_eq!(codes(r#"return "{missing}""#), [UNRESOLVED_NAME]);
+    assert_eq!(codes(r#"return "{1 +}""#), [EXPECTED_EXPRESSION]);
+}
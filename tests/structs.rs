@@ -0,0 +1,54 @@
+//! Structs, their fields, and changing them.
+
+mod common;
+
+use common::{codes, eval};
+use fig::fe::codes::*;
+
+#[test]
+fn literals_and_fields() {
+    let source = "struct P { x: i64, y: i64 }\nlet p = P { y: 2, x: 1 }\nreturn p.x * 10 + p.y";
+    assert_eq!(eval(source), "12");
+    assert_eq!(eval("struct Marker\nlet m = Marker\nreturn m == Marker"), "true");
+    let source = "struct L { a: i64 }\nstruct O { l: L, k: i64 }\nlet o = O { l: L { a: 3 }, k: 4 }\nreturn o.l.a + o.k";
+    assert_eq!(eval(source), "7");
+}
+
+#[test]
+fn structs_are_copied() {
+    let source = "struct P { x: i64 }\nlet a = P { x: 1 }\nlet b = a\nb.x = 5\nreturn a.x * 10 + b.x";
+    assert_eq!(eval(source), "15");
+    let source = "struct P { x: i64 }\nfn bump(p: P) -> i64 {\np.x += 1\nreturn p.x\n}\nlet a = P { x: 1 }\nreturn bump(a) * 10 + a.x";
+    assert_eq!(eval(source), "21");
+    let source = "struct P { x: i64 }\nreturn P { x: 1 } == P { x: 1 } && P { x: 1 } != P { x: 2 }";
+    assert_eq!(eval(source), "true");
+}
+
+#[test]
+fn bad_structs_are_reported() {
+    assert_eq!(codes("struct P { x: i64 }\nlet p = P { x: 1 }\nreturn p.z"), [UNKNOWN_FIELD]);
+    assert_eq!(codes("struct P { x: i64 }\nlet p = P { x: 1, x: 2 }"), [BAD_STRUCT_LITERAL]);
+    assert_eq!(codes("struct P { x: i64 }\nlet p = P { x: true }"), [BAD_STRUCT_LITERAL]);
+    assert_eq!(codes("struct P { x: i64 }\nlet p = P { }"), [BAD_STRUCT_LITERAL]);
+    assert_eq!(codes("struct P { x: Q }"), [UNKNOWN_TYPE]);
+    assert_eq!(codes("struct N { n: N }"), [RECURSIVE_STRUCT]);
+    assert_eq!(codes("struct A { b: B }\nstruct B { a: A }"), [RECURSIVE_STRUCT, RECURSIVE_STRUCT]);
+}
+
+#[test]
+fn fields_can_be_assigned_to() {
+    let source = "struct P { x: i64, y: i64 }\nlet p = P { x: 1, y: 2 }\np.x = 10\np.y += 5\nreturn p.x + p.y";
+    assert_eq!(eval(source), "17");
+    let source = "struct L { a: i64 }\nstruct O { l: L }\nlet o = O { l: L { a: 3 } }\no.l.a += 10\nreturn o.l.a";
+    assert_eq!(eval(source), "13");
+}
+
+#[test]
+fn only_places_can_be_assigned_to() {
+    assert_eq!(codes("1 = 2"), [NOT_ASSIGNABLE]);
+    assert_eq!(codes("let a = 1\n(a + 1) = 2"), [NOT_ASSIGNABLE]);
+    assert_eq!(codes("let a = 1\na + 1 += 2"), [NOT_ASSIGNABLE]);
+    assert_eq!(codes("fn f() -> i64 { return 1 }\nf() = 2"), [NOT_ASSIGNABLE]);
+    let source = "struct P { x: i64 }\nfn f() -> P { return P { x: 1 } }\nf().x = 2";
+    assert_eq!(codes(source), [NOT_ASSIGNABLE]);
+}
@@ -0,0 +1,44 @@
+//! `fig test`, run the way it would be from the command line.
+
+mod common;
+
+use common::fig;
+
+/// Runs `fig test` on `source`, giving back whether it passed and what it
+/// printed.
+fn fig_test(source: &str) -> (bool, String) {
+    let out = fig("test", source, &[]);
+    let printed = String::from_utf8_lossy(&out.stdout).into_owned() + &String::from_utf8_lossy(&out.stderr);
+    (out.status.success(), printed)
+}
+
+#[test]
+fn tests_pass_and_fail() {
+    let source = "fn double(n: i64) -> i64 { return n * 2 }\n\
+        @test fn doubles() { assert(double(2) == 4) }\n\
+        @test fn wrong() { assert(double(2) == 5) }\n";
+    let (passed, out) = fig_test(source);
+    assert!(!passed);
+    assert!(out.contains("test doubles ... ok"), "{}", out);
+    assert!(out.contains("test wrong ... FAILED"), "{}", out);
+    assert!(out.contains("test result: FAILED. 1 passed; 1 failed"), "{}", out);
+}
+
+#[test]
+fn tests_can_use_the_functions_and_structs_around_them() {
+    let source = "@test fn later() { let p = make(3); assert(p.x == 3) }\n\
+        struct P { x: i64 }\n\
+        fn make(x: i64) -> P { return P { x: x } }\n";
+    let (passed, out) = fig_test(source);
+    assert!(passed, "{}", out);
+    assert!(out.contains("test result: ok. 1 passed; 0 failed"), "{}", out);
+}
+
+#[test]
+fn tests_cant_see_variables_from_outside() {
+    let (passed, out) = fig_test("let x = 1\n@test fn t() { assert(x == 1) }\n");
+    assert!(!passed);
+    // it's caught before anything runs
+    assert!(out.contains("error[E0200]") && !out.contains("running"), "{}", out);
+    assert!(out.contains("functions can't see variables from outside"), "{}", out);
+}
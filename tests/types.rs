@@ -0,0 +1,52 @@
+//! The type checker, which reports every mistake it finds instead of stopping
+//! at the first.
+
+mod common;
+
+use common::{codes, errors, eval};
+use fig::fe::codes::*;
+
+#[test]
+fn mistakes_get_their_codes() {
+    assert_eq!(codes("return 1 + true"), [MISMATCHED_OPERANDS]);
+    assert_eq!(codes("return \"a\" * \"b\""), [WRONG_OPERAND_TYPE]);
+    assert_eq!(codes("let a: i64 = true\nreturn a"), [MISMATCHED_DECLARATION]);
+    assert_eq!(codes("let a: number = 1\nreturn a"), [UNKNOWN_TYPE]);
+    assert_eq!(codes("return if 1 { 2 } else { 3 }"), [NON_BOOL_CONDITION]);
+    assert_eq!(codes("assert(1)"), [BAD_BUILTIN_CALL]);
+    assert_eq!(codes("let a = 1\nreturn a()"), [NOT_CALLABLE]);
+}
+
+#[test]
+fn every_mistake_is_reported_in_order() {
+    let found = codes("let a = 1 + true\nlet b: i64 = false\nreturn if 1 { a } else { b }");
+    assert_eq!(found, [MISMATCHED_OPERANDS, MISMATCHED_DECLARATION, NON_BOOL_CONDITION]);
+}
+
+#[test]
+fn one_mistake_doesnt_set_off_others() {
+    // nothing knows the type of `missing`, so nothing else complains about it
+    assert_eq!(codes("let a = missing + 1\nlet b = a * 2\nreturn b"), [UNRESOLVED_NAME]);
+}
+
+#[test]
+fn messages_name_the_types() {
+    let found = errors("return 1 + true");
+    assert_eq!(found[0].1, "`+` needs both sides to have the same type, but they're `i64` and `bool`");
+}
+
+#[test]
+fn both_branches_of_an_if_give_back_the_same_type() {
+    assert_eq!(eval("let x = if false { <- 1 } else { <- 2 }\nreturn x + 1"), "3");
+    let found = errors("let x = if false { <- 1 } else { <- \"a\" }\nlet y = x + 1");
+    assert_eq!(found, [(MISMATCHED_BRANCHES, "expected `i64`, but the `else` gives back `string`".to_string())]);
+    assert_eq!(codes("fn f() -> i64 { <- if true { <- 1 } else { <- \"a\" } }"), [MISMATCHED_BRANCHES]);
+    assert_eq!(codes("let x = if true { <- 1 } else if false { <- 2 } else { <- true }"), [MISMATCHED_BRANCHES]);
+}
+
+#[test]
+fn an_if_without_an_else_gives_back_unit() {
+    let found = errors("let x = if true { <- 1 }\nlet y = x + 1");
+    assert_eq!(found, [(MISMATCHED_OPERANDS, "`+` needs both sides to have the same type, but they're `unit` and `i64`".to_string())]);
+    assert_eq!(codes("fn f() -> i64 { <- if true { <- 1 } }"), [MISMATCHED_RETURN]);
+}